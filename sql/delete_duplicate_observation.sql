@@ -0,0 +1,10 @@
+DELETE FROM 
+	observation
+WHERE 
+	location_id = :location_id
+AND
+	issue_time BETWEEN :start AND :end
+AND
+	issue_time != :issue_time
+AND
+	json_extract(data, '$.observation_time') = :observation_time
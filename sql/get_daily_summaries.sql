@@ -0,0 +1,22 @@
+SELECT 
+	date,
+	min_temp,
+	min_temp_time,
+	max_temp,
+	max_temp_time,
+	rain,
+	max_gust,
+	max_gust_direction,
+	max_gust_time,
+	mean_humidity,
+	observation_count
+FROM 
+	daily_summary 
+WHERE 
+	location_id = (?)
+AND
+	date >= (?)
+AND
+	date <= (?)
+ORDER BY 
+	date ASC;
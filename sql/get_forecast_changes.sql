@@ -0,0 +1,18 @@
+SELECT 
+	issue_time,
+	previous_issue_time,
+	date,
+	kind,
+	old,
+	new,
+	difference
+FROM 
+	forecast_change 
+WHERE 
+	location_id = (?)
+AND
+	issue_time >= (?)
+AND
+	issue_time <= (?)
+ORDER BY 
+	issue_time ASC, date ASC, id ASC;
@@ -0,0 +1,12 @@
+SELECT 
+	data
+FROM 
+	observation 
+WHERE 
+	location_id = (?)
+AND
+	issue_time >= (?)
+AND
+	issue_time <= (?)
+ORDER BY 
+	issue_time ASC;
@@ -0,0 +1,9 @@
+INSERT OR IGNORE INTO daily_forecast (
+	location_id,
+	issue_time,
+	data)
+VALUES (
+	:location_id,
+	:issue_time,
+	:data
+)
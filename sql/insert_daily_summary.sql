@@ -0,0 +1,27 @@
+INSERT OR REPLACE INTO daily_summary (
+	location_id,
+	date,
+	min_temp,
+	min_temp_time,
+	max_temp,
+	max_temp_time,
+	rain,
+	max_gust,
+	max_gust_direction,
+	max_gust_time,
+	mean_humidity,
+	observation_count)
+VALUES (
+	:location_id,
+	:date,
+	:min_temp,
+	:min_temp_time,
+	:max_temp,
+	:max_temp_time,
+	:rain,
+	:max_gust,
+	:max_gust_direction,
+	:max_gust_time,
+	:mean_humidity,
+	:observation_count
+)
@@ -0,0 +1,19 @@
+INSERT OR IGNORE INTO forecast_change (
+	location_id,
+	issue_time,
+	previous_issue_time,
+	date,
+	kind,
+	old,
+	new,
+	difference)
+VALUES (
+	:location_id,
+	:issue_time,
+	:previous_issue_time,
+	:date,
+	:kind,
+	:old,
+	:new,
+	:difference
+)
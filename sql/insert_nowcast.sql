@@ -0,0 +1,9 @@
+INSERT OR REPLACE INTO nowcast (
+	location_id,
+	time,
+	data)
+VALUES (
+	:location_id,
+	:time,
+	:data
+)
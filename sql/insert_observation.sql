@@ -0,0 +1,10 @@
+INSERT INTO observation (
+	location_id,
+	issue_time,
+	data)
+VALUES (
+	:location_id,
+	:issue_time,
+	:data
+)
+ON CONFLICT(location_id, issue_time) DO UPDATE SET data = excluded.data
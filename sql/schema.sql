@@ -65,3 +65,69 @@ CREATE TABLE IF NOT EXISTS radar_feature_layer (
     filename TEXT NOT NULL UNIQUE,
     FOREIGN KEY(radar_id) REFERENCES radar(id)
 );
+
+CREATE TABLE IF NOT EXISTS observation (
+    id INTEGER PRIMARY KEY,
+    location_id TEXT NOT NULL,
+    issue_time INT NOT NULL,
+    data TEXT NOT NULL,
+    UNIQUE(location_id, issue_time),
+    FOREIGN KEY(location_id) REFERENCES location(id)
+);
+
+CREATE TABLE IF NOT EXISTS daily_summary (
+    id INTEGER PRIMARY KEY,
+    location_id TEXT NOT NULL,
+    date TEXT NOT NULL,
+    min_temp REAL,
+    min_temp_time INT,
+    max_temp REAL,
+    max_temp_time INT,
+    rain REAL,
+    max_gust INT,
+    max_gust_direction TEXT,
+    max_gust_time INT,
+    mean_humidity REAL,
+    observation_count INT NOT NULL,
+    UNIQUE(location_id, date),
+    FOREIGN KEY(location_id) REFERENCES location(id)
+);
+
+CREATE TABLE IF NOT EXISTS daily_forecast (
+    id INTEGER PRIMARY KEY,
+    location_id TEXT NOT NULL,
+    issue_time INT NOT NULL,
+    data TEXT NOT NULL,
+    UNIQUE(location_id, issue_time),
+    FOREIGN KEY(location_id) REFERENCES location(id)
+);
+
+CREATE TABLE IF NOT EXISTS hourly_forecast (
+    id INTEGER PRIMARY KEY,
+    location_id TEXT NOT NULL,
+    issue_time INT NOT NULL,
+    data TEXT NOT NULL,
+    UNIQUE(location_id, issue_time),
+    FOREIGN KEY(location_id) REFERENCES location(id)
+);
+
+CREATE TABLE IF NOT EXISTS forecast_change (
+    id INTEGER PRIMARY KEY,
+    location_id TEXT NOT NULL,
+    issue_time INT NOT NULL,
+    previous_issue_time INT NOT NULL,
+    date INT NOT NULL,
+    kind TEXT NOT NULL,
+    old TEXT,
+    new TEXT,
+    difference REAL,
+    UNIQUE(location_id, issue_time, date, kind),
+    FOREIGN KEY(location_id) REFERENCES location(id)
+);
+
+CREATE TABLE IF NOT EXISTS nowcast (
+    location_id TEXT PRIMARY KEY,
+    time INT NOT NULL,
+    data TEXT NOT NULL,
+    FOREIGN KEY(location_id) REFERENCES location(id)
+);
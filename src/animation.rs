@@ -0,0 +1,777 @@
+use anyhow::{anyhow, Context, Result};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use strum_macros::AsRefStr;
+
+/// Animated formats that radar loops can be exported as, in addition to APNG
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AsRefStr, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum AnimationFormat {
+    Gif,
+    /// Lossless animated WebP
+    Webp,
+    /// H.264 video, encoded by ffmpeg
+    Mp4,
+    /// VP9 video, encoded by ffmpeg
+    Webm,
+}
+
+/// Write the frames as an animation with each frame shown for the delay. All frames must be the
+/// same size
+pub fn write_animation(
+    path: &Path,
+    frames: &[RgbaImage],
+    delay_ms: u16,
+    format: AnimationFormat,
+) -> Result<()> {
+    if frames.is_empty() {
+        return Err(anyhow!("No frames to write to {}", path.display()));
+    }
+    match format {
+        AnimationFormat::Gif => write_gif(path, frames, delay_ms)?,
+        AnimationFormat::Webp => fs::write(path, encode_webp(frames, delay_ms))?,
+        AnimationFormat::Mp4 | AnimationFormat::Webm => ffmpeg(path, frames, delay_ms, format)?,
+    }
+    Ok(())
+}
+
+/// An image with at most 256 colours, reduced with median cut if it has more
+pub struct IndexedImage {
+    pub palette: Vec<[u8; 4]>,
+    pub indices: Vec<u8>,
+}
+
+impl IndexedImage {
+    pub fn new(image: &RgbaImage) -> Self {
+        let mut counts: HashMap<[u8; 4], u32> = HashMap::new();
+        for pixel in image.pixels() {
+            *counts.entry(pixel.0).or_default() += 1;
+        }
+        let palette = if counts.len() <= 256 {
+            let mut colours: Vec<[u8; 4]> = counts.into_keys().collect();
+            colours.sort_unstable();
+            colours
+        } else {
+            median_cut(counts.into_iter().collect(), 256)
+        };
+        let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+        let indices = image
+            .pixels()
+            .map(|pixel| {
+                *lookup
+                    .entry(pixel.0)
+                    .or_insert_with(|| nearest(&palette, pixel.0))
+            })
+            .collect();
+        Self { palette, indices }
+    }
+}
+
+fn nearest(palette: &[[u8; 4]], colour: [u8; 4]) -> u8 {
+    let distance = |entry: &[u8; 4]| -> u32 {
+        (0..4)
+            .map(|i| (entry[i] as i32 - colour[i] as i32).pow(2) as u32)
+            .sum()
+    };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap_or(0) as u8
+}
+
+/// Repeatedly split the box of colours with the widest channel at its weighted median, then
+/// average each box
+fn median_cut(colours: Vec<([u8; 4], u32)>, max_colours: usize) -> Vec<[u8; 4]> {
+    let widest = |colours: &[([u8; 4], u32)]| -> (usize, u8) {
+        (0..4)
+            .map(|channel| {
+                let values = colours.iter().map(|(c, _)| c[channel]);
+                let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+                (channel, range)
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap_or((0, 0))
+    };
+    let mut boxes = vec![colours];
+    while boxes.len() < max_colours {
+        let Some((i, (channel, _))) = boxes
+            .iter()
+            .map(|b| widest(b))
+            .enumerate()
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range)
+        else {
+            break;
+        };
+        let mut colours = boxes.swap_remove(i);
+        colours.sort_unstable_by_key(|(c, _)| c[channel]);
+        let total: u64 = colours.iter().map(|(_, n)| *n as u64).sum();
+        let mut seen = 0;
+        let mut split = colours.len() / 2;
+        for (j, (_, n)) in colours.iter().enumerate() {
+            seen += *n as u64;
+            if seen * 2 >= total {
+                split = j + 1;
+                break;
+            }
+        }
+        let rest = colours.split_off(split.clamp(1, colours.len() - 1));
+        boxes.push(colours);
+        boxes.push(rest);
+    }
+    boxes
+        .iter()
+        .map(|colours| {
+            let total: u64 = colours.iter().map(|(_, n)| *n as u64).sum();
+            let mut average = [0; 4];
+            for (channel, value) in average.iter_mut().enumerate() {
+                let sum: u64 = colours
+                    .iter()
+                    .map(|(c, n)| c[channel] as u64 * *n as u64)
+                    .sum();
+                *value = ((sum + total / 2) / total.max(1)) as u8;
+            }
+            average
+        })
+        .collect()
+}
+
+/// Packs values least significant bit first, as WebP expects
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u8) {
+        self.buffer |= (value as u64) << self.bits;
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Frames with 256 colours or less keep them exactly, otherwise they're quantised separately
+fn write_gif(path: &Path, frames: &[RgbaImage], delay_ms: u16) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = GifEncoder::new_with_speed(file, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(delay_ms.into(), 1);
+    encoder.encode_frames(
+        frames
+            .iter()
+            .map(|frame| Frame::from_parts(frame.clone(), 0, 0, delay)),
+    )?;
+    Ok(())
+}
+
+fn riff_chunk(output: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    output.extend(fourcc);
+    output.extend((data.len() as u32).to_le_bytes());
+    output.extend(data);
+    if data.len() % 2 == 1 {
+        output.push(0);
+    }
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+/// An animated WebP with each frame losslessly encoded
+fn encode_webp(frames: &[RgbaImage], delay_ms: u16) -> Vec<u8> {
+    let (width, height) = frames[0].dimensions();
+    let has_alpha = frames.iter().any(|f| f.pixels().any(|p| p[3] < 255));
+    let mut body = b"WEBP".to_vec();
+
+    let mut header = vec![0x02 | if has_alpha { 0x10 } else { 0 }, 0, 0, 0];
+    header.extend(u24(width - 1));
+    header.extend(u24(height - 1));
+    riff_chunk(&mut body, b"VP8X", &header);
+    // Background colour and loop forever
+    riff_chunk(&mut body, b"ANIM", &[255, 255, 255, 255, 0, 0]);
+    for frame in frames {
+        let mut data = Vec::new();
+        data.extend(u24(0));
+        data.extend(u24(0));
+        data.extend(u24(width - 1));
+        data.extend(u24(height - 1));
+        data.extend(u24(delay_ms.into()));
+        // Replace the previous frame rather than blending with it
+        data.push(0x02);
+        riff_chunk(&mut data, b"VP8L", &encode_vp8l(frame));
+        riff_chunk(&mut body, b"ANMF", &data);
+    }
+
+    let mut output = b"RIFF".to_vec();
+    output.extend((body.len() as u32).to_le_bytes());
+    output.extend(body);
+    output
+}
+
+/// WebP lossless with a palette when there are 256 colours or less, and back references to the
+/// pixel to the left or above
+fn encode_vp8l(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let argb: Vec<u32> = image
+        .pixels()
+        .map(|p| u32::from_be_bytes([p[3], p[0], p[1], p[2]]))
+        .collect();
+    let mut writer = BitWriter::default();
+    writer.write(0x2F, 8);
+    writer.write(width - 1, 14);
+    writer.write(height - 1, 14);
+    writer.write(u32::from(argb.iter().any(|p| p >> 24 != 0xFF)), 1);
+    writer.write(0, 3);
+
+    let mut palette: Vec<u32> = argb.clone();
+    palette.sort_unstable();
+    palette.dedup();
+    let (pixels, packed_width) = if palette.len() <= 256 {
+        // Colour indexing transform
+        writer.write(1, 1);
+        writer.write(3, 2);
+        writer.write(palette.len() as u32 - 1, 8);
+        // The palette is stored as the difference from the previous entry
+        let deltas: Vec<u32> = palette
+            .iter()
+            .scan(0u32, |previous, &colour| {
+                let delta = u32::from_le_bytes(std::array::from_fn(|i| {
+                    colour.to_le_bytes()[i].wrapping_sub(previous.to_le_bytes()[i])
+                }));
+                *previous = colour;
+                Some(delta)
+            })
+            .collect();
+        write_entropy_image(&mut writer, &deltas, palette.len() as u32, false);
+
+        // Small palettes pack several pixels into each byte
+        let width_bits = match palette.len() {
+            0..=2 => 3,
+            3..=4 => 2,
+            5..=16 => 1,
+            _ => 0,
+        };
+        let per_byte = 1 << width_bits;
+        let bits_per_pixel = 8 >> width_bits;
+        let packed_width = width.div_ceil(per_byte);
+        let index: HashMap<u32, u32> = palette
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (*c, i as u32))
+            .collect();
+        let mut packed = Vec::with_capacity((packed_width * height) as usize);
+        for row in argb.chunks(width as usize) {
+            for group in row.chunks(per_byte as usize) {
+                let green = group.iter().enumerate().fold(0, |acc, (i, c)| {
+                    acc | index[c] << (i as u32 * bits_per_pixel)
+                });
+                packed.push(green << 8);
+            }
+        }
+        (packed, packed_width)
+    } else {
+        (argb, width)
+    };
+    writer.write(0, 1);
+    write_entropy_image(&mut writer, &pixels, packed_width, true);
+    writer.finish()
+}
+
+enum Token {
+    Literal(u32),
+    Copy { length: u32, distance_code: u32 },
+}
+
+/// Split a value into a prefix symbol, and the number and value of the extra bits
+fn prefix_encode(value: u32) -> (u32, u8, u32) {
+    let v = value - 1;
+    if v < 4 {
+        return (v, 0, 0);
+    }
+    let highest = 31 - v.leading_zeros();
+    let second = (v >> (highest - 1)) & 1;
+    let extra_bits = highest - 1;
+    (
+        2 * highest + second,
+        extra_bits as u8,
+        v & ((1 << extra_bits) - 1),
+    )
+}
+
+/// Greedily copy runs matching the pixels to the left (distance code 2) or above (code 1)
+fn backward_references(pixels: &[u32], width: usize) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < pixels.len() {
+        let match_length = |offset: usize| {
+            if i < offset {
+                return 0;
+            }
+            let max = (pixels.len() - i).min(4096);
+            (0..max)
+                .take_while(|&j| pixels[i + j] == pixels[i + j - offset])
+                .count()
+        };
+        let left = match_length(1);
+        let above = match_length(width);
+        let (length, distance_code) = if above >= left { (above, 1) } else { (left, 2) };
+        if length >= 3 {
+            tokens.push(Token::Copy {
+                length: length as u32,
+                distance_code,
+            });
+            i += length;
+        } else {
+            tokens.push(Token::Literal(pixels[i]));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+fn write_entropy_image(writer: &mut BitWriter, pixels: &[u32], width: u32, is_main: bool) {
+    // No colour cache
+    writer.write(0, 1);
+    if is_main {
+        // A single set of prefix codes for the whole image
+        writer.write(0, 1);
+    }
+    let tokens = backward_references(pixels, width as usize);
+    let mut green = vec![0; 256 + 24];
+    let mut red = vec![0; 256];
+    let mut blue = vec![0; 256];
+    let mut alpha = vec![0; 256];
+    let mut distance = vec![0; 40];
+    for token in &tokens {
+        match token {
+            Token::Literal(argb) => {
+                let [b, g, r, a] = argb.to_le_bytes();
+                green[g as usize] += 1;
+                red[r as usize] += 1;
+                blue[b as usize] += 1;
+                alpha[a as usize] += 1;
+            }
+            Token::Copy {
+                length,
+                distance_code,
+            } => {
+                green[256 + prefix_encode(*length).0 as usize] += 1;
+                distance[prefix_encode(*distance_code).0 as usize] += 1;
+            }
+        }
+    }
+    let codes: Vec<PrefixCode> = [&green, &red, &blue, &alpha, &distance]
+        .into_iter()
+        .map(|counts| PrefixCode::write(writer, counts))
+        .collect();
+    for token in &tokens {
+        match token {
+            Token::Literal(argb) => {
+                let [b, g, r, a] = argb.to_le_bytes();
+                codes[0].write_symbol(writer, g as usize);
+                codes[1].write_symbol(writer, r as usize);
+                codes[2].write_symbol(writer, b as usize);
+                codes[3].write_symbol(writer, a as usize);
+            }
+            Token::Copy {
+                length,
+                distance_code,
+            } => {
+                let (symbol, bits, extra) = prefix_encode(*length);
+                codes[0].write_symbol(writer, 256 + symbol as usize);
+                writer.write(extra, bits);
+                let (symbol, bits, extra) = prefix_encode(*distance_code);
+                codes[4].write_symbol(writer, symbol as usize);
+                writer.write(extra, bits);
+            }
+        }
+    }
+}
+
+/// A canonical prefix (Huffman) code. Codes with only one symbol take no bits
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+}
+
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+impl PrefixCode {
+    fn new(lengths: Vec<u8>) -> Self {
+        let mut codes = vec![0; lengths.len()];
+        let used = lengths.iter().filter(|l| **l > 0).count();
+        if used > 1 {
+            let max = *lengths.iter().max().unwrap_or(&0) as usize;
+            let mut count = vec![0u16; max + 1];
+            for &length in &lengths {
+                count[length as usize] += 1;
+            }
+            count[0] = 0;
+            let mut next = vec![0u16; max + 1];
+            for bits in 1..=max {
+                next[bits] = (next[bits - 1] + count[bits - 1]) << 1;
+            }
+            for (symbol, &length) in lengths.iter().enumerate() {
+                if length > 0 {
+                    codes[symbol] = next[length as usize];
+                    next[length as usize] += 1;
+                }
+            }
+        }
+        let lengths = if used > 1 {
+            lengths
+        } else {
+            vec![0; lengths.len()]
+        };
+        Self { lengths, codes }
+    }
+
+    /// Choose a code for the symbol counts and write it in the simple or normal form
+    fn write(writer: &mut BitWriter, counts: &[u32]) -> Self {
+        let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+        if used.len() <= 2 && used.iter().all(|&s| s < 256) {
+            writer.write(1, 1);
+            let first = used.first().copied().unwrap_or(0);
+            writer.write(used.len().saturating_sub(1) as u32, 1);
+            if first < 2 {
+                writer.write(0, 1);
+                writer.write(first as u32, 1);
+            } else {
+                writer.write(1, 1);
+                writer.write(first as u32, 8);
+            }
+            let mut lengths = vec![0; counts.len()];
+            if let Some(&second) = used.get(1) {
+                writer.write(second as u32, 8);
+                lengths[first] = 1;
+                lengths[second] = 1;
+            }
+            return Self::new(lengths);
+        }
+
+        let lengths = code_lengths(counts, 15);
+        // Run lengths of zeros use symbols 17 (3-10) and 18 (11-138)
+        let mut tokens: Vec<(usize, u8, u32)> = Vec::new();
+        let mut i = 0;
+        while i < lengths.len() {
+            if lengths[i] > 0 {
+                tokens.push((lengths[i] as usize, 0, 0));
+                i += 1;
+                continue;
+            }
+            let run = lengths[i..].iter().take_while(|l| **l == 0).count();
+            let mut remaining = run;
+            while remaining > 0 {
+                if remaining >= 11 {
+                    let n = remaining.min(138);
+                    tokens.push((18, 7, n as u32 - 11));
+                    remaining -= n;
+                } else if remaining >= 3 {
+                    tokens.push((17, 3, remaining as u32 - 3));
+                    remaining = 0;
+                } else {
+                    tokens.push((0, 0, 0));
+                    remaining -= 1;
+                }
+            }
+            i += run;
+        }
+        let mut token_counts = [0; 19];
+        for (symbol, _, _) in &tokens {
+            token_counts[*symbol] += 1;
+        }
+        // The lengths are written even if there's only one symbol, which then takes no bits
+        let length_lengths = code_lengths(&token_counts, 7);
+        let count = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&s| length_lengths[s] > 0)
+            .map_or(4, |i| (i + 1).max(4));
+
+        writer.write(0, 1);
+        writer.write(count as u32 - 4, 4);
+        for &symbol in &CODE_LENGTH_ORDER[..count] {
+            writer.write(length_lengths[symbol] as u32, 3);
+        }
+        let length_code = Self::new(length_lengths);
+        // Every symbol's length is written
+        writer.write(0, 1);
+        for (symbol, bits, extra) in tokens {
+            length_code.write_symbol(writer, symbol);
+            writer.write(extra, bits);
+        }
+        Self::new(lengths)
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, symbol: usize) {
+        let length = self.lengths[symbol];
+        if length == 0 {
+            return;
+        }
+        // Codes are read starting from their most significant bit
+        let code = self.codes[symbol].reverse_bits() >> (16 - length);
+        writer.write(code as u32, length);
+    }
+}
+
+/// Huffman code lengths limited to the maximum, flattening the counts until they fit
+fn code_lengths(counts: &[u32], max_length: u8) -> Vec<u8> {
+    let mut counts = counts.to_vec();
+    loop {
+        let lengths = huffman_lengths(&counts);
+        if lengths.iter().all(|l| *l <= max_length) {
+            return lengths;
+        }
+        for count in counts.iter_mut().filter(|c| **c > 0) {
+            *count = (*count / 2).max(1);
+        }
+    }
+}
+
+fn huffman_lengths(counts: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0; counts.len()];
+    let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+    // Leaves are the used symbols, followed by the internal nodes as they're created
+    let mut parents = vec![0; used.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &symbol)| Reverse((counts[symbol] as u64, node)))
+        .collect();
+    while heap.len() > 1 {
+        let Reverse((a, left)) = heap.pop().unwrap();
+        let Reverse((b, right)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[left] = node;
+        parents[right] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+    for (leaf, &symbol) in used.iter().enumerate() {
+        let mut depth = 0;
+        let mut node = leaf;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            depth += 1;
+        }
+        lengths[symbol] = depth;
+    }
+    lengths
+}
+
+/// Pipe the raw frames to ffmpeg
+fn ffmpeg(path: &Path, frames: &[RgbaImage], delay_ms: u16, format: AnimationFormat) -> Result<()> {
+    let (width, height) = frames[0].dimensions();
+    let codec: &[&str] = match format {
+        AnimationFormat::Webm => &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "30"],
+        _ => &["-c:v", "libx264", "-movflags", "+faststart"],
+    };
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{width}x{height}")])
+        .args(["-framerate", &format!("1000/{delay_ms}")])
+        .args(["-i", "-"])
+        // Most players only support 4:2:0, which needs an even width and height
+        .args([
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .args(codec)
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run ffmpeg. Is it installed?")?;
+    let mut stdin = child.stdin.take().unwrap();
+    let written = frames
+        .iter()
+        .try_for_each(|frame| stdin.write_all(frame.as_raw()));
+    drop(stdin);
+    // If ffmpeg fails it closes the pipe, so its exit status explains the failed write
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg exited with {status}"));
+    }
+    Ok(written?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::webp::WebPDecoder;
+    use image::{AnimationDecoder, ImageFormat, Rgba};
+    use std::io::Cursor;
+
+    /// An image with roughly the requested number of colours, in runs and stripes so the
+    /// back references get used
+    fn test_image(colours: u32, alpha: bool) -> RgbaImage {
+        RgbaImage::from_fn(37, 23, |x, y| {
+            let i = ((x / 3 + y * 7) % colours.max(1)) as u8;
+            let a = if alpha && x % 5 == 0 { i } else { 255 };
+            Rgba([i.wrapping_mul(53), i.wrapping_mul(101), 255 - i, a])
+        })
+    }
+
+    fn decode_vp8l(image: &RgbaImage) -> RgbaImage {
+        let mut webp = b"WEBP".to_vec();
+        riff_chunk(&mut webp, b"VP8L", &encode_vp8l(image));
+        let mut riff = b"RIFF".to_vec();
+        riff.extend((webp.len() as u32).to_le_bytes());
+        riff.extend(webp);
+        image::load_from_memory_with_format(&riff, ImageFormat::WebP)
+            .unwrap()
+            .to_rgba8()
+    }
+
+    #[test]
+    fn vp8l_round_trip() {
+        // Covers each amount of pixel packing with a palette, and the image without one
+        for colours in [1, 2, 3, 4, 5, 16, 17, 256, 1000] {
+            for alpha in [false, true] {
+                let image = test_image(colours, alpha);
+                assert_eq!(
+                    decode_vp8l(&image),
+                    image,
+                    "{colours} colours, alpha {alpha}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn vp8l_many_symbols() {
+        // Every channel value is used so the prefix codes are written in full
+        let image = RgbaImage::from_fn(64, 64, |x, y| {
+            let i = (x * 7 + y * 131) as u8;
+            Rgba([
+                i,
+                i.wrapping_mul(3),
+                i.wrapping_add(y as u8),
+                255 - (x as u8),
+            ])
+        });
+        assert_eq!(decode_vp8l(&image), image);
+    }
+
+    #[test]
+    fn webp_animation() {
+        let frames: Vec<RgbaImage> = (1..4).map(|i| test_image(i * 5, i == 2)).collect();
+        let decoder = WebPDecoder::new(Cursor::new(encode_webp(&frames, 250))).unwrap();
+        let decoded = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), frames.len());
+        for (frame, expected) in decoded.iter().zip(&frames) {
+            assert_eq!(frame.delay().numer_denom_ms(), (250, 1));
+            assert_eq!(frame.buffer(), expected);
+        }
+    }
+
+    #[test]
+    fn gif_animation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("radar.gif");
+        let frames: Vec<RgbaImage> = (1..4).map(|i| test_image(i * 5, false)).collect();
+        write_animation(&path, &frames, 300, AnimationFormat::Gif).unwrap();
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let decoded = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), frames.len());
+        for (frame, expected) in decoded.iter().zip(&frames) {
+            assert_eq!(frame.delay().numer_denom_ms(), (300, 1));
+            assert_eq!(frame.buffer(), expected);
+        }
+    }
+
+    #[test]
+    fn prefix_encode_values() {
+        assert_eq!(prefix_encode(1), (0, 0, 0));
+        assert_eq!(prefix_encode(4), (3, 0, 0));
+        assert_eq!(prefix_encode(5), (4, 1, 0));
+        assert_eq!(prefix_encode(6), (4, 1, 1));
+        assert_eq!(prefix_encode(7), (5, 1, 0));
+        assert_eq!(prefix_encode(9), (6, 2, 0));
+        assert_eq!(prefix_encode(4096), (23, 10, 1023));
+        // Decoded the way the WebP lossless spec describes
+        for value in 1..=4096 {
+            let (prefix, bits, extra) = prefix_encode(value);
+            let decoded = if prefix < 4 {
+                prefix + 1
+            } else {
+                let extra_bits = (prefix - 2) >> 1;
+                assert_eq!(extra_bits, bits as u32);
+                ((2 + (prefix & 1)) << extra_bits) + extra + 1
+            };
+            assert_eq!(decoded, value);
+        }
+    }
+
+    fn kraft_sum(lengths: &[u8]) -> f64 {
+        lengths
+            .iter()
+            .filter(|l| **l > 0)
+            .map(|l| 0.5f64.powi(*l as i32))
+            .sum()
+    }
+
+    #[test]
+    fn code_lengths_complete() {
+        assert_eq!(code_lengths(&[1, 1, 2, 4], 15), [3, 3, 2, 1]);
+        assert_eq!(code_lengths(&[0, 5, 0], 15), [0, 1, 0]);
+        assert_eq!(code_lengths(&[3, 0, 3], 15), [1, 0, 1]);
+        let counts: Vec<u32> = (0..280).map(|i| (i * 37 % 101) as u32).collect();
+        let lengths = code_lengths(&counts, 15);
+        assert_eq!(kraft_sum(&lengths), 1.0);
+        for (count, length) in counts.iter().zip(&lengths) {
+            assert_eq!(*count == 0, *length == 0);
+        }
+    }
+
+    #[test]
+    fn code_lengths_limited() {
+        // Fibonacci counts make the deepest possible tree
+        let mut counts = vec![1u32, 1];
+        while counts.len() < 30 {
+            counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
+        }
+        assert!(huffman_lengths(&counts).iter().any(|l| *l > 15));
+        for max_length in [7, 15] {
+            let lengths = code_lengths(&counts, max_length);
+            assert!(lengths.iter().all(|l| (1..=max_length).contains(l)));
+            assert!(kraft_sum(&lengths) <= 1.0);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::daily::{DailyForecast, DailyForecastData, RainAmount};
+use crate::location::Location;
+use crate::units::Units;
+use crate::util::local_date;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, Display, EnumString};
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, AsRefStr, Display, EnumString, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ChangeKind {
+    MaxTemp,
+    MinTemp,
+    RainChance,
+    RainAmount,
+    ShortText,
+}
+
+/// A forecast value kept in metric units, and only converted and formatted when it's shown
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ChangeValue {
+    Number(f32),
+    RainRange { min: u16, max: Option<u16> },
+    Text(String),
+}
+
+impl ChangeValue {
+    /// Values saved by older versions were the formatted text, which is kept as it is
+    pub fn from_stored(value: &str) -> Self {
+        serde_json::from_str(value).unwrap_or_else(|_| Self::Text(value.to_string()))
+    }
+
+    pub fn format(&self, kind: ChangeKind, units: &Units) -> String {
+        match (self, kind) {
+            (Self::Number(temp), ChangeKind::MaxTemp | ChangeKind::MinTemp) => {
+                units.format_temp(*temp)
+            }
+            (Self::Number(value), _) => value.to_string(),
+            (Self::RainRange { min, max }, _) => units.format_rain_range(*min, *max),
+            (Self::Text(text), _) => text.clone(),
+        }
+    }
+}
+
+/// A difference in one day's forecast between two consecutive issues of the daily forecast
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ForecastChange {
+    pub issue_time: DateTime<Utc>,
+    pub previous_issue_time: DateTime<Utc>,
+    pub date: DateTime<Utc>,
+    pub kind: ChangeKind,
+    pub old: Option<ChangeValue>,
+    pub new: Option<ChangeValue>,
+    /// New minus old for numeric values
+    pub difference: Option<f32>,
+}
+
+impl ForecastChange {
+    /// Compare each day that appears in both forecasts
+    pub fn diff(previous: &DailyForecast, latest: &DailyForecast) -> Vec<Self> {
+        let mut changes = Vec::new();
+        for new_day in &latest.days {
+            let Some(old_day) = previous.days.iter().find(|d| d.date == new_day.date) else {
+                continue;
+            };
+            let mut push =
+                |kind, old: Option<ChangeValue>, new: Option<ChangeValue>, difference| {
+                    if old != new {
+                        changes.push(Self {
+                            issue_time: latest.issue_time,
+                            previous_issue_time: previous.issue_time,
+                            date: new_day.date,
+                            kind,
+                            old,
+                            new,
+                            difference,
+                        });
+                    }
+                };
+            let temp_difference = |old: Option<f32>, new: Option<f32>| Some(new? - old?);
+            push(
+                ChangeKind::MaxTemp,
+                old_day.temp_max.map(ChangeValue::Number),
+                new_day.temp_max.map(ChangeValue::Number),
+                temp_difference(old_day.temp_max, new_day.temp_max),
+            );
+            push(
+                ChangeKind::MinTemp,
+                old_day.temp_min.map(ChangeValue::Number),
+                new_day.temp_min.map(ChangeValue::Number),
+                temp_difference(old_day.temp_min, new_day.temp_min),
+            );
+            push(
+                ChangeKind::RainChance,
+                old_day.rain.chance.map(|c| ChangeValue::Number(c.into())),
+                new_day.rain.chance.map(|c| ChangeValue::Number(c.into())),
+                old_day
+                    .rain
+                    .chance
+                    .zip(new_day.rain.chance)
+                    .map(|(old, new)| new as f32 - old as f32),
+            );
+            push(
+                ChangeKind::RainAmount,
+                Some(rain_range_value(&old_day.rain.amount)),
+                Some(rain_range_value(&new_day.rain.amount)),
+                None,
+            );
+            push(
+                ChangeKind::ShortText,
+                short_text(old_day).map(ChangeValue::Text),
+                short_text(new_day).map(ChangeValue::Text),
+                None,
+            );
+        }
+        changes
+    }
+
+    /// A short description for notifications e.g. "Saturday max revised 31→36°"
+    pub fn description(&self, tz: Tz) -> String {
+        let day = self.date.with_timezone(&tz).format("%A");
+        let units = Units::default();
+        let format = |value: &Option<ChangeValue>| {
+            value
+                .as_ref()
+                .map_or("?".to_string(), |v| v.format(self.kind, &units))
+        };
+        let (old, new) = (format(&self.old), format(&self.new));
+        match self.kind {
+            ChangeKind::MaxTemp => format!("{day} max revised {old}→{new}°"),
+            ChangeKind::MinTemp => format!("{day} min revised {old}→{new}°"),
+            ChangeKind::RainChance => format!("{day} rain chance revised {old}→{new}%"),
+            ChangeKind::RainAmount => format!("{day} rain amount revised {old}→{new}"),
+            ChangeKind::ShortText => format!("{day} forecast changed from {old} to {new}"),
+        }
+    }
+
+    /// Whether the change is big enough to send a notification for
+    pub fn is_significant(&self, temp_threshold: f32, rain_chance_threshold: u8) -> bool {
+        let Some(difference) = self.difference else {
+            return false;
+        };
+        match self.kind {
+            ChangeKind::MaxTemp | ChangeKind::MinTemp => difference.abs() >= temp_threshold,
+            ChangeKind::RainChance => difference.abs() >= rain_chance_threshold as f32,
+            _ => false,
+        }
+    }
+}
+
+/// A flattened change used for tabular, CSV and JSON output
+#[derive(Debug, Serialize)]
+pub struct ForecastChangeRow<'a> {
+    pub location_id: &'a str,
+    pub issue_time: DateTime<Tz>,
+    pub previous_issue_time: DateTime<Tz>,
+    pub date: NaiveDate,
+    pub kind: ChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+    pub difference: Option<f32>,
+}
+
+impl<'a> ForecastChangeRow<'a> {
+    pub fn new(location: &'a Location, change: &'a ForecastChange, units: &Units) -> Self {
+        let convert =
+            |value: &Option<ChangeValue>| value.as_ref().map(|v| v.format(change.kind, units));
+        let difference = match change.kind {
+            ChangeKind::MaxTemp | ChangeKind::MinTemp => {
+                change.difference.map(|d| units.temp_difference(d))
+            }
+            _ => change.difference,
+        };
+        Self {
+            location_id: &location.id,
+            issue_time: change.issue_time.with_timezone(&location.timezone),
+            previous_issue_time: change.previous_issue_time.with_timezone(&location.timezone),
+            date: local_date(change.date, location.timezone),
+            kind: change.kind,
+            old: convert(&change.old),
+            new: convert(&change.new),
+            difference,
+        }
+    }
+}
+
+/// The forecast rain amount as shown in the daily table e.g. 1-5mm
+pub fn rain_range(amount: &RainAmount, units: &Units) -> String {
+    rain_range_value(amount).format(ChangeKind::RainAmount, units)
+}
+
+fn rain_range_value(amount: &RainAmount) -> ChangeValue {
+    match (amount.lower_range, amount.max) {
+        (Some(min), Some(max)) => ChangeValue::RainRange {
+            min,
+            max: Some(max),
+        },
+        _ => ChangeValue::RainRange { min: 0, max: None },
+    }
+}
+
+fn short_text(day: &DailyForecastData) -> Option<String> {
+    day.short_text.clone().filter(|t| !t.is_empty())
+}
@@ -0,0 +1,851 @@
+use crate::colour::Rgb;
+use crate::config::Config;
+use crate::daily::DailyForecastData;
+use crate::hourly::HourlyForecastData;
+use crate::location::Location;
+use crate::observation::Observation;
+use crate::units::Units;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use strum_macros::{AsRefStr, EnumIter};
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, AsRefStr, EnumIter, Deserialize, Serialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ChartKind {
+    /// Temperature, rain chance and wind for the hourly forecast
+    Meteogram,
+    /// The min and max temperature and rain chance for each day
+    Daily,
+    /// Observed temperature, humidity and rain
+    History,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AsRefStr, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ChartFormat {
+    Svg,
+    Png,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChartOptions {
+    pub chart_dir: PathBuf,
+    pub kinds: Vec<ChartKind>,
+    pub formats: Vec<ChartFormat>,
+    pub width: u32,
+    pub height: u32,
+    /// How many hours the meteogram covers
+    pub hours: usize,
+    /// How many days of observations the history chart covers
+    pub history_days: u32,
+    /// Render the charts from the monitor command whenever a new forecast is issued
+    pub render_on_update: bool,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            chart_dir: Config::default_dirs().state.join("charts"),
+            kinds: vec![ChartKind::Meteogram, ChartKind::Daily, ChartKind::History],
+            formats: vec![ChartFormat::Svg, ChartFormat::Png],
+            width: 1000,
+            height: 600,
+            hours: 48,
+            history_days: 3,
+            render_on_update: false,
+        }
+    }
+}
+
+const BACKGROUND: Rgb = Rgb(255, 255, 255);
+const FOREGROUND: Rgb = Rgb(40, 40, 40);
+const GRID: Rgb = Rgb(220, 220, 220);
+const NIGHT: Rgb = Rgb(236, 236, 244);
+const TEMP: Rgb = Rgb(215, 60, 60);
+const FEELS_LIKE: Rgb = Rgb(240, 150, 40);
+const MIN_TEMP: Rgb = Rgb(70, 120, 220);
+const RAIN: Rgb = Rgb(80, 150, 230);
+const HUMIDITY: Rgb = Rgb(60, 170, 120);
+const WIND: Rgb = Rgb(60, 150, 90);
+const GUST: Rgb = Rgb(150, 150, 150);
+
+#[derive(Clone, Copy, Debug)]
+pub enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug)]
+enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        fill: Rgb,
+    },
+    Line {
+        points: Vec<(f32, f32)>,
+        stroke: Rgb,
+        width: f32,
+    },
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        size: f32,
+        fill: Rgb,
+        anchor: Anchor,
+    },
+}
+
+/// A list of shapes that can be written as an SVG or rasterised to a PNG
+#[derive(Debug)]
+pub struct Drawing {
+    width: u32,
+    height: u32,
+    shapes: Vec<Shape>,
+}
+
+impl Drawing {
+    fn new(width: u32, height: u32) -> Self {
+        let mut drawing = Self {
+            width,
+            height,
+            shapes: Vec::new(),
+        };
+        drawing.rect(0.0, 0.0, width as f32, height as f32, BACKGROUND);
+        drawing
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, fill: Rgb) {
+        self.shapes.push(Shape::Rect {
+            x,
+            y,
+            width,
+            height,
+            fill,
+        });
+    }
+
+    fn line(&mut self, points: Vec<(f32, f32)>, stroke: Rgb, width: f32) {
+        if points.len() > 1 {
+            self.shapes.push(Shape::Line {
+                points,
+                stroke,
+                width,
+            });
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: impl Into<String>, size: f32, anchor: Anchor) {
+        self.shapes.push(Shape::Text {
+            x,
+            y,
+            text: text.into(),
+            size,
+            fill: FOREGROUND,
+            anchor,
+        });
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" font-family="sans-serif">"#,
+            self.width, self.height
+        );
+        svg.push('\n');
+        for shape in &self.shapes {
+            let _ = match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    fill,
+                } => writeln!(
+                    svg,
+                    r#"<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="{fill}"/>"#
+                ),
+                Shape::Line {
+                    points,
+                    stroke,
+                    width,
+                } => {
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                        .collect();
+                    writeln!(
+                        svg,
+                        r#"<polyline points="{}" fill="none" stroke="{stroke}" stroke-width="{width}" stroke-linejoin="round"/>"#,
+                        points.join(" ")
+                    )
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    text,
+                    size,
+                    fill,
+                    anchor,
+                } => {
+                    let anchor = match anchor {
+                        Anchor::Start => "start",
+                        Anchor::Middle => "middle",
+                        Anchor::End => "end",
+                    };
+                    writeln!(
+                        svg,
+                        r#"<text x="{x:.1}" y="{y:.1}" font-size="{size}" fill="{fill}" text-anchor="{anchor}">{}</text>"#,
+                        escape_xml(text)
+                    )
+                }
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn to_png(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width, self.height);
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    fill,
+                } => fill_rect(&mut image, *x, *y, *width, *height, *fill),
+                Shape::Line {
+                    points,
+                    stroke,
+                    width,
+                } => {
+                    for pair in points.windows(2) {
+                        draw_line(&mut image, pair[0], pair[1], *width, *stroke);
+                    }
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    text,
+                    size,
+                    fill,
+                    anchor,
+                } => draw_text(&mut image, *x, *y, text, *size, *fill, *anchor),
+            }
+        }
+        image
+    }
+
+    /// Write the drawing in each format, returning the paths written
+    pub fn save(&self, dir: &Path, name: &str, formats: &[ChartFormat]) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        let mut paths = Vec::with_capacity(formats.len());
+        for format in formats {
+            let path = dir.join(format!("{name}.{}", format.as_ref()));
+            match format {
+                ChartFormat::Svg => fs::write(&path, self.to_svg())?,
+                ChartFormat::Png => self.to_png().save(&path)?,
+            }
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn put_pixel(image: &mut RgbaImage, x: i64, y: i64, colour: Rgb) {
+    if x >= 0 && y >= 0 && x < image.width() as i64 && y < image.height() as i64 {
+        image.put_pixel(
+            x as u32,
+            y as u32,
+            Rgba([colour.0, colour.1, colour.2, 255]),
+        );
+    }
+}
+
+pub fn fill_rect(image: &mut RgbaImage, x: f32, y: f32, width: f32, height: f32, colour: Rgb) {
+    let (x0, y0) = (x.round() as i64, y.round() as i64);
+    let (x1, y1) = ((x + width).round() as i64, (y + height).round() as i64);
+    for py in y0..y1 {
+        for px in x0..x1 {
+            put_pixel(image, px, py, colour);
+        }
+    }
+}
+
+/// Stamp a square of the line's width at every pixel along it
+fn draw_line(image: &mut RgbaImage, from: (f32, f32), to: (f32, f32), width: f32, colour: Rgb) {
+    let steps = (to.0 - from.0)
+        .abs()
+        .max((to.1 - from.1).abs())
+        .ceil()
+        .max(1.0) as i64;
+    let half = (width / 2.0).max(0.5);
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let x = from.0 + (to.0 - from.0) * t;
+        let y = from.1 + (to.1 - from.1) * t;
+        fill_rect(image, x - half, y - half, half * 2.0, half * 2.0, colour);
+    }
+}
+
+/// Draw text with the built in 5x7 font, scaled to roughly match the SVG font size.
+/// The y position is the baseline like in SVG
+pub fn draw_text(
+    image: &mut RgbaImage,
+    x: f32,
+    y: f32,
+    text: &str,
+    size: f32,
+    colour: Rgb,
+    anchor: Anchor,
+) {
+    let scale = (size / 9.0).round().max(1.0) as i64;
+    let advance = 6 * scale;
+    let width = text.chars().count() as i64 * advance - scale;
+    let start = match anchor {
+        Anchor::Start => x.round() as i64,
+        Anchor::Middle => x.round() as i64 - width / 2,
+        Anchor::End => x.round() as i64 - width,
+    };
+    let top = y.round() as i64 - 7 * scale;
+    for (i, c) in text.chars().enumerate() {
+        let left = start + i as i64 * advance;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = left + column * scale + dx;
+                        let py = top + row as i64 * scale + dy;
+                        put_pixel(image, px, py, colour);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a 5x7 font with the most significant of the 5 bits on the left. Lowercase letters
+/// use the uppercase glyphs
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '°' => [0x0C, 0x12, 0x12, 0x0C, 0x00, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x00; 7],
+    }
+}
+
+/// A rectangular area of a chart with a vertical scale
+struct Panel {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    min: f32,
+    max: f32,
+}
+
+impl Panel {
+    /// Fit the scale to the values, rounded out to a whole number of ticks
+    fn new(area: (f32, f32, f32, f32), values: impl Iterator<Item = f32>) -> Self {
+        let (mut min, mut max) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(a, b), v| {
+            (a.min(v), b.max(v))
+        });
+        if !min.is_finite() || !max.is_finite() {
+            (min, max) = (0.0, 1.0);
+        }
+        let step = tick_step(max - min);
+        min = (min / step).floor() * step;
+        max = ((max / step).ceil() * step).max(min + step);
+        Self::with_range(area, min, max)
+    }
+
+    fn with_range(area: (f32, f32, f32, f32), min: f32, max: f32) -> Self {
+        let (x, y, width, height) = area;
+        Self {
+            x,
+            y,
+            width,
+            height,
+            min,
+            max,
+        }
+    }
+
+    fn y(&self, value: f32) -> f32 {
+        let fraction = (value - self.min) / (self.max - self.min);
+        self.y + self.height - fraction * self.height
+    }
+
+    /// The x position of item i of n, centred within its slot
+    fn x(&self, i: usize, n: usize) -> f32 {
+        self.x + (i as f32 + 0.5) * self.width / n.max(1) as f32
+    }
+
+    fn slot_width(&self, n: usize) -> f32 {
+        self.width / n.max(1) as f32
+    }
+
+    /// Horizontal grid lines with labels on the left, or the right for a second scale
+    fn axis(&self, drawing: &mut Drawing, unit: &str, right: bool) {
+        let step = tick_step(self.max - self.min);
+        let mut value = self.min;
+        while value <= self.max + step / 2.0 {
+            let y = self.y(value);
+            if !right {
+                drawing.line(vec![(self.x, y), (self.x + self.width, y)], GRID, 1.0);
+            }
+            let label = format!("{}{unit}", (value * 100.0).round() / 100.0);
+            if right {
+                drawing.text(
+                    self.x + self.width + 6.0,
+                    y + 4.0,
+                    label,
+                    11.0,
+                    Anchor::Start,
+                );
+            } else {
+                drawing.text(self.x - 6.0, y + 4.0, label, 11.0, Anchor::End);
+            }
+            value += step;
+        }
+    }
+
+    fn title(&self, drawing: &mut Drawing, title: &str) {
+        drawing.text(self.x, self.y - 6.0, title, 12.0, Anchor::Start);
+    }
+}
+
+/// A round step that splits the range into at most 5 ticks
+fn tick_step(range: f32) -> f32 {
+    let range = range.max(1.0);
+    let magnitude = 10f32.powf((range / 5.0).log10().floor());
+    [1.0, 2.0, 2.5, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| range / step <= 5.0)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// The chart area within the margins, split vertically by the fractions with gaps for titles
+fn layout(width: u32, height: u32, fractions: &[f32]) -> Vec<(f32, f32, f32, f32)> {
+    let (left, right, top, bottom, gap) = (60.0, 60.0, 50.0, 50.0, 30.0);
+    let inner_width = width as f32 - left - right;
+    let inner_height =
+        height as f32 - top - bottom - gap * (fractions.len().saturating_sub(1)) as f32;
+    let mut y = top;
+    fractions
+        .iter()
+        .map(|fraction| {
+            let panel_height = inner_height * fraction;
+            let area = (left, y, inner_width, panel_height);
+            y += panel_height + gap;
+            area
+        })
+        .collect()
+}
+
+/// Labels below the panel for every nth item
+fn time_axis(
+    drawing: &mut Drawing,
+    last: &Panel,
+    times: &[DateTime<Tz>],
+    label_every: usize,
+    format: &str,
+) {
+    let label_every = label_every.max(1);
+    for (i, time) in times.iter().enumerate() {
+        if i % label_every == 0 {
+            let x = last.x(i, times.len());
+            let y = last.y + last.height;
+            drawing.line(vec![(x, y), (x, y + 4.0)], FOREGROUND, 1.0);
+            drawing.text(
+                x,
+                y + 16.0,
+                time.format(format).to_string(),
+                11.0,
+                Anchor::Middle,
+            );
+        }
+    }
+}
+
+fn shade_night(drawing: &mut Drawing, panels: &[&Panel], is_night: &[bool]) {
+    for panel in panels {
+        let width = panel.slot_width(is_night.len());
+        for (i, _) in is_night.iter().enumerate().filter(|(_, night)| **night) {
+            let x = panel.x + i as f32 * width;
+            drawing.rect(x, panel.y, width, panel.height, NIGHT);
+        }
+    }
+}
+
+fn bars(drawing: &mut Drawing, panel: &Panel, values: &[f32], colour: Rgb) {
+    let width = panel.slot_width(values.len()) * 0.7;
+    for (i, value) in values.iter().enumerate() {
+        let top = panel.y(*value);
+        let bottom = panel.y(panel.min);
+        let x = panel.x(i, values.len()) - width / 2.0;
+        drawing.rect(x, top, width, bottom - top, colour);
+    }
+}
+
+fn series(drawing: &mut Drawing, panel: &Panel, values: &[f32], colour: Rgb, width: f32) {
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (panel.x(i, values.len()), panel.y(*v)))
+        .collect();
+    drawing.line(points, colour, width);
+}
+
+fn legend(drawing: &mut Drawing, panel: &Panel, items: &[(&str, Rgb)]) {
+    let mut x = panel.x + panel.width;
+    for (label, colour) in items.iter().rev() {
+        let text_width = label.chars().count() as f32 * 6.5;
+        x -= text_width;
+        drawing.text(x, panel.y - 6.0, *label, 11.0, Anchor::Start);
+        drawing.rect(x - 16.0, panel.y - 12.0, 12.0, 4.0, *colour);
+        x -= 28.0;
+    }
+}
+
+fn title(drawing: &mut Drawing, text: &str) {
+    drawing.text(
+        drawing.width as f32 / 2.0,
+        24.0,
+        text.to_string(),
+        16.0,
+        Anchor::Middle,
+    );
+}
+
+/// Temperature, rain chance and wind for each hour
+pub fn meteogram(
+    location: &Location,
+    hours: &[&HourlyForecastData],
+    units: &Units,
+    opts: &ChartOptions,
+) -> Drawing {
+    let tz = location.timezone;
+    let mut drawing = Drawing::new(opts.width, opts.height);
+    let issued = location
+        .weather
+        .hourly_forecast
+        .issue_time
+        .with_timezone(&tz)
+        .format("%H:%M %a %d %b");
+    title(
+        &mut drawing,
+        &format!("Hourly forecast for {location} issued {issued}"),
+    );
+    let areas = layout(opts.width, opts.height, &[0.5, 0.2, 0.3]);
+
+    let temps: Vec<f32> = hours.iter().map(|h| units.temp(h.temp)).collect();
+    let feels_like: Vec<f32> = hours
+        .iter()
+        .map(|h| units.temp(h.temp_feels_like))
+        .collect();
+    let temp_panel = Panel::new(areas[0], temps.iter().chain(&feels_like).copied());
+    let rain_panel = Panel::with_range(areas[1], 0.0, 100.0);
+    let wind: Vec<f32> = hours
+        .iter()
+        .map(|h| units.wind(h.wind.speed_kilometre, h.wind.speed_knot))
+        .collect();
+    let gusts: Vec<f32> = hours
+        .iter()
+        .map(|h| units.wind(h.wind.gust_speed_kilometre, h.wind.gust_speed_knot))
+        .collect();
+    let mut wind_panel = Panel::new(areas[2], wind.iter().chain(&gusts).copied());
+    wind_panel.min = wind_panel.min.min(0.0);
+    let panels = [&temp_panel, &rain_panel, &wind_panel];
+
+    let is_night: Vec<bool> = hours.iter().map(|h| h.is_night).collect();
+    shade_night(&mut drawing, &panels, &is_night);
+    temp_panel.axis(&mut drawing, units.temp_symbol(), false);
+    rain_panel.axis(&mut drawing, "%", false);
+    wind_panel.axis(&mut drawing, "", false);
+
+    temp_panel.title(
+        &mut drawing,
+        &format!("Temperature ({})", units.temp_symbol()),
+    );
+    legend(
+        &mut drawing,
+        &temp_panel,
+        &[("Temperature", TEMP), ("Feels like", FEELS_LIKE)],
+    );
+    series(&mut drawing, &temp_panel, &feels_like, FEELS_LIKE, 2.0);
+    series(&mut drawing, &temp_panel, &temps, TEMP, 3.0);
+
+    rain_panel.title(&mut drawing, "Chance of rain");
+    let chances: Vec<f32> = hours.iter().map(|h| h.rain.chance as f32).collect();
+    bars(&mut drawing, &rain_panel, &chances, RAIN);
+
+    wind_panel.title(&mut drawing, &format!("Wind ({})", units.wind_symbol()));
+    legend(&mut drawing, &wind_panel, &[("Wind", WIND), ("Gust", GUST)]);
+    series(&mut drawing, &wind_panel, &gusts, GUST, 2.0);
+    series(&mut drawing, &wind_panel, &wind, WIND, 3.0);
+
+    let times: Vec<DateTime<Tz>> = hours.iter().map(|h| h.time.with_timezone(&tz)).collect();
+    let label_every = (hours.len() / 12).max(1);
+    time_axis(&mut drawing, &wind_panel, &times, label_every, "%a %H:%M");
+    drawing
+}
+
+/// The temperature range and rain chance for each day
+pub fn daily(
+    location: &Location,
+    days: &[DailyForecastData],
+    units: &Units,
+    opts: &ChartOptions,
+) -> Drawing {
+    let tz = location.timezone;
+    let mut drawing = Drawing::new(opts.width, opts.height);
+    let issued = location
+        .weather
+        .daily_forecast
+        .issue_time
+        .with_timezone(&tz)
+        .format("%H:%M %a %d %b");
+    title(
+        &mut drawing,
+        &format!("7-day forecast for {location} issued {issued}"),
+    );
+    let areas = layout(opts.width, opts.height, &[0.7, 0.3]);
+
+    let mins: Vec<Option<f32>> = days
+        .iter()
+        .map(|d| d.temp_min.map(|t| units.temp(t)))
+        .collect();
+    let maxes: Vec<Option<f32>> = days
+        .iter()
+        .map(|d| d.temp_max.map(|t| units.temp(t)))
+        .collect();
+    let temp_panel = Panel::new(areas[0], mins.iter().chain(&maxes).flatten().copied());
+    let rain_panel = Panel::with_range(areas[1], 0.0, 100.0);
+    temp_panel.axis(&mut drawing, units.temp_symbol(), false);
+    rain_panel.axis(&mut drawing, "%", false);
+    temp_panel.title(
+        &mut drawing,
+        &format!("Min and max temperature ({})", units.temp_symbol()),
+    );
+    legend(
+        &mut drawing,
+        &temp_panel,
+        &[("Min", MIN_TEMP), ("Max", TEMP)],
+    );
+
+    let width = temp_panel.slot_width(days.len()) * 0.4;
+    for (i, (min, max)) in mins.iter().zip(&maxes).enumerate() {
+        let x = temp_panel.x(i, days.len());
+        // The first day loses its min once it's passed, and the last has no max
+        let low = min.or(*max);
+        let high = max.or(*min);
+        let (Some(low), Some(high)) = (low, high) else {
+            continue;
+        };
+        let (top, bottom) = (temp_panel.y(high), temp_panel.y(low));
+        drawing.rect(x - width / 2.0, top, width, (bottom - top).max(2.0), GRID);
+        if let Some(max) = max {
+            drawing.rect(x - width / 2.0, top - 1.5, width, 3.0, TEMP);
+            drawing.text(x, top - 6.0, max.to_string(), 12.0, Anchor::Middle);
+        }
+        if let Some(min) = min {
+            drawing.rect(x - width / 2.0, bottom - 1.5, width, 3.0, MIN_TEMP);
+            drawing.text(x, bottom + 16.0, min.to_string(), 12.0, Anchor::Middle);
+        }
+    }
+
+    rain_panel.title(&mut drawing, "Chance of rain");
+    let chances: Vec<f32> = days
+        .iter()
+        .map(|d| d.rain.chance.unwrap_or(0) as f32)
+        .collect();
+    bars(&mut drawing, &rain_panel, &chances, RAIN);
+
+    let times: Vec<DateTime<Tz>> = days.iter().map(|d| d.date.with_timezone(&tz)).collect();
+    time_axis(&mut drawing, &rain_panel, &times, 1, "%a %d");
+    drawing
+}
+
+/// Observed temperature and humidity, with the rain accumulated over the period
+pub fn history(
+    location: &Location,
+    observations: &[Observation],
+    units: &Units,
+    opts: &ChartOptions,
+) -> Drawing {
+    let tz = location.timezone;
+    let mut drawing = Drawing::new(opts.width, opts.height);
+    let period = match (observations.first(), observations.last()) {
+        (Some(first), Some(last)) => format!(
+            " from {} to {}",
+            first.observation_time.with_timezone(&tz).format("%a %d %b"),
+            last.observation_time.with_timezone(&tz).format("%a %d %b")
+        ),
+        _ => String::new(),
+    };
+    title(
+        &mut drawing,
+        &format!("Observations for {location}{period}"),
+    );
+    let areas = layout(opts.width, opts.height, &[0.65, 0.35]);
+
+    let temps: Vec<f32> = observations.iter().map(|o| units.temp(o.temp)).collect();
+    let humidity: Vec<f32> = observations.iter().map(|o| o.humidity as f32).collect();
+    let rain: Vec<f32> = rain_accumulation(observations)
+        .into_iter()
+        .map(|mm| units.rain(mm))
+        .collect();
+    let temp_panel = Panel::new(areas[0], temps.iter().copied());
+    let humidity_panel = Panel::with_range(areas[0], 0.0, 100.0);
+    let mut rain_panel = Panel::new(areas[1], rain.iter().copied().chain([0.0, 1.0]));
+    rain_panel.min = 0.0;
+
+    temp_panel.axis(&mut drawing, units.temp_symbol(), false);
+    humidity_panel.axis(&mut drawing, "%", true);
+    rain_panel.axis(&mut drawing, "", false);
+    temp_panel.title(
+        &mut drawing,
+        &format!("Temperature ({}) and humidity", units.temp_symbol()),
+    );
+    legend(
+        &mut drawing,
+        &temp_panel,
+        &[("Temperature", TEMP), ("Humidity", HUMIDITY)],
+    );
+    series(&mut drawing, &humidity_panel, &humidity, HUMIDITY, 2.0);
+    series(&mut drawing, &temp_panel, &temps, TEMP, 3.0);
+
+    rain_panel.title(
+        &mut drawing,
+        &format!("Accumulated rain ({})", units.rain_symbol()),
+    );
+    series(&mut drawing, &rain_panel, &rain, RAIN, 3.0);
+
+    let times: Vec<DateTime<Tz>> = observations
+        .iter()
+        .map(|o| o.observation_time.with_timezone(&tz))
+        .collect();
+    let label_every = (observations.len() / 10).max(1);
+    time_axis(&mut drawing, &rain_panel, &times, label_every, "%a %H:%M");
+    drawing
+}
+
+/// Running total of rain in mm. The rain since 9am resets each morning, so a drop in the value
+/// starts a new day's total
+fn rain_accumulation(observations: &[Observation]) -> Vec<f32> {
+    let mut total = 0.0;
+    let mut previous: Option<f32> = None;
+    observations
+        .iter()
+        .map(|obs| {
+            let current = obs.rain_since_9am.unwrap_or(0.0);
+            match previous {
+                Some(last) if current >= last => total += current - last,
+                Some(_) => total += current,
+                // The first observation's rain fell before the period started
+                None => (),
+            }
+            previous = Some(current);
+            total
+        })
+        .collect()
+}
+
+/// Render the configured charts for a location, returning the paths written
+pub fn render_charts(
+    location: &Location,
+    observations: &[Observation],
+    units: &Units,
+    opts: &ChartOptions,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for kind in &opts.kinds {
+        let drawing = match kind {
+            ChartKind::Meteogram => {
+                let hours: Vec<&HourlyForecastData> = location
+                    .weather
+                    .hourly_forecast
+                    .upcoming(Utc::now())
+                    .take(opts.hours)
+                    .collect();
+                meteogram(location, &hours, units, opts)
+            }
+            ChartKind::Daily => daily(location, &location.weather.daily_forecast.days, units, opts),
+            ChartKind::History => history(location, observations, units, opts),
+        };
+        let name = format!("{}-{}", location.id, kind.as_ref());
+        paths.extend(drawing.save(&opts.chart_dir, &name, &opts.formats)?);
+    }
+    Ok(paths)
+}
@@ -1,19 +1,41 @@
+use crate::animation::AnimationFormat;
+use crate::changes::{rain_range, ChangeKind, ForecastChangeRow};
+use crate::chart::{ChartFormat, ChartKind};
 use crate::client::Client;
+use crate::colour::Colours;
 use crate::config::Config;
+use crate::daily::DailyRow;
+use crate::dashboard::Dashboard;
+use crate::descriptor::IconTheme;
 use crate::ftp::FtpClient;
-use crate::location::SearchResult;
+use crate::history::ObservationRow;
+use crate::hourly::HourlyRow;
+use crate::location::{Location, SearchResult};
 use crate::logging::{setup_logging, LogLevel};
+use crate::meteogram::Meteogram;
 use crate::persistence::Database;
 use crate::radar::{
-    get_radar_image_managers, update_radar_images, Radar, RadarImageFeature, RadarImageManager,
-    RadarImageOptions, RadarType,
+    get_mosaic_image_managers, get_radar_image_managers, update_radar_images, Radar, RadarId,
+    RadarImageFeature, RadarImageManager, RadarImageOptions, RadarOverlay, RadarOverlayInfo,
+    RadarType, TerminalRadarViewer,
+};
+use crate::services::{
+    archive_forecasts, create_location, get_nearby_radars, ids_to_locations, latest_nowcast,
+    update_charts, update_if_due, update_nowcasts, update_summaries, verify_forecasts,
 };
-use crate::services::{create_location, get_nearby_radars, ids_to_locations, update_if_due};
 use crate::station::StationsTable;
-use crate::util::{format_duration, remove_if_exists};
-use crate::weather::{FstringKey, WeatherOptions};
+use crate::statusbar::{StatusBar, StatusBarFormat};
+use crate::summary::{rain_totals, SummaryPeriod, SummaryRow};
+use crate::terminal_graphics::TerminalGraphics;
+use crate::units::{RainUnit, TemperatureUnit, WindUnit};
+use crate::util::{compass_point, format_duration, remove_if_exists, start_of_day};
+use crate::verification::{ErrorStats, Verification};
+use crate::weather::{
+    CurrentRow, DailyKey, ForecastContext, FstringKey, HourlyKey, WeatherOptions,
+};
 use anyhow::{anyhow, Result};
-use chrono::{Duration, Local, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
 use clap::{Parser, Subcommand};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
@@ -63,6 +85,26 @@ pub struct Cli {
     #[arg(short = 'i', long = "location-id", value_name = "ID")]
     pub locations: Option<Vec<String>>,
 
+    /// Temperature unit (overrides config)
+    #[arg(long, global = true, value_enum, value_name = "UNIT")]
+    #[serde(skip)]
+    pub temp_unit: Option<TemperatureUnit>,
+
+    /// Wind speed unit (overrides config)
+    #[arg(long, global = true, value_enum, value_name = "UNIT")]
+    #[serde(skip)]
+    pub wind_unit: Option<WindUnit>,
+
+    /// Rainfall unit (overrides config)
+    #[arg(long, global = true, value_enum, value_name = "UNIT")]
+    #[serde(skip)]
+    pub rain_unit: Option<RainUnit>,
+
+    /// Weather icon theme (overrides config)
+    #[arg(long, global = true, value_enum, value_name = "THEME")]
+    #[serde(skip)]
+    pub icon_theme: Option<IconTheme>,
+
     #[command(subcommand)]
     #[serde(skip)]
     pub command: Option<Commands>,
@@ -82,8 +124,22 @@ pub enum Commands {
     Daily(DailyArgs),
     /// Display the hourly forecast
     Hourly(HourlyArgs),
+    /// Chart the hourly forecast in the terminal
+    Meteogram(MeteogramArgs),
+    /// Render forecast and observation charts as SVG or PNG images
+    Chart(ChartArgs),
     /// Display the current weather
     Current(CurrentArgs),
+    /// Show the weather for every location in a full screen view that updates in place
+    Dashboard,
+    /// Display past observations from the database
+    History(HistoryArgs),
+    /// Display daily, weekly or monthly summaries of past observations
+    Summary(SummaryArgs),
+    /// Display the accuracy of past forecasts
+    Verify(VerifyArgs),
+    /// Display how the daily forecast changed between issues
+    Changes(ChangesArgs),
     /// Download and view radar images
     Radar(RadarArgs),
 }
@@ -102,8 +158,15 @@ pub fn cli() -> Result<()> {
         Some(Commands::EditOpts) => edit_weather_opts(&config)?,
         Some(Commands::Daily(args)) => daily(&config, args)?,
         Some(Commands::Hourly(args)) => hourly(&config, args)?,
+        Some(Commands::Meteogram(args)) => meteogram(&config, args)?,
+        Some(Commands::Chart(args)) => chart(&config, args)?,
         Some(Commands::Current(args)) => current(&config, args)?,
-        Some(Commands::Radar(args)) => radar(&config, args.monitor)?,
+        Some(Commands::Dashboard) => dashboard(&config)?,
+        Some(Commands::History(args)) => history(&config, args)?,
+        Some(Commands::Summary(args)) => summary(&config, args)?,
+        Some(Commands::Verify(args)) => verify(&config, args)?,
+        Some(Commands::Changes(args)) => changes(&config, args)?,
+        Some(Commands::Radar(args)) => radar(&config, args.monitor, args.eta)?,
         None => {}
     }
     Ok(())
@@ -167,14 +230,33 @@ fn monitor(config: &Config) -> Result<()> {
     for location in &locations {
         info!("Monitoring weather for {}", location.id);
     }
+    let charts = &config.main.charts;
     loop {
+        let issue_times: Vec<_> = locations.iter().map(forecast_issue_times).collect();
         let next_check = update_if_due(&mut locations, &client, &database)?;
+        for (location, issue_times) in locations.iter().zip(issue_times) {
+            if !charts.render_on_update || forecast_issue_times(location) == issue_times {
+                continue;
+            }
+            match update_charts(location, &database, &config.main.units, charts) {
+                Ok(paths) => info!("Rendered {} charts for {}", paths.len(), location.id),
+                Err(e) => error!("Unable to render charts for {}. {e}", location.id),
+            }
+        }
         let sleep_duration = (next_check - Utc::now()).max(Duration::seconds(1));
         debug!("Next weather update in {}", format_duration(sleep_duration));
         sleep((sleep_duration + Duration::seconds(1)).to_std().unwrap());
     }
 }
 
+fn forecast_issue_times(location: &Location) -> (DateTime<Utc>, DateTime<Utc>) {
+    let weather = &location.weather;
+    (
+        weather.hourly_forecast.issue_time,
+        weather.daily_forecast.issue_time,
+    )
+}
+
 fn add_location(config: &mut Config) -> Result<()> {
     let client = config.get_client();
     let database = config.get_database()?;
@@ -238,6 +320,15 @@ pub struct CurrentArgs {
     /// List the keys that can be used in an fstring
     #[arg(short, long)]
     list_keys: bool,
+    /// Output in the format expected by a status bar
+    #[arg(short, long, default_value_t, value_enum)]
+    output: StatusBarFormat,
+    /// Format string for the Waybar tooltip [default: the next 6 hours]
+    #[arg(short, long)]
+    tooltip_fstring: Option<String>,
+    /// Output all fields in a structured format instead of using the fstring
+    #[arg(short = 'F', long, value_enum)]
+    format: Option<OutputFormat>,
 }
 
 fn current(config: &Config, args: &CurrentArgs) -> Result<()> {
@@ -245,30 +336,87 @@ fn current(config: &Config, args: &CurrentArgs) -> Result<()> {
         for key in FstringKey::iter() {
             println!("{}", key.as_ref());
         }
+        println!("\nhour+N.<field> where N is hours from now (0 is the current hour)");
+        for key in HourlyKey::iter() {
+            println!("  {}", key.as_ref());
+        }
+        println!("\ndayN.<field> where N is days from today (0 is today)");
+        for key in DailyKey::iter() {
+            println!("  {}", key.as_ref());
+        }
         return Ok(());
     }
     if config.main.locations.is_empty() {
         return Err(anyhow!("No locations specified"));
     }
+    let fstring = args
+        .fstring
+        .as_ref()
+        .unwrap_or(&config.main.current_fstring);
+    let status_bar = StatusBar::new(
+        args.output,
+        fstring,
+        args.tooltip_fstring.as_deref(),
+        &config.main.status_bar,
+    )?;
     let client = config.get_client();
     let database = config.get_database()?;
     let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
     if args.check {
         update_if_due(&mut locations, &client, &database)?;
     }
-    let fstring = args
-        .fstring
-        .as_ref()
-        .unwrap_or(&config.main.current_fstring);
-    for location in locations {
-        let current = location.weather.current();
-        let output = current.process_fstring(fstring)?;
-        if std::io::stdout().is_terminal() {
+    let mut rows = Vec::new();
+    for location in &locations {
+        let today = location.today();
+        let month_start = SummaryPeriod::Month.start_of(today);
+        let mut current =
+            location
+                .weather
+                .current(location.timezone, config.main.units, &config.main.icons);
+        let summaries =
+            database.get_daily_summaries(&location.id, month_start - Duration::days(1), today)?;
+        (current.yesterday_rain, current.month_rain) = rain_totals(&location.id, &summaries, today);
+        current.nowcast = latest_nowcast(location, &database, &config.main.nowcast)?;
+        if args.format.is_some() {
+            rows.push(CurrentRow::new(&location.id, &current));
+            continue;
+        }
+        let output = status_bar.render(&current)?;
+        // Status bars read a line at a time
+        if std::io::stdout().is_terminal() || args.output != StatusBarFormat::Plain {
             println!("{output}");
         } else {
             print!("{output}");
         }
     }
+
+    match args.format {
+        // One field per row as there are too many for columns
+        Some(OutputFormat::Table) => {
+            for row in rows {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_content_arrangement(ContentArrangement::Dynamic)
+                    .set_header(vec!["Field", "Value"]);
+                if let serde_json::Value::Object(fields) = serde_json::to_value(&row)? {
+                    for (field, value) in fields {
+                        let value = match value {
+                            serde_json::Value::String(s) => s,
+                            serde_json::Value::Null => String::new(),
+                            value => value.to_string(),
+                        };
+                        table.add_row(vec![field, value]);
+                    }
+                }
+                println!("Current weather for {}", row.location_id);
+                println!("{table}");
+            }
+        }
+        Some(format) => print_rows(rows, format)?,
+        None => {}
+    }
     Ok(())
 }
 
@@ -283,6 +431,15 @@ pub struct DailyArgs {
     /// Show the extended description for each day's forecast
     #[arg(short, long)]
     extended: bool,
+    /// Show sunrise and sunset, the UV index with sun protection times and the fire danger
+    #[arg(short, long)]
+    sun: bool,
+    /// Use the named set of columns from the config file [default: "default" if defined]
+    #[arg(long)]
+    view: Option<String>,
+    /// Output format
+    #[arg(short = 'F', long, default_value_t, value_enum)]
+    format: OutputFormat,
 }
 
 fn daily(config: &Config, args: &DailyArgs) -> Result<()> {
@@ -292,17 +449,36 @@ fn daily(config: &Config, args: &DailyArgs) -> Result<()> {
     let client = config.get_client();
     let database = config.get_database()?;
     let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let units = &config.main.units;
+    let icons = &config.main.icons;
+    let colours = Colours::new(&config.main.colours);
+    let view = selected_view(&args.view, &config.main.views.daily)
+        .map(|name| config.main.views.daily(name))
+        .transpose()?;
 
     if args.force_check {
         for location in &mut locations {
             let new_daily = client.get_daily(&location.geohash)?;
             location.weather.update_daily(Utc::now(), new_daily);
             database.update_weather(location)?;
+            archive_forecasts(location, &database)?;
         }
     } else if args.check {
         update_if_due(&mut locations, &client, &database)?;
     }
 
+    if !matches!(args.format, OutputFormat::Table) {
+        let rows = locations.iter().flat_map(|location| {
+            location
+                .weather
+                .daily_forecast
+                .days
+                .iter()
+                .map(move |day| DailyRow::new(location, day, units, icons))
+        });
+        return print_rows(rows, args.format);
+    }
+
     for location in locations {
         let mut table = Table::new();
 
@@ -310,26 +486,54 @@ fn daily(config: &Config, args: &DailyArgs) -> Result<()> {
             .weather
             .daily_forecast
             .issue_time
-            .with_timezone(&Local)
+            .with_timezone(&location.timezone)
             .format("%r on %a %d %b");
 
         let header = format!("Forecast for {} issued at {}", location, issued);
         println!("{header}");
+        if let Some(view) = &view {
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_content_arrangement(ContentArrangement::Dynamic)
+                .set_header(view.header());
+            for day in &location.weather.daily_forecast.days {
+                let context = ForecastContext {
+                    data: day,
+                    units: *units,
+                    tz: location.timezone,
+                    icons,
+                };
+                table.add_row(view.row(&context));
+            }
+            println!("{table}");
+            continue;
+        }
+        let mut columns = vec!["Day", "Min", "Max", "Rain", "Chance"];
+        if args.sun {
+            columns.extend(["Sun", "UV", "Fire danger"]);
+        }
+        columns.push("Description");
         table
             .load_preset(UTF8_FULL)
             .apply_modifier(UTF8_ROUND_CORNERS)
             .set_content_arrangement(ContentArrangement::Dynamic)
-            .set_header(vec!["Day", "Min", "Max", "Rain", "Chance", "Description"]);
+            .set_header(columns);
 
+        let time = |t: DateTime<Utc>| t.with_timezone(&location.timezone).format("%H:%M");
         for day in &location.weather.daily_forecast.days {
             let date = day
                 .date
-                .with_timezone(&Local)
+                .with_timezone(&location.timezone)
                 .format("%a %d %b")
                 .to_string();
 
-            let max = day.temp_max.map_or("".to_string(), |t| t.to_string());
-            let min = day.temp_min.map_or("".to_string(), |t| t.to_string());
+            let max = day
+                .temp_max
+                .map_or("".to_string(), |t| units.format_temp(t));
+            let min = day
+                .temp_min
+                .map_or("".to_string(), |t| units.format_temp(t));
             let mut extended = day.extended_text.clone().unwrap_or(String::new());
             let description = if args.extended {
                 extended
@@ -344,31 +548,55 @@ fn daily(config: &Config, args: &DailyArgs) -> Result<()> {
                     short
                 }
             };
-
-            let rain = if day.rain.amount.max.is_some() && day.rain.amount.lower_range.is_some() {
-                format!(
-                    "{}-{}{}",
-                    day.rain.amount.lower_range.unwrap(),
-                    day.rain.amount.max.unwrap(),
-                    day.rain.amount.units
-                )
-            } else {
-                "0mm".to_string()
+            let description = match (&day.icon_descriptor, icons.theme) {
+                (Some(descriptor), theme) if theme != IconTheme::Text => {
+                    format!("{} {description}", icons.icon(descriptor, false))
+                }
+                _ => description,
             };
+
+            let rain = rain_range(&day.rain.amount, units);
             let chance = if let Some(chance) = day.rain.chance {
                 format!("{}%", chance)
             } else {
                 String::new()
             };
 
-            table.add_row(vec![
+            let mut row = vec![
                 Cell::new(&date),
-                Cell::new(&min),
-                Cell::new(&max),
+                colours.cell(&min, day.temp_min.and_then(|t| colours.temp(t))),
+                colours.cell(&max, day.temp_max.and_then(|t| colours.temp(t))),
                 Cell::new(&rain),
-                Cell::new(&chance),
-                Cell::new(&description),
-            ]);
+                colours.cell(
+                    &chance,
+                    day.rain.chance.and_then(|c| colours.rain_chance(c)),
+                ),
+            ];
+            if args.sun {
+                let astro = &day.astronomical;
+                let sun = format!("{}-{}", time(astro.sunrise_time), time(astro.sunset_time));
+                let mut uv = match (day.uv.max_index, &day.uv.category) {
+                    (Some(index), Some(category)) => format!("{index} {category}"),
+                    (Some(index), None) => index.to_string(),
+                    (None, Some(category)) => category.to_string(),
+                    (None, None) => String::new(),
+                };
+                if let (Some(start), Some(end)) = (day.uv.start_time, day.uv.end_time) {
+                    uv.push_str(&format!("\n{}-{}", time(start), time(end)));
+                }
+                let fire = day
+                    .fire_danger
+                    .as_deref()
+                    .or(day.fire_danger_category.text.as_deref())
+                    .unwrap_or("");
+                row.extend([
+                    Cell::new(sun),
+                    colours.cell(uv, day.uv.max_index.and_then(|i| colours.uv(i))),
+                    colours.cell(fire, colours.fire_danger(&day.fire_danger_category)),
+                ]);
+            }
+            row.push(Cell::new(&description));
+            table.add_row(row);
         }
         println!("{table}");
     }
@@ -389,6 +617,12 @@ pub struct HourlyArgs {
     /// Show the 'feels like' temp in brackets if it differs from the actual temp
     #[arg(short = 'l', long)]
     feels_like: bool,
+    /// Use the named set of columns from the config file [default: "default" if defined]
+    #[arg(long)]
+    view: Option<String>,
+    /// Output format
+    #[arg(short = 'F', long, default_value_t, value_enum)]
+    format: OutputFormat,
 }
 
 fn hourly(config: &Config, args: &HourlyArgs) -> Result<()> {
@@ -398,18 +632,37 @@ fn hourly(config: &Config, args: &HourlyArgs) -> Result<()> {
     let client = config.get_client();
     let database = config.get_database()?;
     let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let units = &config.main.units;
+    let icons = &config.main.icons;
+    let colours = Colours::new(&config.main.colours);
+    let view = selected_view(&args.view, &config.main.views.hourly)
+        .map(|name| config.main.views.hourly(name))
+        .transpose()?;
 
     if args.force_check {
         for location in &mut locations {
             let new_hourly = client.get_hourly(&location.geohash)?;
             location.weather.update_hourly(Utc::now(), new_hourly);
             database.update_weather(location)?;
+            archive_forecasts(location, &database)?;
         }
     } else if args.check {
         update_if_due(&mut locations, &client, &database)?;
     }
 
-    for location in locations {
+    let now = Utc::now();
+    if !matches!(args.format, OutputFormat::Table) {
+        let rows = locations.iter().flat_map(|location| {
+            let hourly = &location.weather.hourly_forecast;
+            hourly
+                .upcoming(now)
+                .take(args.hours)
+                .map(move |hour| HourlyRow::new(location, hour, units, icons))
+        });
+        return print_rows(rows, args.format);
+    }
+
+    for location in &locations {
         let mut table = Table::new();
         table
             .load_preset(UTF8_FULL)
@@ -420,21 +673,34 @@ fn hourly(config: &Config, args: &HourlyArgs) -> Result<()> {
             .weather
             .hourly_forecast
             .issue_time
-            .with_timezone(&Local)
+            .with_timezone(&location.timezone)
             .format("%r on %a %d %b");
         let title = format!("Hourly forecast for {} issued at {}", location, issue_time);
 
         let todo = location
             .weather
             .hourly_forecast
-            .data
-            .iter()
-            .filter(|h| h.next_forecast_period > Utc::now())
+            .upcoming(now)
             .take(args.hours);
 
+        if let Some(view) = &view {
+            table.set_header(view.header());
+            for hour in todo {
+                let context = ForecastContext {
+                    data: hour,
+                    units: *units,
+                    tz: location.timezone,
+                    icons,
+                };
+                table.add_row(view.row(&context));
+            }
+            println!("{title}");
+            println!("{table}");
+            continue;
+        }
+
+        // Without a view the rain columns are only shown if there's a chance of rain
         let show_rain = todo.clone().any(|h| h.rain.chance > 0);
-        // TODO: Make this configurable. Perhaps let the user specify column names paired with
-        // an fstring that's used to generate the row text.
         let columns = if show_rain {
             vec![
                 "Time", "Temp", "Desc", "Rain", "Chance", "Wind", "Gust", "Humidity",
@@ -445,40 +711,57 @@ fn hourly(config: &Config, args: &HourlyArgs) -> Result<()> {
         table.set_header(columns);
 
         for hour in todo {
-            let time = hour.time.with_timezone(&Local).format("%a %r").to_string();
+            let time = hour
+                .time
+                .with_timezone(&location.timezone)
+                .format("%a %r")
+                .to_string();
             let chance = format!("{}%", hour.rain.chance);
-            let wind = format!("{} {}", hour.wind.speed_kilometre, hour.wind.direction);
-            let gust = format!("{}", hour.wind.gust_speed_kilometre);
+            let wind = format!(
+                "{} {}",
+                units.format_wind(hour.wind.speed_kilometre, hour.wind.speed_knot),
+                hour.wind.direction
+            );
+            let gust = units.format_wind(hour.wind.gust_speed_kilometre, hour.wind.gust_speed_knot);
             let temp = if args.feels_like && hour.temp != hour.temp_feels_like {
-                format!("{} ({})", hour.temp, hour.temp_feels_like)
+                format!(
+                    "{} ({})",
+                    units.format_temp(hour.temp),
+                    units.format_temp(hour.temp_feels_like)
+                )
             } else {
-                hour.temp.to_string()
+                units.format_temp(hour.temp)
             };
             let desc = hour.icon_descriptor.get_description(hour.is_night);
+            let desc = match icons.theme {
+                IconTheme::Text => desc.to_string(),
+                _ => format!(
+                    "{} {desc}",
+                    icons.icon(&hour.icon_descriptor, hour.is_night)
+                ),
+            };
 
+            let temp = colours.cell(temp, colours.temp(hour.temp));
+            let gust = colours.cell(gust, colours.gust(hour.wind.gust_speed_kilometre));
             let cells = if show_rain {
-                let rain = if let Some(max) = hour.rain.amount.max {
-                    format!("{}-{}{}", hour.rain.amount.min, max, hour.rain.amount.units)
-                } else {
-                    "0mm".to_string()
-                };
+                let rain = units.format_rain_range(hour.rain.amount.min, hour.rain.amount.max);
                 vec![
                     Cell::new(&time),
-                    Cell::new(&temp),
+                    temp,
                     Cell::new(desc),
                     Cell::new(&rain),
-                    Cell::new(&chance),
+                    colours.cell(&chance, colours.rain_chance(hour.rain.chance)),
                     Cell::new(&wind),
-                    Cell::new(&gust),
+                    gust,
                     Cell::new(format!("{}%", &hour.relative_humidity)),
                 ]
             } else {
                 vec![
                     Cell::new(&time),
-                    Cell::new(temp),
+                    temp,
                     Cell::new(desc),
                     Cell::new(&wind),
-                    Cell::new(&gust),
+                    gust,
                     Cell::new(format!("{}%", &hour.relative_humidity)),
                 ]
             };
@@ -490,6 +773,607 @@ fn hourly(config: &Config, args: &HourlyArgs) -> Result<()> {
     Ok(())
 }
 
+#[derive(Parser, Debug, Serialize, Deserialize)]
+pub struct MeteogramArgs {
+    /// Check for updates if due
+    #[arg(short, long)]
+    check: bool,
+    /// How many hours to show (max 72)
+    #[arg(short = 'H', long, default_value_t = 48)]
+    hours: usize,
+}
+
+fn meteogram(config: &Config, args: &MeteogramArgs) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let colours = Colours::new(&config.main.colours);
+    if args.check {
+        update_if_due(&mut locations, &client, &database)?;
+    }
+
+    let now = Utc::now();
+    for location in &locations {
+        let hourly = &location.weather.hourly_forecast;
+        let hours: Vec<_> = hourly.upcoming(now).take(args.hours).collect();
+        if hours.is_empty() {
+            return Err(anyhow!("The hourly forecast for {location} has run out"));
+        }
+        let issue_time = hourly
+            .issue_time
+            .with_timezone(&location.timezone)
+            .format("%r on %a %d %b");
+        let chart = Meteogram::new(&hours, &config.main.units, location.timezone, &colours);
+        println!("Hourly forecast for {location} issued at {issue_time}");
+        println!("{}", chart.render());
+    }
+    Ok(())
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+pub struct ChartArgs {
+    /// Check for updates if due
+    #[arg(short, long)]
+    check: bool,
+    /// Which charts to render. Can be repeated (defaults to the config)
+    #[arg(short, long, value_enum)]
+    kind: Vec<ChartKind>,
+    /// Image formats to write. Can be repeated (defaults to the config)
+    #[arg(short = 'F', long, value_enum)]
+    format: Vec<ChartFormat>,
+    #[arg(short, long, value_name = "DIR",
+        help = show_default(&Config::default().main.charts.chart_dir.display(), "Directory to write the images to"))]
+    dir: Option<PathBuf>,
+    /// How many days of observations to show in the history chart
+    #[arg(short = 'D', long)]
+    days: Option<u32>,
+}
+
+fn chart(config: &Config, args: &ChartArgs) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    if args.check {
+        update_if_due(&mut locations, &client, &database)?;
+    }
+
+    let mut opts = config.main.charts.clone();
+    if !args.kind.is_empty() {
+        opts.kinds.clone_from(&args.kind);
+    }
+    if !args.format.is_empty() {
+        opts.formats.clone_from(&args.format);
+    }
+    if let Some(dir) = &args.dir {
+        opts.chart_dir.clone_from(dir);
+    }
+    if let Some(days) = args.days {
+        opts.history_days = days;
+    }
+    for location in &locations {
+        for path in update_charts(location, &database, &config.main.units, &opts)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// The view named on the command line, or the one named default if it's in the config
+fn selected_view<'a, T>(name: &'a Option<String>, views: &BTreeMap<String, T>) -> Option<&'a str> {
+    match name {
+        Some(name) => Some(name),
+        None => views.contains_key("default").then_some("default"),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+    /// Newline-delimited JSON with one object per line
+    Ndjson,
+}
+
+/// Print rows in one of the machine-readable formats. Tables are built by each command
+fn print_rows<T: Serialize>(rows: impl IntoIterator<Item = T>, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => unreachable!("tables are printed by each command"),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Json => {
+            let rows: Vec<T> = rows.into_iter().collect();
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+        OutputFormat::Ndjson => {
+            for row in rows {
+                println!("{}", serde_json::to_string(&row)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn dashboard(config: &Config) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    if !std::io::stdout().is_terminal() {
+        return Err(anyhow!("The dashboard needs to be run in a terminal"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    Dashboard::new(config, &client, &database, locations)?.run()
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+pub struct HistoryArgs {
+    /// Check for updates if due
+    #[arg(short, long)]
+    check: bool,
+    /// First day to show e.g. 2024-01-31 [default: yesterday]
+    #[arg(short, long)]
+    start: Option<NaiveDate>,
+    /// Last day to show e.g. 2024-02-01 [default: today]
+    #[arg(short, long)]
+    end: Option<NaiveDate>,
+    /// Output format
+    #[arg(short = 'F', long, default_value_t, value_enum)]
+    format: OutputFormat,
+}
+
+fn history(config: &Config, args: &HistoryArgs) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let units = &config.main.units;
+    let colours = Colours::new(&config.main.colours);
+    if args.check {
+        update_if_due(&mut locations, &client, &database)?;
+    }
+
+    let mut archives = Vec::with_capacity(locations.len());
+    for location in &locations {
+        let today = location.today();
+        let start = args.start.unwrap_or(today - Duration::days(1));
+        let end = args.end.unwrap_or(today);
+        if start > end {
+            return Err(anyhow!("Start date {start} is after end date {end}"));
+        }
+        let start_time = start_of_day(start, location.timezone);
+        let end_time =
+            start_of_day(end + Duration::days(1), location.timezone) - Duration::seconds(1);
+        let observations = database.get_observations(&location.id, start_time, end_time)?;
+        archives.push((location, start, end, observations));
+    }
+    let rows = archives.iter().flat_map(|(location, _, _, observations)| {
+        observations
+            .iter()
+            .map(|obs| ObservationRow::new(location, obs, units))
+    });
+
+    match args.format {
+        OutputFormat::Table => {
+            for (location, start, end, observations) in &archives {
+                let mut table = Table::new();
+                // Only stations with a 72-hour product have the extra fields
+                let show_extra = observations.iter().any(|o| o.has_extra());
+                let columns = if show_extra {
+                    vec![
+                        "Time",
+                        "Temp",
+                        "Feels",
+                        "Dew point",
+                        "Humidity",
+                        "Pressure",
+                        "Wind",
+                        "Gust",
+                        "Rain",
+                        "Cloud",
+                        "Visibility",
+                    ]
+                } else {
+                    vec!["Time", "Temp", "Feels", "Humidity", "Wind", "Gust", "Rain"]
+                };
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_content_arrangement(ContentArrangement::Dynamic)
+                    .set_header(columns);
+                for obs in observations {
+                    let row = ObservationRow::new(location, obs, units);
+                    let wind = if let Some(direction) = row.wind_direction {
+                        format!("{} {}", row.wind_speed, direction)
+                    } else {
+                        row.wind_speed.to_string()
+                    };
+                    let rain = row
+                        .rain_since_9am
+                        .map_or(String::new(), |r| format!("{r}{}", units.rain_symbol()));
+                    let time = Cell::new(row.time.format("%a %d %b %H:%M"));
+                    let humidity = Cell::new(format!("{}%", row.humidity));
+                    let temp = colours.cell(row.temp, colours.temp(obs.temp));
+                    let feels_like =
+                        colours.cell(row.temp_feels_like, colours.temp(obs.temp_feels_like));
+                    let gust = colours.cell(row.gust_speed, colours.gust(obs.gust.speed_kilometre));
+                    let cells = if show_extra {
+                        let optional = |v: Option<String>| Cell::new(v.unwrap_or_default());
+                        vec![
+                            time,
+                            temp,
+                            feels_like,
+                            optional(row.dew_point.map(|d| d.to_string())),
+                            humidity,
+                            optional(row.pressure.map(|p| format!("{p}hPa"))),
+                            Cell::new(wind),
+                            gust,
+                            Cell::new(rain),
+                            optional(row.cloud.map(|c| c.to_string())),
+                            optional(row.visibility.map(|v| format!("{v}km"))),
+                        ]
+                    } else {
+                        vec![
+                            time,
+                            temp,
+                            feels_like,
+                            humidity,
+                            Cell::new(wind),
+                            gust,
+                            Cell::new(rain),
+                        ]
+                    };
+                    table.add_row(cells);
+                }
+                println!("Observations for {location} from {start} to {end}");
+                println!("{table}");
+            }
+        }
+        format => print_rows(rows, format)?,
+    }
+    Ok(())
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+pub struct SummaryArgs {
+    /// Check for updates if due
+    #[arg(short, long)]
+    check: bool,
+    /// The period each row summarises
+    #[arg(short, long, default_value_t, value_enum)]
+    period: SummaryPeriod,
+    /// First day to include e.g. 2024-01-31 [default: depends on the period]
+    #[arg(short, long)]
+    start: Option<NaiveDate>,
+    /// Last day to include e.g. 2024-02-01 [default: today]
+    #[arg(short, long)]
+    end: Option<NaiveDate>,
+    /// Recalculate the summaries from the stored observations
+    #[arg(short, long)]
+    rebuild: bool,
+    /// Output format
+    #[arg(short = 'F', long, default_value_t, value_enum)]
+    format: OutputFormat,
+}
+
+fn summary(config: &Config, args: &SummaryArgs) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let units = &config.main.units;
+    if args.check {
+        update_if_due(&mut locations, &client, &database)?;
+    }
+
+    let mut all_summaries = Vec::with_capacity(locations.len());
+    for location in &locations {
+        let end = args.end.unwrap_or(location.today());
+        let start = args.start.unwrap_or(args.period.default_start(end));
+        if start > end {
+            return Err(anyhow!("Start date {start} is after end date {end}"));
+        }
+        let start = args.period.start_of(start);
+        if args.rebuild {
+            info!("Recalculating summaries for {} from {start}", location.id);
+            update_summaries(location, &database, start)?;
+        }
+        let days = database.get_daily_summaries(&location.id, start, end)?;
+        all_summaries.push((location, start, end, args.period.group(&days)));
+    }
+
+    let rows = all_summaries
+        .iter()
+        .flat_map(|(location, _, _, summaries)| {
+            summaries
+                .iter()
+                .map(|summary| SummaryRow::new(location, summary, units))
+        });
+
+    match args.format {
+        OutputFormat::Table => {
+            let time_format = match args.period {
+                SummaryPeriod::Day => "%H:%M",
+                _ => "%a %d %H:%M",
+            };
+            let format_time = |time: Option<DateTime<Tz>>| {
+                time.map_or(String::new(), |t| format!(" ({})", t.format(time_format)))
+            };
+            for (location, start, end, summaries) in &all_summaries {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_content_arrangement(ContentArrangement::Dynamic)
+                    .set_header(vec![
+                        args.period.to_string().as_str(),
+                        "Min",
+                        "Max",
+                        "Rain",
+                        "Max gust",
+                        "Humidity",
+                    ]);
+                for summary in summaries {
+                    let summary = SummaryRow::new(location, summary, units);
+                    let min = summary.min_temp.map_or(String::new(), |t| {
+                        format!("{t}{}", format_time(summary.min_temp_time))
+                    });
+                    let max = summary.max_temp.map_or(String::new(), |t| {
+                        format!("{t}{}", format_time(summary.max_temp_time))
+                    });
+                    let rain = summary
+                        .rain
+                        .map_or(String::new(), |r| format!("{r}{}", units.rain_symbol()));
+                    let gust = summary.max_gust.map_or(String::new(), |g| {
+                        let direction = summary.max_gust_direction.unwrap_or("");
+                        format!("{g} {direction}{}", format_time(summary.max_gust_time))
+                    });
+                    let humidity = summary
+                        .mean_humidity
+                        .map_or(String::new(), |h| format!("{h:.0}%"));
+                    table.add_row(vec![
+                        Cell::new(args.period.label(summary.start)),
+                        Cell::new(min),
+                        Cell::new(max),
+                        Cell::new(rain),
+                        Cell::new(gust),
+                        Cell::new(humidity),
+                    ]);
+                }
+                println!("Summary for {location} from {start} to {end}");
+                println!("{table}");
+            }
+        }
+        format => print_rows(rows, format)?,
+    }
+    Ok(())
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+pub struct VerifyArgs {
+    /// First day to verify e.g. 2024-01-31 [default: 30 days ago]
+    #[arg(short, long)]
+    start: Option<NaiveDate>,
+    /// Last day to verify e.g. 2024-02-01 [default: yesterday]
+    #[arg(short, long)]
+    end: Option<NaiveDate>,
+    /// Output format (CSV is not supported)
+    #[arg(short = 'F', long, default_value_t, value_enum)]
+    format: OutputFormat,
+}
+
+fn verify(config: &Config, args: &VerifyArgs) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    if let OutputFormat::Csv = args.format {
+        return Err(anyhow!("CSV output is not supported by the verify command"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let units = &config.main.units;
+
+    let mut verifications = Vec::with_capacity(locations.len());
+    for location in &locations {
+        let end = args.end.unwrap_or(location.today() - Duration::days(1));
+        let start = args.start.unwrap_or(end - Duration::days(29));
+        if start > end {
+            return Err(anyhow!("Start date {start} is after end date {end}"));
+        }
+        let mut verification = verify_forecasts(location, &database, start, end)?;
+        verification.convert_units(units);
+        verifications.push((location, verification));
+    }
+
+    if let OutputFormat::Json | OutputFormat::Ndjson = args.format {
+        return print_rows(verifications.iter().map(|(_, v)| v), args.format);
+    }
+
+    let new_table = |header: Vec<&str>| {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(header);
+        table
+    };
+    let error_cells = |stats: Option<&ErrorStats>| match stats {
+        Some(s) => vec![
+            Cell::new(s.count),
+            Cell::new(format!("{:+.1}", s.bias)),
+            Cell::new(format!("{:.1}", s.mean_absolute_error)),
+        ],
+        None => vec![Cell::new(0), Cell::new(""), Cell::new("")],
+    };
+
+    for (location, verification) in &verifications {
+        println!(
+            "Forecast accuracy for {location} from {} to {}",
+            verification.start, verification.end
+        );
+        let mut table = new_table(vec![
+            "Lead",
+            "Max count",
+            "Max bias",
+            "Max error",
+            "Min count",
+            "Min bias",
+            "Min error",
+        ]);
+        let mut leads: Vec<&i64> = verification.daily_max.keys().collect();
+        leads.extend(verification.daily_min.keys());
+        leads.sort();
+        leads.dedup();
+        for lead in leads {
+            let label = match lead {
+                0 => "Same day".to_string(),
+                1 => "1 day".to_string(),
+                n => format!("{n} days"),
+            };
+            let mut row = vec![Cell::new(label)];
+            row.extend(error_cells(verification.daily_max.get(lead)));
+            row.extend(error_cells(verification.daily_min.get(lead)));
+            table.add_row(row);
+        }
+        println!("Daily temperature");
+        println!("{table}");
+
+        let mut table = new_table(vec!["Lead", "Count", "Bias", "Error"]);
+        for (group, stats) in &verification.hourly_temp {
+            let mut row = vec![Cell::new(Verification::hourly_lead_label(*group))];
+            row.extend(error_cells(Some(stats)));
+            table.add_row(row);
+        }
+        println!("Hourly temperature");
+        println!("{table}");
+
+        let mut table = new_table(vec!["Chance", "Days", "Mean chance", "Rained"]);
+        for bucket in verification.rain_chance.iter().filter(|b| b.count > 0) {
+            table.add_row(vec![
+                Cell::new(format!("{}-{}%", bucket.min_chance, bucket.max_chance)),
+                Cell::new(bucket.count),
+                Cell::new(format!("{:.0}%", bucket.mean_chance)),
+                Cell::new(format!("{:.0}%", bucket.observed_frequency)),
+            ]);
+        }
+        println!("Daily rain chance");
+        println!("{table}");
+    }
+    Ok(())
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+pub struct ChangesArgs {
+    /// Check for updates if due
+    #[arg(short, long)]
+    check: bool,
+    /// Include forecasts issued from this day e.g. 2024-01-31 [default: 2 days ago]
+    #[arg(short, long)]
+    start: Option<NaiveDate>,
+    /// Include forecasts issued up to this day e.g. 2024-02-01 [default: today]
+    #[arg(short, long)]
+    end: Option<NaiveDate>,
+    /// Include changes to the short description
+    #[arg(short, long)]
+    text: bool,
+    /// Output format
+    #[arg(short = 'F', long, default_value_t, value_enum)]
+    format: OutputFormat,
+}
+
+fn changes(config: &Config, args: &ChangesArgs) -> Result<()> {
+    if config.main.locations.is_empty() {
+        return Err(anyhow!("No locations specified"));
+    }
+    let client = config.get_client();
+    let database = config.get_database()?;
+    let mut locations = ids_to_locations(&config.main.locations, &client, &database)?;
+    let units = &config.main.units;
+    if args.check {
+        update_if_due(&mut locations, &client, &database)?;
+    }
+
+    let mut all_changes = Vec::with_capacity(locations.len());
+    for location in &locations {
+        let today = location.today();
+        let start = args.start.unwrap_or(today - Duration::days(2));
+        let end = args.end.unwrap_or(today);
+        if start > end {
+            return Err(anyhow!("Start date {start} is after end date {end}"));
+        }
+        let start_time = start_of_day(start, location.timezone);
+        let end_time =
+            start_of_day(end + Duration::days(1), location.timezone) - Duration::seconds(1);
+        let mut changes = database.get_forecast_changes(&location.id, start_time, end_time)?;
+        if !args.text {
+            changes.retain(|c| c.kind != ChangeKind::ShortText);
+        }
+        all_changes.push((location, start, end, changes));
+    }
+    let rows = all_changes.iter().flat_map(|(location, _, _, changes)| {
+        changes
+            .iter()
+            .map(|change| ForecastChangeRow::new(location, change, units))
+    });
+
+    match args.format {
+        OutputFormat::Table => {
+            for (location, start, end, changes) in &all_changes {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_content_arrangement(ContentArrangement::Dynamic)
+                    .set_header(vec!["Issued", "Day", "Change", "From", "To"]);
+                for change in changes {
+                    let row = ForecastChangeRow::new(location, change, units);
+                    let (label, unit) = match row.kind {
+                        ChangeKind::MaxTemp => ("Max", units.temp_symbol()),
+                        ChangeKind::MinTemp => ("Min", units.temp_symbol()),
+                        ChangeKind::RainChance => ("Rain chance", "%"),
+                        ChangeKind::RainAmount => ("Rain amount", ""),
+                        ChangeKind::ShortText => ("Description", ""),
+                    };
+                    let value = |v: &Option<String>| {
+                        v.as_ref().map_or(String::new(), |v| format!("{v}{unit}"))
+                    };
+                    table.add_row(vec![
+                        Cell::new(row.issue_time.format("%a %d %b %H:%M")),
+                        Cell::new(row.date.format("%a %d %b")),
+                        Cell::new(label),
+                        Cell::new(value(&row.old)),
+                        Cell::new(value(&row.new)),
+                    ]);
+                }
+                println!("Forecast changes for {location} issued from {start} to {end}");
+                println!("{table}");
+            }
+        }
+        format => print_rows(rows, format)?,
+    }
+    Ok(())
+}
+
 #[skip_serializing_none]
 #[derive(Parser, Debug, Deserialize, Serialize)]
 pub struct RadarArgs {
@@ -515,12 +1399,31 @@ pub struct RadarArgs {
     #[arg(short = 'a', long)]
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub create_apng: bool,
+    /// Combine all images into an animated GIF, WebP, MP4 or WebM file. MP4 and WebM need
+    /// ffmpeg. Can be specified multiple times
+    #[arg(short = 'A', long = "animation", value_enum, value_name = "FORMAT")]
+    pub animation_formats: Option<Vec<AnimationFormat>>,
+    /// Write a self-contained HTML page that plays the images as a loop
+    #[arg(short = 'H', long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub create_html: bool,
+    /// Draw the time, radar name, age or location markers on each image. Can be specified
+    /// multiple times
+    #[arg(short = 'O', long = "overlay", value_enum, value_name = "OVERLAY")]
+    pub overlays: Option<Vec<RadarOverlay>>,
     /// View the images as a loop in MPV
     #[arg(short = 'v', long)]
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub open_mpv: bool,
-    /// Time between each frame in milliseconds (applies to APNG and MPV)
-    #[arg(short = 'd', long = "frame-delay")]
+    /// View the images as a loop in the terminal
+    #[arg(short = 't', long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub open_terminal: bool,
+    /// How to draw images in the terminal (detected by default)
+    #[arg(short = 'g', long, value_enum, value_name = "PROTOCOL")]
+    pub terminal_graphics: Option<TerminalGraphics>,
+    /// Time between each frame in milliseconds (applies to animations, HTML, MPV and the terminal)
+    #[arg(short = 'd', long = "frame-delay", value_parser = clap::value_parser!(u16).range(1..))]
     pub frame_delay_ms: Option<u16>,
     /// Maximum amount of frames to create
     #[arg(short, long)]
@@ -538,26 +1441,76 @@ pub struct RadarArgs {
     #[serde(skip)]
     #[arg(short = 'M', long)]
     pub monitor: bool,
+    /// Print when rain is expected at each location, from the motion of the rain in the
+    /// rainfall images
+    #[serde(skip)]
+    #[arg(short = 'e', long)]
+    pub eta: bool,
 }
 
-fn radar(config: &Config, monitor: bool) -> Result<()> {
+fn radar(config: &Config, monitor: bool, eta: bool) -> Result<()> {
     let mut db = config.get_database()?;
     let mut ftp = FtpClient::new()?;
     let mut managers = Vec::new();
     for radar in &config.main.radars {
         info!("Fetching radar images for {}", &radar.name);
-        managers.extend(get_radar_image_managers(
-            radar.id,
-            &mut db,
-            &mut ftp,
-            &radar.opts,
-        )?);
+        let mut radar_managers =
+            get_radar_image_managers(radar.id, &mut db, &mut ftp, &radar.opts)?;
+        if !radar.opts.overlays.is_empty() {
+            let info = radar_overlay_info(config, &db, &radar.name, radar.id)?;
+            for manager in &mut radar_managers {
+                manager.set_overlay_info(info.clone());
+            }
+        }
+        managers.extend(radar_managers);
+    }
+    for mosaic in &config.main.mosaics {
+        info!("Fetching radar images for the {} mosaic", &mosaic.name);
+        let mut mosaic_managers = get_mosaic_image_managers(mosaic, &mut db, &mut ftp)?;
+        if !mosaic.opts.overlays.is_empty() {
+            let info = radar_overlay_info(config, &db, &mosaic.name, mosaic.radars[0])?;
+            for manager in &mut mosaic_managers {
+                manager.set_overlay_info(info.clone());
+            }
+        }
+        managers.extend(mosaic_managers);
     }
 
     let mut next_check = update_radar_images(&mut managers, &mut db, &mut ftp)?;
     manage_radar_images(&mut managers, &mut db)?;
 
+    let mut viewer = match managers.iter().find(|m| m.opts.open_terminal) {
+        Some(_) if !std::io::stdout().is_terminal() => {
+            return Err(anyhow!("Radar images can only be viewed in a terminal"));
+        }
+        Some(manager) => {
+            let locations = db.get_locations(&config.main.locations)?;
+            Some(TerminalRadarViewer::new(
+                manager.opts.terminal_graphics,
+                locations.first().map(|l| l.timezone),
+            )?)
+        }
+        None => None,
+    };
+    // Only worked out again when there's a new image
+    let newest_times = |managers: &[RadarImageManager]| -> Vec<Option<DateTime<Utc>>> {
+        managers.iter().map(|m| m.newest_data_time()).collect()
+    };
+    let mut newest = newest_times(&managers);
+    save_nowcasts(config, &db);
+
+    // The ETA is only printed when it changes, and not over the terminal viewer
+    let eta = eta && viewer.is_none();
+    let mut last_eta = String::new();
+    if eta {
+        last_eta = rain_eta(config, &db)?;
+        println!("{last_eta}");
+    }
+
     if !monitor {
+        if let Some(viewer) = &mut viewer {
+            viewer.play(&managers, None)?;
+        }
         return Ok(());
     }
 
@@ -569,16 +1522,109 @@ fn radar(config: &Config, monitor: bool) -> Result<()> {
             "Next check for radar images in {} seconds",
             sleep_duration.num_seconds()
         );
-        if sleep_duration > Duration::seconds(0) {
+        if let Some(viewer) = &mut viewer {
+            if !viewer.play(&managers, Some(Utc::now() + sleep_duration))? {
+                return Ok(());
+            }
+        } else if sleep_duration > Duration::seconds(0) {
             sleep(sleep_duration.to_std().unwrap());
         }
         next_check = update_radar_images(&mut managers, &mut db, &mut ftp)?;
         manage_radar_images(&mut managers, &mut db)?;
+        if newest_times(&managers) != newest {
+            newest = newest_times(&managers);
+            save_nowcasts(config, &db);
+        }
+        if eta {
+            let output = rain_eta(config, &db)?;
+            if output != last_eta {
+                println!("{output}");
+                last_eta = output;
+            }
+        }
     }
 }
 
-fn manage_radar_images(managers: &mut Vec<RadarImageManager>, db: &mut Database) -> Result<()> {
-    for manager in managers {
+/// Errors are logged so the radar images keep updating
+fn save_nowcasts(config: &Config, db: &Database) {
+    let result = db
+        .get_locations(&config.main.locations)
+        .and_then(|locations| {
+            update_nowcasts(&locations, db, &config.main.radars, &config.main.nowcast)
+        });
+    if let Err(e) = result {
+        error!("Unable to check the radar images for rain. {e}");
+    }
+}
+
+/// A line for each location e.g. "Canberra: rain in about 20 min (14:26), moving SE at 35 km/h"
+fn rain_eta(config: &Config, db: &Database) -> Result<String> {
+    let units = config.main.units;
+    let opts = &config.main.nowcast;
+    let mut lines = Vec::new();
+    for location in db.get_locations(&config.main.locations)? {
+        let nowcast = latest_nowcast(&location, db, opts)?;
+        let Some(nowcast) = nowcast else {
+            lines.push(format!(
+                "{}: no recent rainfall radar images",
+                location.name
+            ));
+            continue;
+        };
+        let mut line = match nowcast.arrival {
+            _ if nowcast.is_raining() => format!(
+                "{}: raining now ({}{}/h)",
+                location.name,
+                units.format_rain(nowcast.rain_rate),
+                units.rain_symbol()
+            ),
+            Some(arrival) => format!(
+                "{}: rain in about {} min ({})",
+                location.name,
+                (arrival - Utc::now()).num_minutes().max(0),
+                arrival.with_timezone(&location.timezone).format("%H:%M")
+            ),
+            None => format!(
+                "{}: no rain expected in the next {} min",
+                location.name, opts.max_lead_minutes
+            ),
+        };
+        if let Some(motion) = nowcast.motion {
+            line.push_str(&format!(
+                ", moving {} at {} {}",
+                compass_point(motion.bearing),
+                units.wind_kmh(motion.speed_kmh as f32),
+                units.wind_symbol()
+            ));
+        }
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+fn radar_overlay_info(
+    config: &Config,
+    db: &Database,
+    name: &str,
+    id: RadarId,
+) -> Result<RadarOverlayInfo> {
+    let details = db.get_radar(id)?;
+    let locations = db.get_locations(&config.main.locations)?;
+    Ok(RadarOverlayInfo {
+        name: name.to_string(),
+        latitude: details.latitude as f64,
+        longitude: details.longitude as f64,
+        timezone: locations.first().map(|l| l.timezone),
+        markers: locations
+            .iter()
+            .map(|l| (l.name.clone(), l.latitude, l.longitude))
+            .collect(),
+    })
+}
+
+fn manage_radar_images(managers: &mut [RadarImageManager], db: &mut Database) -> Result<()> {
+    let mut removed = Vec::new();
+    for manager in managers.iter_mut() {
         if manager.opts.create_png {
             info!(
                 "Writing radar PNG files to {}",
@@ -593,12 +1639,33 @@ fn manage_radar_images(managers: &mut Vec<RadarImageManager>, db: &mut Database)
             );
             manager.create_apng()?;
         }
+        for format in manager.opts.animation_formats.clone() {
+            info!(
+                "Writing radar {} file to {}",
+                format.as_ref().to_uppercase(),
+                &manager.opts.image_dir.display()
+            );
+            manager.create_animation(format)?;
+        }
+        if manager.opts.create_html {
+            info!(
+                "Writing radar HTML file to {}",
+                &manager.opts.image_dir.display()
+            );
+            manager.create_html()?;
+        }
         if manager.opts.open_mpv {
             info!("Opening radar images in MPV");
             manager.open_images()?;
         }
-        let removed = manager.prune()?;
-        db.delete_radar_data_layers(&removed)?;
+        if manager.opts.open_terminal {
+            manager.construct_frames()?;
+        }
+        removed.extend(manager.prune()?);
     }
+    // A radar's images can be shared by a mosaic and the radar on its own, and the nowcasts
+    // read them from the database too, so only delete the ones no manager uses any more
+    removed.retain(|layer| !managers.iter().any(|m| m.has_data_layer(layer)));
+    db.delete_radar_data_layers(&removed)?;
     Ok(())
 }
@@ -201,6 +201,8 @@ impl Client {
             next_daily_due,
             next_hourly_due,
             next_warning_due,
+            next_past_observations_due: now,
+            daily_changes: Vec::new(),
             opts,
         })
     }
@@ -0,0 +1,165 @@
+use crate::daily::FireDangerCategory;
+use anyhow::{anyhow, Result};
+use comfy_table::{Cell, Color};
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::fmt::{self, Display};
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// A colour written as a hex code e.g. #FF5F5F
+#[derive(Clone, Copy, Debug, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || anyhow!("{s} is not a valid colour. Expected a hex code e.g. #FF5F5F");
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(err());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| err());
+        Ok(Self(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(rgb: Rgb) -> Self {
+        Color::Rgb {
+            r: rgb.0,
+            g: rgb.1,
+            b: rgb.2,
+        }
+    }
+}
+
+/// Values at or above the minimum are shown in the colour
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Threshold {
+    pub min: f32,
+    pub colour: Rgb,
+}
+
+/// A point on the temperature gradient
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct GradientStop {
+    pub temp: f32,
+    pub colour: Rgb,
+}
+
+/// Colours for table cells. Thresholds use °C, km/h and % regardless of the display units
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ColourOptions {
+    pub enabled: bool,
+    /// Temperatures are coloured by blending between the nearest stops, coldest first
+    pub temp_gradient: Vec<GradientStop>,
+    pub rain_chance: Threshold,
+    pub gust: Threshold,
+    pub uv: Threshold,
+}
+
+impl Default for ColourOptions {
+    fn default() -> Self {
+        let stop = |temp, colour| GradientStop {
+            temp,
+            colour: Rgb::from_str(colour).unwrap(),
+        };
+        let threshold = |min, colour| Threshold {
+            min,
+            colour: Rgb::from_str(colour).unwrap(),
+        };
+        Self {
+            enabled: true,
+            temp_gradient: vec![
+                stop(0.0, "#5F87FF"),
+                stop(15.0, "#5FD7AF"),
+                stop(25.0, "#FFD75F"),
+                stop(35.0, "#FF5F5F"),
+                stop(45.0, "#D75FD7"),
+            ],
+            rain_chance: threshold(50.0, "#5FAFFF"),
+            gust: threshold(60.0, "#FF8700"),
+            // The BOM's extreme category
+            uv: threshold(11.0, "#AF5FD7"),
+        }
+    }
+}
+
+/// Picks cell colours, or none at all if colour is disabled, stdout isn't a terminal or
+/// NO_COLOR is set (https://no-color.org)
+pub struct Colours<'a> {
+    opts: Option<&'a ColourOptions>,
+}
+
+impl<'a> Colours<'a> {
+    pub fn new(opts: &'a ColourOptions) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let enabled = opts.enabled && !no_color && std::io::stdout().is_terminal();
+        Self {
+            opts: enabled.then_some(opts),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.opts.is_some()
+    }
+
+    pub fn temp(&self, celsius: f32) -> Option<Color> {
+        let stops = &self.opts?.temp_gradient;
+        let first = stops.first()?;
+        if celsius <= first.temp {
+            return Some(first.colour.into());
+        }
+        for pair in stops.windows(2) {
+            let (low, high) = (pair[0], pair[1]);
+            if celsius <= high.temp {
+                let t = (celsius - low.temp) / (high.temp - low.temp);
+                let blend = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+                let (a, b) = (low.colour, high.colour);
+                return Some(Rgb(blend(a.0, b.0), blend(a.1, b.1), blend(a.2, b.2)).into());
+            }
+        }
+        stops.last().map(|stop| stop.colour.into())
+    }
+
+    pub fn rain_chance(&self, chance: u8) -> Option<Color> {
+        threshold(&self.opts?.rain_chance, chance as f32)
+    }
+
+    pub fn gust(&self, kmh: u8) -> Option<Color> {
+        threshold(&self.opts?.gust, kmh as f32)
+    }
+
+    pub fn uv(&self, index: u8) -> Option<Color> {
+        threshold(&self.opts?.uv, index as f32)
+    }
+
+    /// The colour the BOM uses for the fire danger rating
+    pub fn fire_danger(&self, category: &FireDangerCategory) -> Option<Color> {
+        self.opts?;
+        let colour = category.default_colour.as_deref()?;
+        Rgb::from_str(colour).ok().map(Into::into)
+    }
+
+    /// Apply the colour to the cell if there is one
+    pub fn cell<T: ToString>(&self, content: T, colour: Option<Color>) -> Cell {
+        let cell = Cell::new(content);
+        match colour {
+            Some(colour) => cell.fg(colour),
+            None => cell,
+        }
+    }
+}
+
+fn threshold(threshold: &Threshold, value: f32) -> Option<Color> {
+    (value >= threshold.min).then(|| threshold.colour.into())
+}
@@ -1,8 +1,16 @@
+use crate::chart::ChartOptions;
 use crate::cli::{Cli, Commands};
 use crate::client::{Client, ClientOptions};
+use crate::colour::ColourOptions;
+use crate::dashboard::DashboardOptions;
+use crate::descriptor::IconOptions;
+use crate::nowcast::NowcastOptions;
 use crate::persistence::Database;
 use crate::radar::{Radar, RadarId, RadarImageOptions};
+use crate::statusbar::StatusBarOptions;
+use crate::units::Units;
 use crate::util::remove_if_exists;
+use crate::views::TableViews;
 use crate::{location::Location, logging::LoggingOptions};
 use anyhow::{anyhow, Result};
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
@@ -37,7 +45,16 @@ pub struct MainConfig {
     pub logging: LoggingOptions,
     pub client: ClientOptions,
     pub radars: Vec<RadarConfig>,
+    pub mosaics: Vec<MosaicConfig>,
     pub current_fstring: String,
+    pub status_bar: StatusBarOptions,
+    pub units: Units,
+    pub icons: IconOptions,
+    pub views: TableViews,
+    pub colours: ColourOptions,
+    pub charts: ChartOptions,
+    pub dashboard: DashboardOptions,
+    pub nowcast: NowcastOptions,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -47,6 +64,15 @@ pub struct RadarConfig {
     pub opts: RadarImageOptions,
 }
 
+/// Radars whose images are combined into one loop
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MosaicConfig {
+    pub name: String,
+    pub radars: Vec<RadarId>,
+    #[serde(default)]
+    pub opts: RadarImageOptions,
+}
+
 impl Default for MainConfig {
     fn default() -> Self {
         Self {
@@ -54,8 +80,17 @@ impl Default for MainConfig {
             logging: LoggingOptions::default(),
             client: ClientOptions::default(),
             radars: Vec::new(),
+            mosaics: Vec::new(),
             locations: Vec::new(),
             current_fstring: "{icon} {temp} ({next_temp})".to_string(),
+            status_bar: StatusBarOptions::default(),
+            units: Units::default(),
+            icons: IconOptions::default(),
+            views: TableViews::default(),
+            colours: ColourOptions::default(),
+            charts: ChartOptions::default(),
+            dashboard: DashboardOptions::default(),
+            nowcast: NowcastOptions::default(),
         }
     }
 }
@@ -121,13 +156,25 @@ impl Config {
                 let arg_opts = serde_json::to_value(rargs)?;
                 let mut radar_array: serde_json::Value = main.extract_inner("radars")?;
                 override_array_opts(&mut radar_array, &arg_opts);
+                let mut mosaic_array: serde_json::Value = main.extract_inner("mosaics")?;
+                override_array_opts(&mut mosaic_array, &arg_opts);
                 main.merge(("radars", radar_array))
+                    .merge(("mosaics", mosaic_array))
             }
             _ => main,
         };
 
         let mut main: MainConfig = main.extract()?;
 
+        let radar_opts = main.radars.iter().map(|r| (&r.name, &r.opts));
+        let mosaic_opts = main.mosaics.iter().map(|m| (&m.name, &m.opts));
+        if let Some((name, _)) = radar_opts
+            .chain(mosaic_opts)
+            .find(|(_, opts)| opts.frame_delay_ms == 0)
+        {
+            return Err(anyhow!("The frame delay for {name} must be at least 1 ms"));
+        }
+
         if let Some(level) = args.log_level {
             main.logging.console_level = level;
         }
@@ -137,6 +184,18 @@ impl Config {
         if let Some(path) = &args.log_path {
             main.logging.file_path = path.clone();
         }
+        if let Some(unit) = args.temp_unit {
+            main.units.temperature = unit;
+        }
+        if let Some(unit) = args.wind_unit {
+            main.units.wind = unit;
+        }
+        if let Some(unit) = args.rain_unit {
+            main.units.rain = unit;
+        }
+        if let Some(theme) = args.icon_theme {
+            main.icons.theme = theme;
+        }
         Ok(Config { config_path, main })
     }
 
@@ -206,16 +265,18 @@ pub struct DefaultDirs {
 fn override_array_opts(config_array: &mut serde_json::Value, arg_opts: &serde_json::Value) {
     let config_array = config_array.as_array_mut().unwrap();
     for element in &mut *config_array {
-        let Some(conf_opts) = element.get_mut("opts") else {
+        let Some(element) = element.as_object_mut() else {
             continue;
         };
+        // Mosaics are written by hand, so their options can be missing
+        let conf_opts = element
+            .entry("opts")
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
         let Some(conf_opts) = conf_opts.as_object_mut() else {
             continue;
         };
         for (key, arg_value) in arg_opts.as_object().unwrap() {
-            if let Some(conf_value) = conf_opts.get_mut(key) {
-                *conf_value = arg_value.clone();
-            }
+            conf_opts.insert(key.clone(), arg_value.clone());
         }
     }
 }
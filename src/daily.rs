@@ -1,5 +1,9 @@
-use crate::descriptor::IconDescriptor;
+use crate::descriptor::{IconDescriptor, IconOptions};
+use crate::location::Location;
+use crate::units::Units;
+use crate::util::local_date;
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -103,3 +107,68 @@ pub struct RainAmount {
     pub max: Option<u16>,
     pub units: String,
 }
+
+/// A flattened day of the forecast used for CSV and JSON output, with values converted to the
+/// units
+#[derive(Debug, Serialize)]
+pub struct DailyRow<'a> {
+    pub location_id: &'a str,
+    pub issue_time: DateTime<Tz>,
+    pub date: NaiveDate,
+    pub temp_min: Option<f32>,
+    pub temp_max: Option<f32>,
+    pub rain_chance: Option<u8>,
+    pub rain_min: Option<f32>,
+    pub rain_max: Option<f32>,
+    pub icon_descriptor: Option<String>,
+    pub icon: Option<&'a str>,
+    pub short_text: Option<&'a str>,
+    pub extended_text: Option<&'a str>,
+    pub uv_category: Option<&'a str>,
+    pub uv_max_index: Option<u8>,
+    pub sun_protection_start: Option<DateTime<Tz>>,
+    pub sun_protection_end: Option<DateTime<Tz>>,
+    pub sunrise: DateTime<Tz>,
+    pub sunset: DateTime<Tz>,
+    pub fire_danger: Option<&'a str>,
+}
+
+impl<'a> DailyRow<'a> {
+    pub fn new(
+        location: &'a Location,
+        day: &'a DailyForecastData,
+        units: &Units,
+        icons: &'a IconOptions,
+    ) -> Self {
+        let tz = location.timezone;
+        let rain = |mm: Option<u16>| mm.map(|mm| units.rain(mm as f32));
+        Self {
+            location_id: &location.id,
+            issue_time: location
+                .weather
+                .daily_forecast
+                .issue_time
+                .with_timezone(&tz),
+            date: local_date(day.date, tz),
+            temp_min: day.temp_min.map(|t| units.temp(t)),
+            temp_max: day.temp_max.map(|t| units.temp(t)),
+            rain_chance: day.rain.chance,
+            rain_min: rain(day.rain.amount.lower_range),
+            rain_max: rain(day.rain.amount.max),
+            icon_descriptor: day.icon_descriptor.as_ref().map(|i| i.name()),
+            icon: day.icon_descriptor.as_ref().map(|i| icons.icon(i, false)),
+            short_text: day.short_text.as_deref(),
+            extended_text: day.extended_text.as_deref(),
+            uv_category: day.uv.category.as_deref(),
+            uv_max_index: day.uv.max_index,
+            sun_protection_start: day.uv.start_time.map(|t| t.with_timezone(&tz)),
+            sun_protection_end: day.uv.end_time.map(|t| t.with_timezone(&tz)),
+            sunrise: day.astronomical.sunrise_time.with_timezone(&tz),
+            sunset: day.astronomical.sunset_time.with_timezone(&tz),
+            fire_danger: day
+                .fire_danger
+                .as_deref()
+                .or(day.fire_danger_category.text.as_deref()),
+        }
+    }
+}
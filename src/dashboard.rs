@@ -0,0 +1,394 @@
+use crate::client::Client;
+use crate::colour::Colours;
+use crate::config::Config;
+use crate::fstring::Fstring;
+use crate::hourly::HourlyForecastData;
+use crate::location::Location;
+use crate::nowcast::Nowcast;
+use crate::persistence::Database;
+use crate::services::{latest_nowcast, update_if_due};
+use crate::summary::{rain_totals, SummaryPeriod};
+use crate::terminal_graphics::TerminalGuard;
+use crate::util::format_duration;
+use crate::views::{Column, View};
+use crate::weather::{CurrentWeather, DailyKey, HourlyKey};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{ContentArrangement, Table};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
+use crossterm::{cursor, queue, terminal};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use tracing::error;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// What the dashboard shows for each location
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DashboardOptions {
+    /// Format strings for the current conditions, one per line
+    pub current: Vec<String>,
+    /// The rows of the hourly strip, with one column per hour
+    pub hourly: Vec<Column>,
+    /// The columns of the 7-day outlook table
+    pub daily: Vec<Column>,
+    /// The most hours shown in the hourly strip, if they fit
+    pub hours: usize,
+}
+
+impl Default for DashboardOptions {
+    fn default() -> Self {
+        Self {
+            current: vec![
+                "{icon} {temp}{temp_unit} feels like {temp_feels_like}{temp_unit}  {short_text}"
+                    .to_string(),
+                "Max {max_temp}  {next_label} {next_temp}  {later_label} {later_temp}".to_string(),
+                "Wind {wind_speed} {wind_direction} gusting {wind_gust} {wind_unit}".to_string(),
+                "Rain {rain_since_9am|0}{rain_unit} since 9am  {today_rain_chance}% chance of {today_rain_min}-{today_rain_max}{rain_unit} today"
+                    .to_string(),
+                "Sunrise {sunrise}  Sunset {sunset}  UV {uv_category|-}".to_string(),
+            ],
+            hourly: vec![
+                Column::new("", "{time}"),
+                Column::new("", "{icon}"),
+                Column::new("Temp", "{temp}{temp_unit}"),
+                Column::new("Rain", "{rain_chance}%"),
+                Column::new("Wind", "{wind_speed}"),
+            ],
+            daily: vec![
+                Column::new("Day", "{day} {date}"),
+                Column::new("Min", "{min|}"),
+                Column::new("Max", "{max|}"),
+                Column::new("Chance", "{rain_chance|0}%"),
+                Column::new("Rain", "{rain_min|0}-{rain_max|0}"),
+                Column::new("Description", "{icon} {short_text|}"),
+            ],
+            hours: 24,
+        }
+    }
+}
+
+/// A full screen view of every location that updates in place
+pub struct Dashboard<'a> {
+    config: &'a Config,
+    client: &'a Client,
+    database: &'a Database,
+    locations: Vec<Location>,
+    selected: usize,
+    current: Vec<Fstring>,
+    hourly: View<HourlyKey>,
+    daily: View<DailyKey>,
+    colours: Colours<'a>,
+    next_check: DateTime<Utc>,
+    error: Option<String>,
+    /// Each location's rain from the radar images, as saved by the radar command
+    nowcasts: Vec<Option<Nowcast>>,
+}
+
+enum Action {
+    Quit,
+    Select(usize),
+    Redraw,
+}
+
+impl<'a> Dashboard<'a> {
+    pub fn new(
+        config: &'a Config,
+        client: &'a Client,
+        database: &'a Database,
+        locations: Vec<Location>,
+    ) -> Result<Self> {
+        let opts = &config.main.dashboard;
+        let current = opts
+            .current
+            .iter()
+            .map(|line| Fstring::parse(line))
+            .collect::<Result<_>>()
+            .context("Invalid dashboard format string")?;
+        let hourly = View::from_columns(&opts.hourly).context("Invalid dashboard hourly row")?;
+        let daily = View::from_columns(&opts.daily).context("Invalid dashboard daily column")?;
+        Ok(Self {
+            config,
+            client,
+            database,
+            locations,
+            selected: 0,
+            current,
+            hourly,
+            daily,
+            colours: Colours::new(&config.main.colours),
+            next_check: Utc::now(),
+            error: None,
+            nowcasts: Vec::new(),
+        })
+    }
+
+    /// Show the dashboard until the user quits, checking for updates when they're due
+    pub fn run(&mut self) -> Result<()> {
+        let _guard = TerminalGuard::new()?;
+        self.load_nowcasts()?;
+        self.update();
+        loop {
+            self.draw()?;
+            // Redraw at least once a minute to keep the times current
+            let until_check = (self.next_check - Utc::now()).max(Duration::zero());
+            let timeout = until_check.min(Duration::minutes(1)).to_std()?;
+            if event::poll(timeout)? {
+                match self.handle_event(event::read()?) {
+                    Action::Quit => return Ok(()),
+                    Action::Select(i) => self.selected = i,
+                    Action::Redraw => {}
+                }
+            } else {
+                self.load_nowcasts()?;
+                if Utc::now() >= self.next_check {
+                    self.update();
+                }
+            }
+        }
+    }
+
+    /// The radar images are updated more often than the weather, so these are loaded every minute
+    fn load_nowcasts(&mut self) -> Result<()> {
+        let opts = &self.config.main.nowcast;
+        self.nowcasts = self
+            .locations
+            .iter()
+            .map(|location| latest_nowcast(location, self.database, opts))
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// Check for updates like the monitor command, showing any error instead of exiting
+    fn update(&mut self) {
+        match update_if_due(&mut self.locations, self.client, self.database) {
+            Ok(next_check) => {
+                self.next_check = next_check;
+                self.error = None;
+            }
+            Err(e) => {
+                error!("Unable to update the weather. {e}");
+                self.error = Some(e.to_string());
+                self.next_check = Utc::now() + Duration::minutes(1);
+            }
+        }
+    }
+
+    fn handle_event(&self, event: Event) -> Action {
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind,
+            ..
+        }) = event
+        else {
+            return Action::Redraw;
+        };
+        if kind == KeyEventKind::Release {
+            return Action::Redraw;
+        }
+        let count = self.locations.len();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+            KeyCode::Right | KeyCode::Tab | KeyCode::Char('l') => {
+                Action::Select((self.selected + 1) % count)
+            }
+            KeyCode::Left | KeyCode::BackTab | KeyCode::Char('h') => {
+                Action::Select((self.selected + count - 1) % count)
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let i = c as usize - '1' as usize;
+                Action::Select(if i < count { i } else { self.selected })
+            }
+            _ => Action::Redraw,
+        }
+    }
+
+    fn draw(&self) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let lines = self.render(width as usize)?;
+        let mut stdout = io::stdout();
+        queue!(
+            stdout,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        let lines: Vec<String> = lines.into_iter().take(height as usize).collect();
+        // Raw mode doesn't return to the start of the line
+        write!(stdout, "{}", lines.join("\r\n"))?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn render(&self, width: usize) -> Result<Vec<String>> {
+        let location = &self.locations[self.selected];
+        let mut lines = vec![self.tabs(width), String::new()];
+
+        let issued = location
+            .weather
+            .hourly_forecast
+            .issue_time
+            .with_timezone(&location.timezone)
+            .format("%H:%M");
+        lines.push(self.heading(&format!(
+            "{location}  {}",
+            location.now().format("%a %d %b %H:%M")
+        )));
+        let mut current = location.weather.current(
+            location.timezone,
+            self.config.main.units,
+            &self.config.main.icons,
+        );
+        let today = location.today();
+        let month_start = SummaryPeriod::Month.start_of(today);
+        let summaries = self.database.get_daily_summaries(
+            &location.id,
+            month_start - Duration::days(1),
+            today,
+        )?;
+        (current.yesterday_rain, current.month_rain) = rain_totals(&location.id, &summaries, today);
+        current.nowcast = self.nowcasts.get(self.selected).cloned().flatten();
+        for fstring in &self.current {
+            lines.push(truncate(&fstring.render(&current), width));
+        }
+
+        lines.push(String::new());
+        lines.push(self.heading(&format!("Next hours (issued {issued})")));
+        let hours = current.hours.len().min(self.config.main.dashboard.hours);
+        lines.extend(self.hourly_strip(&current, &current.hours[..hours], width));
+
+        lines.push(String::new());
+        lines.push(self.heading("7-day outlook"));
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL_CONDENSED)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(width as u16)
+            .set_header(self.daily.header());
+        for day in current.days {
+            table.add_row(self.daily.row(&current.forecast_context(day)));
+        }
+        lines.extend(table.lines());
+
+        lines.push(String::new());
+        lines.push(self.heading("Warnings"));
+        if current.warnings.is_empty() {
+            lines.push("No current warnings".to_string());
+        }
+        for warning in current.warnings {
+            let line = truncate(&format!("⚠ {}", warning.title), width);
+            lines.push(self.highlight(line));
+        }
+
+        lines.push(String::new());
+        lines.push(self.footer(width));
+        Ok(lines)
+    }
+
+    /// The location names with the selected one highlighted
+    fn tabs(&self, width: usize) -> String {
+        let mut line = String::new();
+        let mut used = 0;
+        for (i, location) in self.locations.iter().enumerate() {
+            let tab = format!(" {} {} ", i + 1, location.name);
+            used += tab.width();
+            if used > width {
+                break;
+            }
+            if i == self.selected {
+                line.push_str(&format!("{}", tab.reverse()));
+            } else {
+                line.push_str(&tab);
+            }
+        }
+        line
+    }
+
+    /// One column per hour that fits in the width, with the row labels on the left
+    fn hourly_strip(
+        &self,
+        current: &CurrentWeather,
+        hours: &[HourlyForecastData],
+        width: usize,
+    ) -> Vec<String> {
+        let labels = self.hourly.header();
+        let label_width = labels.iter().map(|l| l.width()).max().unwrap_or(0) + 1;
+        let columns: Vec<Vec<String>> = hours
+            .iter()
+            .map(|hour| self.hourly.row(&current.forecast_context(hour)))
+            .collect();
+        let column_width = columns
+            .iter()
+            .flatten()
+            .map(|cell| cell.width())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let count = (width.saturating_sub(label_width) / column_width).min(columns.len());
+        labels
+            .iter()
+            .enumerate()
+            .map(|(row, label)| {
+                let mut line = pad(label, label_width);
+                for column in &columns[..count] {
+                    line.push_str(&pad(&column[row], column_width));
+                }
+                line
+            })
+            .collect()
+    }
+
+    fn footer(&self, width: usize) -> String {
+        let until = (self.next_check - Utc::now()).max(Duration::zero());
+        let status = match &self.error {
+            Some(e) => format!("Update failed: {e}"),
+            None => format!("Next check in {}", format_duration(until)),
+        };
+        let keys = if self.locations.len() > 1 {
+            "←/→ switch location  q quit"
+        } else {
+            "q quit"
+        };
+        truncate(&format!("{status}  {keys}"), width)
+    }
+
+    fn heading(&self, text: &str) -> String {
+        if self.colours.is_enabled() {
+            format!("{}", text.bold())
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn highlight(&self, text: String) -> String {
+        if self.colours.is_enabled() {
+            format!("{}", text.red().bold())
+        } else {
+            text
+        }
+    }
+}
+
+/// Left align the text in a column, allowing for wide characters like emoji
+fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text.width());
+    format!("{text}{}", " ".repeat(padding))
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    let mut output = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            break;
+        }
+        output.push(c);
+    }
+    output
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use strum_macros::AsRefStr;
 
 // https://reg.bom.gov.au/info/forecast_icons.shtml
@@ -27,9 +28,11 @@ pub enum IconDescriptor {
 }
 
 impl IconDescriptor {
-    // TODO: Add option to use nerd font weather icons. They don't look as nice but have proper
-    // icons for all descriptors at night time unlike emojis
-    // https://erikflowers.github.io/weather-icons/
+    /// The name used in the config file e.g. mostly_sunny
+    pub fn name(&self) -> String {
+        self.as_ref().to_lowercase().replace(' ', "_")
+    }
+
     pub fn get_icon_emoji(&self, is_night: bool) -> &str {
         match self {
             Self::Sunny if is_night => "🌙",
@@ -54,6 +57,35 @@ impl IconDescriptor {
         }
     }
 
+    // https://www.nerdfonts.com/cheat-sheet (nf-weather-*)
+    pub fn get_nerd_font_icon(&self, is_night: bool) -> &str {
+        match (self, is_night) {
+            (Self::Sunny | Self::Clear, false) => "\u{e30d}",
+            (Self::Sunny | Self::Clear, true) => "\u{e32b}",
+            (Self::MostlySunny, false) => "\u{e30c}",
+            (Self::PartlyCloudy, false) => "\u{e302}",
+            (Self::MostlySunny | Self::PartlyCloudy, true) => "\u{e32e}",
+            (Self::Cloudy, _) => "\u{e312}",
+            (Self::Hazy | Self::Fog, false) => "\u{e303}",
+            (Self::Hazy | Self::Fog, true) => "\u{e346}",
+            (Self::Windy, _) => "\u{e31e}",
+            (Self::LightRain | Self::LightShower, false) => "\u{e30b}",
+            (Self::LightRain | Self::LightShower, true) => "\u{e336}",
+            (Self::Shower, false) => "\u{e309}",
+            (Self::Shower, true) => "\u{e334}",
+            (Self::HeavyShower, false) => "\u{e307}",
+            (Self::HeavyShower, true) => "\u{e332}",
+            (Self::Rain, false) => "\u{e308}",
+            (Self::Rain, true) => "\u{e333}",
+            (Self::Dusty, _) => "\u{e35d}",
+            (Self::Frost | Self::Snow, false) => "\u{e30a}",
+            (Self::Frost | Self::Snow, true) => "\u{e335}",
+            (Self::Storm, false) => "\u{e30f}",
+            (Self::Storm, true) => "\u{e338}",
+            (Self::Cyclone, _) => "\u{e351}",
+        }
+    }
+
     pub fn get_description(&self, is_night: bool) -> &str {
         match self {
             Self::Sunny if is_night => "Clear",
@@ -62,3 +94,42 @@ impl IconDescriptor {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum IconTheme {
+    #[default]
+    Emoji,
+    /// Weather icons from a patched Nerd Font
+    NerdFont,
+    /// The description e.g. Mostly Clear
+    #[value(alias = "ascii")]
+    #[serde(alias = "ascii")]
+    Text,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IconOptions {
+    pub theme: IconTheme,
+    /// Icons that replace the theme's, keyed by descriptor name e.g. sunny. Add _night for the
+    /// night time variant e.g. sunny_night, otherwise the same icon is used day and night
+    pub custom: BTreeMap<String, String>,
+}
+
+impl IconOptions {
+    pub fn icon<'a>(&'a self, descriptor: &'a IconDescriptor, is_night: bool) -> &'a str {
+        let name = descriptor.name();
+        let night = is_night
+            .then(|| self.custom.get(&format!("{name}_night")))
+            .flatten();
+        if let Some(icon) = night.or_else(|| self.custom.get(&name)) {
+            return icon;
+        }
+        match self.theme {
+            IconTheme::Emoji => descriptor.get_icon_emoji(is_night),
+            IconTheme::NerdFont => descriptor.get_nerd_font_icon(is_night),
+            IconTheme::Text => descriptor.get_description(is_night),
+        }
+    }
+}
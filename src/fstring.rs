@@ -0,0 +1,719 @@
+use crate::weather::{CurrentWeather, DailyKey, FstringKey, HourlyKey};
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::str::FromStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// A parsed format string e.g. "{icon} {temp:.0}{?hourly_rain_chance>30} {hourly_rain_chance}%{/}"
+///
+/// - `{key}` is replaced with the key's value. `{{` and `}}` are literal braces
+/// - `{key:spec}` pads or rounds the value. The spec is `[[fill]align][width][.precision]`
+///   where align is `<`, `>` or `^` e.g. `{temp:.0}` or `{wind_speed:>3}`
+/// - `{key|text}` shows the text instead of `??` if the value is missing e.g. `{rain_ten|0}`
+/// - `{?condition}...{/}` only shows the enclosed section if the condition is true. Conditions
+///   are `key`, `!key` (true if the value is present and not empty or zero) or `key op value`
+///   where op is one of `>`, `>=`, `<`, `<=`, `=` or `!=`
+///
+/// The keys default to those of the current weather, but any context can provide its own
+#[derive(Debug)]
+pub struct Fstring<K = Key> {
+    segments: Vec<Segment<K>>,
+}
+
+/// Something the keys of a format string can be looked up in
+pub trait FstringContext {
+    type Key: ParseKey + Debug;
+
+    fn value(&self, key: &Self::Key) -> FstringValue<'_>;
+}
+
+impl FstringContext for CurrentWeather<'_> {
+    type Key = Key;
+
+    fn value(&self, key: &Key) -> FstringValue<'_> {
+        key.value(self)
+    }
+}
+
+#[derive(Debug)]
+enum Segment<K> {
+    Text(String),
+    Field(Field<K>),
+    Conditional(Conditional<K>),
+}
+
+/// A key for the current weather, or a field of a forecast period addressed by its offset from
+/// the current hour (hour+3.temp) or today (day2.max)
+#[derive(Debug, Clone, Copy)]
+pub enum Key {
+    Current(FstringKey),
+    Hour(usize, HourlyKey),
+    Day(usize, DailyKey),
+}
+
+/// Keys that can be used in a format string, with an error that explains what was expected
+pub trait ParseKey: Sized {
+    fn parse_key(s: &str) -> Result<Self>;
+}
+
+impl ParseKey for HourlyKey {
+    fn parse_key(s: &str) -> Result<Self> {
+        Self::from_str(s).map_err(|_| anyhow!("Unknown hourly field \"{s}\""))
+    }
+}
+
+impl ParseKey for DailyKey {
+    fn parse_key(s: &str) -> Result<Self> {
+        Self::from_str(s).map_err(|_| anyhow!("Unknown daily field \"{s}\""))
+    }
+}
+
+impl ParseKey for Key {
+    fn parse_key(s: &str) -> Result<Self> {
+        let (period, field) = match s.split_once('.') {
+            Some((period, field)) => (period, Some(field)),
+            None => (s, None),
+        };
+        if let Some(offset) = period.strip_prefix("hour+") {
+            let offset = offset
+                .parse()
+                .map_err(|_| anyhow!("Expected a number of hours after hour+"))?;
+            let field = field.ok_or_else(|| anyhow!("Expected a field e.g. hour+3.temp"))?;
+            return Ok(Self::Hour(offset, HourlyKey::parse_key(field)?));
+        }
+        if let Some(offset) = period
+            .strip_prefix("day")
+            .filter(|o| o.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let offset = offset
+                .parse()
+                .map_err(|_| anyhow!("Expected a number of days after day"))?;
+            let field = field.ok_or_else(|| anyhow!("Expected a field e.g. day2.max"))?;
+            return Ok(Self::Day(offset, DailyKey::parse_key(field)?));
+        }
+        FstringKey::from_str(s)
+            .map(Self::Current)
+            .map_err(|_| anyhow!("Unknown key \"{s}\""))
+    }
+}
+
+impl Key {
+    /// Periods beyond the end of the forecast are missing
+    pub fn value<'a>(&self, w: &'a CurrentWeather) -> FstringValue<'a> {
+        match self {
+            Self::Current(key) => key.value(w),
+            Self::Hour(offset, key) => w
+                .hours
+                .get(*offset)
+                .map_or(FstringValue::Missing, |h| key.value(&w.forecast_context(h))),
+            Self::Day(offset, key) => w
+                .days
+                .get(*offset)
+                .map_or(FstringValue::Missing, |d| key.value(&w.forecast_context(d))),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Field<K> {
+    key: K,
+    spec: FormatSpec,
+    fallback: Option<String>,
+}
+
+#[derive(Debug)]
+struct Conditional<K> {
+    key: K,
+    negate: bool,
+    comparison: Option<(Operator, String)>,
+    segments: Vec<Segment<K>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Default)]
+struct FormatSpec {
+    fill: Option<char>,
+    align: Option<Align>,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// The value of a key before it's formatted
+pub enum FstringValue<'a> {
+    Number(f32),
+    Text(Cow<'a, str>),
+    /// Shown as ?? unless a fallback is provided
+    Missing,
+}
+
+impl<'a> From<&'a str> for FstringValue<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Text(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for FstringValue<'_> {
+    fn from(value: String) -> Self {
+        Self::Text(Cow::Owned(value))
+    }
+}
+
+impl<'a, T: Into<FstringValue<'a>>> From<Option<T>> for FstringValue<'a> {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Missing, Into::into)
+    }
+}
+
+macro_rules! number_value {
+    ($($t:ty),*) => {
+        $(impl From<$t> for FstringValue<'_> {
+            fn from(value: $t) -> Self {
+                Self::Number(value as f32)
+            }
+        })*
+    };
+}
+number_value!(f32, f64, u8, u16, u32);
+
+impl FstringValue<'_> {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Number(n) => *n != 0.0,
+            Self::Text(t) => !t.is_empty(),
+            Self::Missing => false,
+        }
+    }
+
+    fn compare(&self, operator: Operator, other: &str) -> bool {
+        let ordering = match self {
+            Self::Number(n) => match other.parse::<f32>() {
+                Ok(other) => n.partial_cmp(&other),
+                Err(_) => return false,
+            },
+            Self::Text(t) => Some(t.as_ref().cmp(other)),
+            Self::Missing => return false,
+        };
+        let Some(ordering) = ordering else {
+            return false;
+        };
+        match operator {
+            Operator::Greater => ordering.is_gt(),
+            Operator::GreaterEqual => ordering.is_ge(),
+            Operator::Less => ordering.is_lt(),
+            Operator::LessEqual => ordering.is_le(),
+            Operator::Equal => ordering.is_eq(),
+            Operator::NotEqual => ordering.is_ne(),
+        }
+    }
+}
+
+impl<K: ParseKey> FromStr for Fstring<K> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl<K: ParseKey> Fstring<K> {
+    /// Parse and validate the format string, reporting the position of any errors
+    pub fn parse(fstring: &str) -> Result<Self> {
+        let mut parser = Parser {
+            source: fstring,
+            chars: fstring.chars().collect(),
+            pos: 0,
+        };
+        let segments = parser.parse_segments(None)?;
+        Ok(Self { segments })
+    }
+
+    pub fn render<C: FstringContext<Key = K>>(&self, context: &C) -> String {
+        let mut output = String::new();
+        render_segments(&self.segments, context, &mut output);
+        output
+    }
+}
+
+fn render_segments<C: FstringContext>(
+    segments: &[Segment<C::Key>],
+    context: &C,
+    output: &mut String,
+) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => output.push_str(text),
+            Segment::Field(field) => {
+                let value = context.value(&field.key);
+                let text = match (&value, &field.fallback) {
+                    (FstringValue::Missing, Some(fallback)) => Cow::Borrowed(fallback.as_str()),
+                    (FstringValue::Missing, None) => Cow::Borrowed("??"),
+                    (FstringValue::Number(n), _) => match field.spec.precision {
+                        Some(precision) => Cow::Owned(format!("{n:.precision$}")),
+                        None => Cow::Owned(n.to_string()),
+                    },
+                    (FstringValue::Text(t), _) => Cow::Borrowed(t.as_ref()),
+                };
+                field.spec.pad(&text, output);
+            }
+            Segment::Conditional(conditional) => {
+                let value = context.value(&conditional.key);
+                let mut is_true = match &conditional.comparison {
+                    Some((operator, other)) => value.compare(*operator, other),
+                    None => value.is_truthy(),
+                };
+                if conditional.negate {
+                    is_true = !is_true;
+                }
+                if is_true {
+                    render_segments(&conditional.segments, context, output);
+                }
+            }
+        }
+    }
+}
+
+impl FormatSpec {
+    fn pad(&self, text: &str, output: &mut String) {
+        let len = text.width();
+        let padding = self.width.map_or(0, |w| w.saturating_sub(len));
+        let (before, after) = match self.align {
+            Some(Align::Right) => (padding, 0),
+            Some(Align::Center) => (padding / 2, padding - padding / 2),
+            Some(Align::Left) | None => (0, padding),
+        };
+        let fill = self.fill.unwrap_or(' ').to_string();
+        output.push_str(&fill.repeat(before));
+        output.push_str(text);
+        output.push_str(&fill.repeat(after));
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    /// Point at the characters from start to end below the format string
+    fn error(&self, start: usize, end: usize, message: &str) -> anyhow::Error {
+        anyhow!(
+            "{message} at position {}\n{}\n{}{}",
+            start + 1,
+            self.source,
+            " ".repeat(self.width(0, start)),
+            "^".repeat(self.width(start, end).max(1))
+        )
+    }
+
+    /// How many columns the characters take up in a terminal, as emoji and CJK characters are
+    /// two wide
+    fn width(&self, start: usize, end: usize) -> usize {
+        let end = end.min(self.chars.len());
+        let start = start.min(end);
+        self.chars[start..end]
+            .iter()
+            .map(|c| c.width().unwrap_or(0))
+            .sum()
+    }
+
+    /// Parse until the end of the string or, within a conditional starting at the given
+    /// position, until the closing {/}
+    fn parse_segments<K: ParseKey>(
+        &mut self,
+        conditional_start: Option<usize>,
+    ) -> Result<Vec<Segment<K>>> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.peek(0) else {
+                if let Some(start) = conditional_start {
+                    return Err(self.error(start, start + 2, "Conditional section is missing {/}"));
+                }
+                break;
+            };
+            match (c, self.peek(1)) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    text.push(c);
+                    self.pos += 2;
+                }
+                ('{', Some('/')) => {
+                    if conditional_start.is_none() || self.peek(2) != Some('}') {
+                        return Err(self.error(self.pos, self.pos + 3, "Unexpected {/}"));
+                    }
+                    self.pos += 3;
+                    break;
+                }
+                ('{', next) => {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    let segment = if next == Some('?') {
+                        Segment::Conditional(self.parse_conditional()?)
+                    } else {
+                        Segment::Field(self.parse_field()?)
+                    };
+                    segments.push(segment);
+                }
+                ('}', _) => {
+                    return Err(self.error(
+                        self.pos,
+                        self.pos + 1,
+                        "Unmatched } (use }} for a literal brace)",
+                    ));
+                }
+                _ => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(segments)
+    }
+
+    /// Read until one of the delimiters, returning the text and its start position
+    fn take_until(&mut self, delimiters: &[char]) -> (String, usize) {
+        let start = self.pos;
+        while let Some(c) = self.peek(0) {
+            if delimiters.contains(&c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        (self.chars[start..self.pos].iter().collect(), start)
+    }
+
+    fn parse_key<K: ParseKey>(&mut self, delimiters: &[char]) -> Result<K> {
+        let (key, start) = self.take_until(delimiters);
+        let trimmed = key.trim();
+        if trimmed.is_empty() {
+            return Err(self.error(start, self.pos + 1, "Missing key"));
+        }
+        K::parse_key(trimmed).map_err(|e| self.error(start, self.pos, &e.to_string()))
+    }
+
+    fn expect_close(&mut self, open: usize) -> Result<()> {
+        if self.peek(0) == Some('}') {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(open, self.pos, "Missing closing }"))
+        }
+    }
+
+    fn parse_field<K: ParseKey>(&mut self) -> Result<Field<K>> {
+        let open = self.pos;
+        self.pos += 1;
+        let key = self.parse_key(&[':', '|', '}', '{'])?;
+        let spec = if self.peek(0) == Some(':') {
+            self.pos += 1;
+            self.parse_spec()?
+        } else {
+            FormatSpec::default()
+        };
+        let fallback = if self.peek(0) == Some('|') {
+            self.pos += 1;
+            Some(self.take_until(&['}', '{']).0)
+        } else {
+            None
+        };
+        self.expect_close(open)?;
+        Ok(Field {
+            key,
+            spec,
+            fallback,
+        })
+    }
+
+    fn parse_spec(&mut self) -> Result<FormatSpec> {
+        let (spec_str, start) = self.take_until(&['|', '}', '{']);
+        let chars: Vec<char> = spec_str.chars().collect();
+        let to_align = |c: char| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+        let mut spec = FormatSpec::default();
+        let mut i = 0;
+        if let Some(align) = chars.get(1).and_then(|&c| to_align(c)) {
+            spec.fill = Some(chars[0]);
+            spec.align = Some(align);
+            i = 2;
+        } else if let Some(align) = chars.first().and_then(|&c| to_align(c)) {
+            spec.align = Some(align);
+            i = 1;
+        }
+        let number = |i: &mut usize| {
+            let digits_start = *i;
+            while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+                *i += 1;
+            }
+            let digits: String = chars[digits_start..*i].iter().collect();
+            digits.parse::<usize>().ok()
+        };
+        spec.width = number(&mut i);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            spec.precision = number(&mut i);
+            if spec.precision.is_none() {
+                return Err(self.error(start + i - 1, start + i, "Missing precision after ."));
+            }
+        }
+        if i < chars.len() {
+            return Err(self.error(
+                start + i,
+                start + chars.len(),
+                "Invalid format spec. Expected [[fill]align][width][.precision]",
+            ));
+        }
+        Ok(spec)
+    }
+
+    fn parse_conditional<K: ParseKey>(&mut self) -> Result<Conditional<K>> {
+        let open = self.pos;
+        self.pos += 2;
+        let negate = self.peek(0) == Some('!') && self.peek(1) != Some('=');
+        if negate {
+            self.pos += 1;
+        }
+        let key = self.parse_key(&['<', '>', '=', '!', '}', '{'])?;
+        let op_start = self.pos;
+        let operator = match (self.peek(0), self.peek(1)) {
+            (Some('>'), Some('=')) => Some((Operator::GreaterEqual, 2)),
+            (Some('<'), Some('=')) => Some((Operator::LessEqual, 2)),
+            (Some('!'), Some('=')) => Some((Operator::NotEqual, 2)),
+            (Some('='), Some('=')) => Some((Operator::Equal, 2)),
+            (Some('>'), _) => Some((Operator::Greater, 1)),
+            (Some('<'), _) => Some((Operator::Less, 1)),
+            (Some('='), _) => Some((Operator::Equal, 1)),
+            (Some('!'), _) => return Err(self.error(op_start, op_start + 1, "Expected !=")),
+            _ => None,
+        };
+        let comparison = if let Some((operator, len)) = operator {
+            if negate {
+                return Err(self.error(open + 2, open + 3, "Can't use ! with a comparison"));
+            }
+            self.pos += len;
+            let (value, start) = self.take_until(&['}', '{']);
+            let value = value.trim().to_string();
+            if value.is_empty() {
+                return Err(self.error(start, self.pos + 1, "Missing value to compare with"));
+            }
+            Some((operator, value))
+        } else {
+            None
+        };
+        self.expect_close(open)?;
+        let segments = self.parse_segments(Some(open))?;
+        Ok(Conditional {
+            key,
+            negate,
+            comparison,
+            segments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    enum TestKey {
+        Temp,
+        Text,
+        Icon,
+        Zero,
+        Empty,
+        Missing,
+    }
+
+    impl ParseKey for TestKey {
+        fn parse_key(s: &str) -> Result<Self> {
+            match s {
+                "temp" => Ok(Self::Temp),
+                "text" => Ok(Self::Text),
+                "icon" => Ok(Self::Icon),
+                "zero" => Ok(Self::Zero),
+                "empty" => Ok(Self::Empty),
+                "missing" => Ok(Self::Missing),
+                _ => Err(anyhow!("Unknown key \"{s}\"")),
+            }
+        }
+    }
+
+    struct Context;
+
+    impl FstringContext for Context {
+        type Key = TestKey;
+
+        fn value(&self, key: &TestKey) -> FstringValue<'_> {
+            match key {
+                TestKey::Temp => 21.46f32.into(),
+                TestKey::Text => "Sunny".into(),
+                TestKey::Icon => "雨".into(),
+                TestKey::Zero => 0u8.into(),
+                TestKey::Empty => "".into(),
+                TestKey::Missing => FstringValue::Missing,
+            }
+        }
+    }
+
+    fn render(fstring: &str) -> String {
+        Fstring::<TestKey>::parse(fstring).unwrap().render(&Context)
+    }
+
+    fn error(fstring: &str) -> String {
+        Fstring::<TestKey>::parse(fstring).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn text_and_keys() {
+        assert_eq!(render("plain text"), "plain text");
+        assert_eq!(render("{temp}° {text}"), "21.46° Sunny");
+        assert_eq!(render("{ temp }"), "21.46");
+        assert_eq!(render("{{temp}} {{"), "{temp} {");
+        assert_eq!(render("}}{text}}}"), "}Sunny}");
+    }
+
+    #[test]
+    fn format_specs() {
+        assert_eq!(render("{temp:.0}"), "21");
+        assert_eq!(render("{temp:.1}"), "21.5");
+        assert_eq!(render("[{text:7}]"), "[Sunny  ]");
+        assert_eq!(render("[{text:<7}]"), "[Sunny  ]");
+        assert_eq!(render("[{text:>7}]"), "[  Sunny]");
+        assert_eq!(render("[{text:^8}]"), "[ Sunny  ]");
+        assert_eq!(render("{text:*^9}"), "**Sunny**");
+        assert_eq!(render("{temp:0>6.1}"), "0021.5");
+        assert_eq!(render("{text:3}"), "Sunny");
+        assert_eq!(render("[{icon:>4}]"), "[  雨]");
+    }
+
+    #[test]
+    fn fallbacks() {
+        assert_eq!(render("{missing}"), "??");
+        assert_eq!(render("{missing|none}"), "none");
+        assert_eq!(render("{missing|}"), "");
+        assert_eq!(render("{temp|none}"), "21.46");
+        assert_eq!(render("[{missing:>4|-}]"), "[   -]");
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(render("{?temp}warm{/}"), "warm");
+        assert_eq!(render("{?zero}shown{/}"), "");
+        assert_eq!(render("{?empty}shown{/}"), "");
+        assert_eq!(render("{?missing}shown{/}"), "");
+        assert_eq!(render("{?!missing}hidden{/}"), "hidden");
+        assert_eq!(render("{?!temp}hidden{/}"), "");
+        assert_eq!(render("{?temp>21}a{/}{?temp>22}b{/}"), "a");
+        assert_eq!(render("{?temp>=21.46}a{/}{?temp<=21}b{/}"), "a");
+        assert_eq!(render("{?temp<22}a{/}{?temp<21}b{/}"), "a");
+        assert_eq!(render("{?zero=0}a{/}{?zero==0}b{/}{?zero!=0}c{/}"), "ab");
+        assert_eq!(render("{?text=Sunny}a{/}{?text != Rain}b{/}"), "ab");
+        assert_eq!(render("{?temp>warm}a{/}{?missing<1}b{/}"), "");
+        assert_eq!(
+            render("{?temp}{text}{?zero} zero{/}{?text=Sunny} {temp:.0}{/}{/}!"),
+            "Sunny 21!"
+        );
+    }
+
+    #[test]
+    fn forecast_keys() {
+        assert!(Fstring::<Key>::parse("{temp} {hour+3.temp} {day2.max}").is_ok());
+        let error = |s| Fstring::<Key>::parse(s).unwrap_err().to_string();
+        assert!(error("{nope}").starts_with("Unknown key \"nope\" at position 2"));
+        assert!(error("{hour+x.temp}").starts_with("Expected a number of hours after hour+"));
+        assert!(error("{hour+3}").starts_with("Expected a field e.g. hour+3.temp"));
+        assert!(error("{hour+3.nope}").starts_with("Unknown hourly field \"nope\""));
+        assert!(error("{day2x.max}").starts_with("Expected a number of days after day"));
+        assert!(error("{day2}").starts_with("Expected a field e.g. day2.max"));
+        assert!(error("{day2.nope}").starts_with("Unknown daily field \"nope\""));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("{?temp}warm"),
+            "Conditional section is missing {/} at position 1\n{?temp}warm\n^^"
+        );
+        assert_eq!(error("a{/}"), "Unexpected {/} at position 2\na{/}\n ^^^");
+        assert_eq!(
+            error("{?temp}a{/x"),
+            "Unexpected {/} at position 9\n{?temp}a{/x\n        ^^^"
+        );
+        assert_eq!(
+            error("a}b"),
+            "Unmatched } (use }} for a literal brace) at position 2\na}b\n ^"
+        );
+        assert_eq!(error("{}"), "Missing key at position 2\n{}\n ^");
+        assert_eq!(
+            error("{nope}"),
+            "Unknown key \"nope\" at position 2\n{nope}\n ^^^^"
+        );
+        assert_eq!(
+            error("{temp"),
+            "Missing closing } at position 1\n{temp\n^^^^^"
+        );
+        assert_eq!(
+            error("{temp:.}"),
+            "Missing precision after . at position 7\n{temp:.}\n      ^"
+        );
+        assert_eq!(
+            error("{temp:>4x}"),
+            "Invalid format spec. Expected [[fill]align][width][.precision] at position 9\n\
+            {temp:>4x}\n        ^"
+        );
+        assert_eq!(
+            error("{?temp!5}{/}"),
+            "Expected != at position 7\n{?temp!5}{/}\n      ^"
+        );
+        assert_eq!(
+            error("{?!temp>5}{/}"),
+            "Can't use ! with a comparison at position 3\n{?!temp>5}{/}\n  ^"
+        );
+        assert_eq!(
+            error("{?temp>}{/}"),
+            "Missing value to compare with at position 8\n{?temp>}{/}\n       ^"
+        );
+    }
+
+    #[test]
+    fn error_caret_with_wide_characters() {
+        // The umbrella and the CJK characters each take up two columns
+        assert_eq!(
+            error("☔ {nope}"),
+            "Unknown key \"nope\" at position 4\n☔ {nope}\n    ^^^^"
+        );
+        assert_eq!(
+            error("{雨雨}"),
+            "Unknown key \"雨雨\" at position 2\n{雨雨}\n ^^^^"
+        );
+    }
+}
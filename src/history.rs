@@ -0,0 +1,107 @@
+use crate::location::Location;
+use crate::observation::Observation;
+use crate::units::Units;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Months, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// How long observations are kept in the archive e.g. "30d", "12m" or "unlimited"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub enum Retention {
+    Unlimited,
+    Days(u32),
+    Months(u32),
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self::Months(12)
+    }
+}
+
+impl Retention {
+    /// Observations issued before the returned time should be deleted
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Unlimited => None,
+            Self::Days(days) => Some(now - Duration::days(*days as i64)),
+            Self::Months(months) => now.checked_sub_months(Months::new(*months)),
+        }
+    }
+}
+
+impl FromStr for Retention {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        if s == "unlimited" {
+            return Ok(Self::Unlimited);
+        }
+        let err = || anyhow!("{s} is not a valid retention. Expected e.g. 30d, 12m or unlimited");
+        let idx = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+        let amount: u32 = s[..idx].parse().map_err(|_| err())?;
+        match &s[idx..] {
+            "d" | "day" | "days" => Ok(Self::Days(amount)),
+            "m" | "month" | "months" => Ok(Self::Months(amount)),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl Display for Retention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unlimited => write!(f, "unlimited"),
+            Self::Days(days) => write!(f, "{days}d"),
+            Self::Months(months) => write!(f, "{months}m"),
+        }
+    }
+}
+
+/// A flattened observation used for tabular, CSV and JSON output of the archive,
+/// with values converted to the units
+#[derive(Debug, Serialize)]
+pub struct ObservationRow<'a> {
+    pub location_id: &'a str,
+    pub time: DateTime<Tz>,
+    pub temp: f32,
+    pub temp_feels_like: f32,
+    pub humidity: u8,
+    pub wind_direction: Option<&'a str>,
+    pub wind_speed: f32,
+    pub gust_speed: f32,
+    pub rain_since_9am: Option<f32>,
+    pub rain_ten: Option<f32>,
+    pub pressure: Option<f64>,
+    pub dew_point: Option<f32>,
+    pub cloud: Option<&'a str>,
+    pub cloud_oktas: Option<u32>,
+    pub visibility: Option<f32>,
+}
+
+impl<'a> ObservationRow<'a> {
+    pub fn new(location: &'a Location, obs: &'a Observation, units: &Units) -> Self {
+        Self {
+            location_id: &location.id,
+            time: obs.observation_time.with_timezone(&location.timezone),
+            temp: units.temp(obs.temp),
+            temp_feels_like: units.temp(obs.temp_feels_like),
+            humidity: obs.humidity,
+            wind_direction: obs.wind.direction.as_deref(),
+            wind_speed: units.wind(obs.wind.speed_kilometre, obs.wind.speed_knot),
+            gust_speed: units.wind(obs.gust.speed_kilometre, obs.gust.speed_knot),
+            rain_since_9am: obs.rain_since_9am.map(|r| units.rain(r)),
+            rain_ten: obs.rain_ten.map(|r| units.rain(r)),
+            pressure: obs.pressure,
+            dew_point: obs.dew_point.map(|t| units.temp(t)),
+            cloud: obs.cloud.as_deref(),
+            cloud_oktas: obs.cloud_oktas,
+            visibility: obs.visibility,
+        }
+    }
+}
@@ -1,5 +1,8 @@
-use crate::descriptor::IconDescriptor;
+use crate::descriptor::{IconDescriptor, IconOptions};
+use crate::location::Location;
+use crate::units::Units;
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -8,6 +11,18 @@ pub struct HourlyForecast {
     pub data: Vec<HourlyForecastData>,
 }
 
+impl HourlyForecast {
+    /// The hours that haven't finished yet, starting with the current one
+    pub fn upcoming(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl Iterator<Item = &HourlyForecastData> + Clone {
+        self.data
+            .iter()
+            .filter(move |h| h.next_forecast_period > now)
+    }
+}
+
 impl From<HourlyResponse> for HourlyForecast {
     fn from(response: HourlyResponse) -> Self {
         HourlyForecast {
@@ -68,3 +83,58 @@ pub struct HourlyForecastWind {
     pub gust_speed_kilometre: u8,
     pub gust_speed_knot: u8,
 }
+
+/// A flattened hour of the forecast used for CSV and JSON output, with values converted to the
+/// units
+#[derive(Debug, Serialize)]
+pub struct HourlyRow<'a> {
+    pub location_id: &'a str,
+    pub issue_time: DateTime<Tz>,
+    pub time: DateTime<Tz>,
+    pub temp: f32,
+    pub temp_feels_like: f32,
+    pub icon_descriptor: String,
+    pub icon: &'a str,
+    pub is_night: bool,
+    pub rain_chance: u8,
+    pub rain_min: f32,
+    pub rain_max: Option<f32>,
+    pub wind_direction: &'a str,
+    pub wind_speed: f32,
+    pub gust_speed: f32,
+    pub humidity: u8,
+    pub uv: u8,
+}
+
+impl<'a> HourlyRow<'a> {
+    pub fn new(
+        location: &'a Location,
+        hour: &'a HourlyForecastData,
+        units: &Units,
+        icons: &'a IconOptions,
+    ) -> Self {
+        let tz = location.timezone;
+        Self {
+            location_id: &location.id,
+            issue_time: location
+                .weather
+                .hourly_forecast
+                .issue_time
+                .with_timezone(&tz),
+            time: hour.time.with_timezone(&tz),
+            temp: units.temp(hour.temp),
+            temp_feels_like: units.temp(hour.temp_feels_like),
+            icon_descriptor: hour.icon_descriptor.name(),
+            icon: icons.icon(&hour.icon_descriptor, hour.is_night),
+            is_night: hour.is_night,
+            rain_chance: hour.rain.chance,
+            rain_min: units.rain(hour.rain.amount.min as f32),
+            rain_max: hour.rain.amount.max.map(|mm| units.rain(mm as f32)),
+            wind_direction: &hour.wind.direction,
+            wind_speed: units.wind(hour.wind.speed_kilometre, hour.wind.speed_knot),
+            gust_speed: units.wind(hour.wind.gust_speed_kilometre, hour.wind.gust_speed_knot),
+            humidity: hour.relative_humidity,
+            uv: hour.uv,
+        }
+    }
+}
@@ -1,17 +1,32 @@
+pub mod animation;
+pub mod changes;
+pub mod chart;
 pub mod cli;
 pub mod client;
+pub mod colour;
 pub mod config;
 pub mod daily;
+pub mod dashboard;
 pub mod descriptor;
+pub mod fstring;
 pub mod ftp;
+pub mod history;
 pub mod hourly;
 pub mod location;
 pub mod logging;
+pub mod meteogram;
+pub mod nowcast;
 pub mod observation;
 pub mod persistence;
 pub mod radar;
 pub mod services;
 pub mod station;
+pub mod statusbar;
+pub mod summary;
+pub mod terminal_graphics;
+pub mod units;
 pub mod util;
+pub mod verification;
+pub mod views;
 pub mod warning;
 pub mod weather;
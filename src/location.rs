@@ -1,5 +1,7 @@
 use crate::station::WeatherStation;
 use crate::weather::Weather;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 use strum_macros::{Display, EnumString};
@@ -17,10 +19,21 @@ pub struct Location {
     pub state: State,
     pub postcode: String,
     pub tidal_point: Option<String>,
-    pub timezone: String,
+    pub timezone: Tz,
     pub weather: Weather,
 }
 
+impl Location {
+    /// The current time in the location's timezone
+    pub fn now(&self) -> DateTime<Tz> {
+        Utc::now().with_timezone(&self.timezone)
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
 impl Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {} {}", self.name, self.state, self.postcode)
@@ -0,0 +1,283 @@
+use crate::colour::Colours;
+use crate::hourly::HourlyForecastData;
+use crate::units::Units;
+use chrono::Timelike;
+use chrono_tz::Tz;
+use comfy_table::Color;
+
+const LABEL_WIDTH: usize = 7;
+const NIGHT_BACKGROUND: &str = "\x1b[48;2;40;40;48m";
+const FEELS_LIKE_COLOUR: Color = Color::Rgb {
+    r: 135,
+    g: 175,
+    b: 215,
+};
+const RAIN_COLOUR: Color = Color::Rgb {
+    r: 95,
+    g: 175,
+    b: 255,
+};
+const RESET: &str = "\x1b[0m";
+const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// A chart of the hourly forecast drawn with braille and block characters, one column per hour
+pub struct Meteogram<'a> {
+    hours: &'a [&'a HourlyForecastData],
+    units: &'a Units,
+    tz: Tz,
+    colours: &'a Colours<'a>,
+}
+
+/// Braille characters used as a grid of dots, 2 wide and 4 high per character
+struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>,
+    colours: Vec<Option<Color>>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![0; width * height],
+            colours: vec![None; width * height],
+        }
+    }
+
+    /// Set the dot at x (0 is the left) and y (0 is the bottom)
+    fn set(&mut self, x: usize, y: usize, colour: Option<Color>) {
+        if x >= self.width * 2 || y >= self.height * 4 {
+            return;
+        }
+        let row = self.height - 1 - y / 4;
+        let idx = row * self.width + x / 2;
+        // Dot numbering within a character, from the top
+        let dot_row = 3 - y % 4;
+        let bit = match (x % 2, dot_row) {
+            (0, 3) => 0x40,
+            (1, 3) => 0x80,
+            (0, r) => 1 << r,
+            (_, r) => 1 << (r + 3),
+        };
+        self.cells[idx] |= bit;
+        if colour.is_some() {
+            self.colours[idx] = colour;
+        }
+    }
+
+    /// Draw a line between two dots, filling the gaps so it's continuous
+    fn line(&mut self, from: (usize, usize), to: (usize, usize), colour: Option<Color>) {
+        let steps = from.0.abs_diff(to.0).max(from.1.abs_diff(to.1)).max(1);
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let x = from.0 as f32 + (to.0 as f32 - from.0 as f32) * t;
+            let y = from.1 as f32 + (to.1 as f32 - from.1 as f32) * t;
+            self.set(x.round() as usize, y.round() as usize, colour);
+        }
+    }
+
+    fn cell(&self, row: usize, column: usize) -> (char, Option<Color>) {
+        let idx = row * self.width + column;
+        let c = char::from_u32(0x2800 + self.cells[idx] as u32).unwrap_or(' ');
+        (c, self.colours[idx])
+    }
+}
+
+/// Maps values onto the dots of a panel
+struct Scale {
+    min: f32,
+    max: f32,
+    dots: usize,
+}
+
+impl Scale {
+    fn new(values: impl Iterator<Item = f32> + Clone, rows: usize) -> Self {
+        let min = values.clone().fold(f32::INFINITY, f32::min).floor();
+        let mut max = values.fold(f32::NEG_INFINITY, f32::max).ceil();
+        if max <= min {
+            max = min + 1.0;
+        }
+        Self {
+            min,
+            max,
+            dots: rows * 4,
+        }
+    }
+
+    fn dot(&self, value: f32) -> usize {
+        let fraction = (value - self.min) / (self.max - self.min);
+        (fraction * (self.dots - 1) as f32).round() as usize
+    }
+}
+
+impl<'a> Meteogram<'a> {
+    pub fn new(
+        hours: &'a [&'a HourlyForecastData],
+        units: &'a Units,
+        tz: Tz,
+        colours: &'a Colours<'a>,
+    ) -> Self {
+        Self {
+            hours,
+            units,
+            tz,
+            colours,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let u = self.units;
+        let mut lines = Vec::new();
+
+        lines.push(format!(
+            "Temperature ({}) ⠒ actual ⠂ feels like",
+            u.temp_symbol()
+        ));
+        let temps = self.hours.iter().map(|h| u.temp(h.temp));
+        let feels_like = self.hours.iter().map(|h| u.temp(h.temp_feels_like));
+        let scale = Scale::new(temps.clone().chain(feels_like.clone()), 8);
+        let mut canvas = Canvas::new(self.hours.len(), 8);
+        let feels_colour = self.colours.is_enabled().then_some(FEELS_LIKE_COLOUR);
+        for (i, value) in feels_like.enumerate() {
+            canvas.set(i * 2, scale.dot(value), feels_colour);
+        }
+        self.trace(&mut canvas, &scale, temps, |i| {
+            self.colours.temp(self.hours[i].temp)
+        });
+        lines.extend(self.panel(&canvas, &scale));
+
+        lines.push("Chance of rain (%)".to_string());
+        lines.extend(self.rain_bars(4));
+
+        lines.push(format!("Wind ({}) ⠒ speed ⠂ gust", u.wind_symbol()));
+        let speeds = self
+            .hours
+            .iter()
+            .map(|h| u.wind(h.wind.speed_kilometre, h.wind.speed_knot));
+        let gusts = self
+            .hours
+            .iter()
+            .map(|h| u.wind(h.wind.gust_speed_kilometre, h.wind.gust_speed_knot));
+        let scale = Scale::new(speeds.clone().chain(gusts.clone()), 5);
+        let mut canvas = Canvas::new(self.hours.len(), 5);
+        for (i, value) in gusts.enumerate() {
+            let colour = self.colours.gust(self.hours[i].wind.gust_speed_kilometre);
+            canvas.set(i * 2, scale.dot(value), colour);
+        }
+        self.trace(&mut canvas, &scale, speeds, |_| None);
+        lines.extend(self.panel(&canvas, &scale));
+
+        lines.extend(self.time_axis());
+        lines.join("\n")
+    }
+
+    /// Draw a continuous line through the values, with the right half of each character
+    /// halfway to the next hour
+    fn trace(
+        &self,
+        canvas: &mut Canvas,
+        scale: &Scale,
+        values: impl Iterator<Item = f32>,
+        colour: impl Fn(usize) -> Option<Color>,
+    ) {
+        let values: Vec<usize> = values.map(|v| scale.dot(v)).collect();
+        for (i, &y) in values.iter().enumerate() {
+            let next = values.get(i + 1).copied().unwrap_or(y);
+            canvas.line((i * 2, y), (i * 2 + 2, next), colour(i));
+        }
+    }
+
+    fn panel(&self, canvas: &Canvas, scale: &Scale) -> Vec<String> {
+        (0..canvas.height)
+            .map(|row| {
+                let label = if row == 0 {
+                    scale.max.to_string()
+                } else if row == canvas.height - 1 {
+                    scale.min.to_string()
+                } else {
+                    String::new()
+                };
+                let cells = (0..canvas.width).map(|column| canvas.cell(row, column));
+                format!("{label:>width$} ", width = LABEL_WIDTH - 1) + &self.columns(cells)
+            })
+            .collect()
+    }
+
+    fn rain_bars(&self, rows: usize) -> Vec<String> {
+        let colour = self.colours.is_enabled().then_some(RAIN_COLOUR);
+        (0..rows)
+            .map(|row| {
+                let label = match row {
+                    0 => "100",
+                    r if r == rows - 1 => "0",
+                    _ => "",
+                };
+                // Eighths of a character filled above the bottom of this row
+                let row_base = (rows - 1 - row) * 8;
+                let cells = self.hours.iter().map(|h| {
+                    let filled = (h.rain.chance as usize * rows * 8 + 50) / 100;
+                    let eighths = filled.saturating_sub(row_base).min(8);
+                    (BARS[eighths], colour)
+                });
+                format!("{label:>width$} ", width = LABEL_WIDTH - 1) + &self.columns(cells)
+            })
+            .collect()
+    }
+
+    /// Hour labels every 6 hours, then the day at each midnight
+    fn time_axis(&self) -> Vec<String> {
+        let width = self.hours.len();
+        let mut hours = vec![' '; width];
+        let mut days = vec![' '; width];
+        for (i, hour) in self.hours.iter().enumerate() {
+            let time = hour.time.with_timezone(&self.tz);
+            if time.hour() % 6 == 0 {
+                for (j, c) in format!("{:02}", time.hour()).chars().enumerate() {
+                    if let Some(slot) = hours.get_mut(i + j) {
+                        *slot = c;
+                    }
+                }
+            }
+            if i == 0 || time.hour() == 0 {
+                for (j, c) in time.format("%a").to_string().chars().enumerate() {
+                    if let Some(slot) = days.get_mut(i + j) {
+                        *slot = c;
+                    }
+                }
+            }
+        }
+        let padding = " ".repeat(LABEL_WIDTH);
+        let night = self
+            .hours
+            .iter()
+            .map(|h| if h.is_night { '░' } else { ' ' })
+            .collect::<String>();
+        vec![
+            format!("{padding}{}", hours.iter().collect::<String>()),
+            format!("{padding}{}", days.iter().collect::<String>()),
+            format!("{:>width$} {night}", "night", width = LABEL_WIDTH - 1),
+        ]
+    }
+
+    /// Join the characters of each hour, shading the night time hours if colour is enabled
+    fn columns(&self, cells: impl Iterator<Item = (char, Option<Color>)>) -> String {
+        let mut output = String::new();
+        for ((c, colour), hour) in cells.zip(self.hours) {
+            if !self.colours.is_enabled() {
+                output.push(c);
+                continue;
+            }
+            if hour.is_night {
+                output.push_str(NIGHT_BACKGROUND);
+            }
+            if let Some(Color::Rgb { r, g, b }) = colour {
+                output.push_str(&format!("\x1b[38;2;{r};{g};{b}m"));
+            }
+            output.push(c);
+            output.push_str(RESET);
+        }
+        output
+    }
+}
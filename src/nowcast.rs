@@ -0,0 +1,580 @@
+use crate::radar::{project, RadarImageDataLayer};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use image::{ImageFormat, Rgba};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+/// The rain rate in mm/h at the bottom of each band of the radar rainfall legend, by the colour
+/// of the band
+const RAINFALL_LEGEND: [([u8; 3], f32); 15] = [
+    ([0xF5, 0xF5, 0xFF], 0.2),
+    ([0xB4, 0xB4, 0xFF], 0.5),
+    ([0x78, 0x78, 0xFF], 1.5),
+    ([0x14, 0x14, 0xFF], 2.5),
+    ([0x00, 0xD8, 0xC3], 4.0),
+    ([0x00, 0x96, 0x90], 6.0),
+    ([0x00, 0x66, 0x66], 10.0),
+    ([0xFF, 0xFF, 0x00], 15.0),
+    ([0xFF, 0xC8, 0x00], 20.0),
+    ([0xFF, 0x96, 0x00], 35.0),
+    ([0xFF, 0x64, 0x00], 50.0),
+    ([0xFF, 0x00, 0x00], 80.0),
+    ([0xC8, 0x00, 0x00], 120.0),
+    ([0x78, 0x00, 0x00], 200.0),
+    ([0x28, 0x00, 0x00], 300.0),
+];
+
+/// The rain rate in mm/h shown by a pixel of a rainfall radar image, or 0 for any colour that
+/// isn't in the legend like the header text
+pub fn rain_rate(pixel: &Rgba<u8>) -> f32 {
+    match rain_level(pixel) {
+        0 => 0.0,
+        level => RAINFALL_LEGEND[level as usize - 1].1,
+    }
+}
+
+/// The band of the rainfall legend a pixel is in, counting from 1 for the lightest rain
+pub fn rain_level(pixel: &Rgba<u8>) -> u8 {
+    if pixel[3] == 0 {
+        return 0;
+    }
+    RAINFALL_LEGEND
+        .iter()
+        .position(|(colour, _)| colour[..] == pixel.0[..3])
+        .map_or(0, |i| i as u8 + 1)
+}
+
+/// The rain level of every pixel of a 512x512 radar image
+struct RainGrid {
+    time: DateTime<Utc>,
+    levels: Vec<u8>,
+}
+
+impl RainGrid {
+    fn new(layer: &RadarImageDataLayer) -> Result<Self> {
+        let image = image::load_from_memory_with_format(&layer.png_buf, ImageFormat::Png)?;
+        let image = image.to_rgba8();
+        let levels = (0..SIZE * SIZE)
+            .map(
+                |i| match image.get_pixel_checked((i % SIZE) as u32, (i / SIZE) as u32) {
+                    Some(pixel) => rain_level(pixel),
+                    None => 0,
+                },
+            )
+            .collect();
+        Ok(Self {
+            time: layer.datetime,
+            levels,
+        })
+    }
+
+    fn level(&self, x: i64, y: i64) -> u8 {
+        if x < 0 || y < 0 || x >= SIZE as i64 || y >= SIZE as i64 {
+            return 0;
+        }
+        self.levels[y as usize * SIZE + x as usize]
+    }
+
+    /// The heaviest rain within the radius of the point, which can be outside the image
+    fn max_level(&self, x: f64, y: f64, radius: f64) -> u8 {
+        let radius = radius.max(0.5);
+        let mut level = 0;
+        for py in (y - radius).floor() as i64..=(y + radius).ceil() as i64 {
+            for px in (x - radius).floor() as i64..=(x + radius).ceil() as i64 {
+                if (px as f64 - x).hypot(py as f64 - y) <= radius {
+                    level = level.max(self.level(px, py));
+                }
+            }
+        }
+        level
+    }
+
+    /// Halve the size, keeping the heaviest rain of each 2x2 square
+    fn downsample(&self) -> Vec<u8> {
+        let half = SIZE / 2;
+        (0..half * half)
+            .map(|i| {
+                let (x, y) = ((i % half) as i64 * 2, (i / half) as i64 * 2);
+                self.level(x, y)
+                    .max(self.level(x + 1, y))
+                    .max(self.level(x, y + 1))
+                    .max(self.level(x + 1, y + 1))
+            })
+            .collect()
+    }
+}
+
+const SIZE: usize = 512;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NowcastOptions {
+    /// Rain within this distance of a location counts as raining there, to allow for the size
+    /// of the radar's pixels and the rain moving between images
+    pub radius_km: f64,
+    /// Older radar images are ignored so a stopped radar command doesn't look like a dry spell
+    pub max_age_minutes: i64,
+    /// How much the rain rate (mm/h) has to change over the loop to be increasing or decreasing
+    pub trend_change: f32,
+    /// How many of the newest images the rain's motion is estimated from, which needs at least 2
+    pub motion_images: usize,
+    /// Rain moving faster than this (km/h) isn't looked for when estimating the motion
+    pub max_speed_kmh: f64,
+    /// How far ahead the motion is extrapolated to find when rain will arrive
+    pub max_lead_minutes: i64,
+}
+
+impl Default for NowcastOptions {
+    fn default() -> Self {
+        Self {
+            radius_km: 2.0,
+            max_age_minutes: 20,
+            trend_change: 0.5,
+            motion_images: 4,
+            max_speed_kmh: 120.0,
+            max_lead_minutes: 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum RainTrend {
+    Increasing,
+    Decreasing,
+    Steady,
+}
+
+/// Rain at a location according to the radar images
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Nowcast {
+    /// The radar image product e.g. IDR403
+    pub product: String,
+    /// The time of the newest image
+    pub time: DateTime<Utc>,
+    /// The heaviest rain in mm/h around the location in the newest image
+    pub rain_rate: f32,
+    pub trend: RainTrend,
+    /// The rain rate in each image, oldest first
+    pub rates: Vec<(DateTime<Utc>, f32)>,
+    /// How the rain is moving, if there's enough of it to tell
+    pub motion: Option<Motion>,
+    /// When rain is expected at the location if it keeps moving the same way, which is the time
+    /// of the newest image if it's already raining
+    pub arrival: Option<DateTime<Utc>>,
+}
+
+/// The average movement of the rain across the images
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Motion {
+    pub speed_kmh: f64,
+    /// The bearing the rain is moving towards, in degrees
+    pub bearing: f64,
+    /// Pixels per minute to the right and down
+    dx: f64,
+    dy: f64,
+}
+
+impl Nowcast {
+    /// Sample the images around the location's latitude and longitude. Returns None if it's
+    /// outside the images or there aren't any
+    pub fn from_layers(
+        location: (f64, f64),
+        radar: (f64, f64),
+        range_km: f64,
+        layers: &[RadarImageDataLayer],
+        opts: &NowcastOptions,
+    ) -> Result<Option<Self>> {
+        let (x, y) = project(radar, range_km, location);
+        let radius = opts.radius_km * 256.0 / range_km;
+        if x - radius < 0.0 || y - radius < 0.0 || x + radius >= 512.0 || y + radius >= 512.0 {
+            return Ok(None);
+        }
+        let Some(first) = layers.first() else {
+            return Ok(None);
+        };
+        let product = first.filename.split('.').next().unwrap_or_default();
+
+        let mut layers: Vec<&RadarImageDataLayer> = layers.iter().collect();
+        layers.sort_by_key(|l| l.datetime);
+        let mut rates = Vec::with_capacity(layers.len());
+        let mut grids = Vec::new();
+        for (i, layer) in layers.iter().enumerate() {
+            let grid = RainGrid::new(layer)?;
+            let rate = match grid.max_level(x, y, radius) {
+                0 => 0.0,
+                level => RAINFALL_LEGEND[level as usize - 1].1,
+            };
+            rates.push((layer.datetime, rate));
+            // The newest is always kept for the arrival, even if motion isn't estimated
+            if i + opts.motion_images.max(1) >= layers.len() {
+                grids.push(grid);
+            }
+        }
+        let (time, rain_rate) = rates[rates.len() - 1];
+
+        let pixels_per_km = 256.0 / range_km;
+        let motion =
+            estimate_motion(&grids, opts.max_speed_kmh * pixels_per_km / 60.0).map(|(dx, dy)| {
+                Motion {
+                    speed_kmh: dx.hypot(dy) * 60.0 / pixels_per_km,
+                    bearing: dx.atan2(-dy).to_degrees().rem_euclid(360.0),
+                    dx,
+                    dy,
+                }
+            });
+        // Look upwind for the rain that will reach the location
+        let newest = &grids[grids.len() - 1];
+        let arrival = if rain_rate > 0.0 {
+            Some(time)
+        } else {
+            motion.and_then(|m| {
+                (1..=opts.max_lead_minutes)
+                    .find(|&t| {
+                        let (upwind_x, upwind_y) = (x - m.dx * t as f64, y - m.dy * t as f64);
+                        newest.max_level(upwind_x, upwind_y, radius) > 0
+                    })
+                    .map(|t| time + Duration::minutes(t))
+            })
+        };
+
+        Ok(Some(Self {
+            product: product.to_string(),
+            time,
+            rain_rate,
+            trend: trend(&rates, opts.trend_change),
+            rates,
+            motion,
+            arrival,
+        }))
+    }
+
+    pub fn is_raining(&self) -> bool {
+        self.rain_rate > 0.0
+    }
+}
+
+/// Estimate how far the rain moves per minute by block matching consecutive images. Each block
+/// with enough rain in the later image is compared with the earlier image at every shift up to
+/// the maximum speed, and the median of the best shifts is used. Returns pixels per minute
+fn estimate_motion(grids: &[RainGrid], max_pixels_per_minute: f64) -> Option<(f64, f64)> {
+    // Matching at half the resolution is much faster and accurate enough
+    const BLOCK: usize = 16;
+    const HALF: usize = SIZE / 2;
+    let mut vectors = Vec::new();
+    for pair in grids.windows(2) {
+        let minutes = (pair[1].time - pair[0].time).num_seconds() as f64 / 60.0;
+        if minutes <= 0.0 {
+            continue;
+        }
+        let (before, after) = (pair[0].downsample(), pair[1].downsample());
+        let max_shift = ((max_pixels_per_minute * minutes / 2.0).ceil() as i64).min(HALF as i64);
+        for block_y in (0..HALF).step_by(BLOCK) {
+            for block_x in (0..HALF).step_by(BLOCK) {
+                let pixels: Vec<(usize, usize)> = (block_y..block_y + BLOCK)
+                    .flat_map(|y| (block_x..block_x + BLOCK).map(move |x| (x, y)))
+                    .collect();
+                let raining = pixels
+                    .iter()
+                    .filter(|(x, y)| after[y * HALF + x] > 0)
+                    .count();
+                // Blocks that are empty or filled with rain can't show any movement
+                if raining < BLOCK * BLOCK / 16 || raining == BLOCK * BLOCK {
+                    continue;
+                }
+                let cost = |dx: i64, dy: i64| -> u32 {
+                    pixels
+                        .iter()
+                        .map(|&(x, y)| {
+                            let (from_x, from_y) = (x as i64 - dx, y as i64 - dy);
+                            let from = if from_x < 0
+                                || from_y < 0
+                                || from_x >= HALF as i64
+                                || from_y >= HALF as i64
+                            {
+                                0
+                            } else {
+                                before[from_y as usize * HALF + from_x as usize]
+                            };
+                            after[y * HALF + x].abs_diff(from) as u32
+                        })
+                        .sum()
+                };
+                let mut best = (cost(0, 0), 0i64, 0i64);
+                for dy in -max_shift..=max_shift {
+                    for dx in -max_shift..=max_shift {
+                        let shift_cost = cost(dx, dy);
+                        // Prefer the smaller shift when they match equally well
+                        let closer = dx.abs() + dy.abs() < best.1.abs() + best.2.abs();
+                        if shift_cost < best.0 || (shift_cost == best.0 && closer) {
+                            best = (shift_cost, dx, dy);
+                        }
+                    }
+                }
+                vectors.push((best.1 as f64 * 2.0 / minutes, best.2 as f64 * 2.0 / minutes));
+            }
+        }
+    }
+    if vectors.is_empty() {
+        return None;
+    }
+    let median = |mut values: Vec<f64>| {
+        values.sort_by(f64::total_cmp);
+        values[values.len() / 2]
+    };
+    Some((
+        median(vectors.iter().map(|v| v.0).collect()),
+        median(vectors.iter().map(|v| v.1).collect()),
+    ))
+}
+
+/// Compare the change over the loop, from the line of best fit, to the threshold
+fn trend(rates: &[(DateTime<Utc>, f32)], threshold: f32) -> RainTrend {
+    let (Some((start, _)), Some((end, _))) = (rates.first(), rates.last()) else {
+        return RainTrend::Steady;
+    };
+    let hours: Vec<f32> = rates
+        .iter()
+        .map(|(time, _)| (*time - *start).num_seconds() as f32 / 3600.0)
+        .collect();
+    let count = rates.len() as f32;
+    let mean_hours = hours.iter().sum::<f32>() / count;
+    let mean_rate = rates.iter().map(|(_, rate)| rate).sum::<f32>() / count;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (hour, (_, rate)) in hours.iter().zip(rates) {
+        covariance += (hour - mean_hours) * (rate - mean_rate);
+        variance += (hour - mean_hours).powi(2);
+    }
+    if variance == 0.0 {
+        return RainTrend::Steady;
+    }
+    let change = covariance / variance * (*end - *start).num_seconds() as f32 / 3600.0;
+    if change >= threshold {
+        RainTrend::Increasing
+    } else if change <= -threshold {
+        RainTrend::Decreasing
+    } else {
+        RainTrend::Steady
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radar::{unproject, RadarType};
+    use chrono::TimeZone;
+    use image::{ImageOutputFormat, RgbaImage};
+    use std::io::Cursor;
+
+    const RADAR: (f64, f64) = (-35.66, 149.51);
+    const RANGE_KM: f64 = 128.0;
+
+    fn time(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn legend_pixel(level: u8) -> Rgba<u8> {
+        let [r, g, b] = RAINFALL_LEGEND[level as usize - 1].0;
+        Rgba([r, g, b, 255])
+    }
+
+    /// A transparent image with a square of rain of one level centred on a pixel
+    fn square(centre: (u32, u32), half: u32, level: u8) -> RgbaImage {
+        RgbaImage::from_fn(512, 512, |x, y| {
+            if x.abs_diff(centre.0) <= half && y.abs_diff(centre.1) <= half {
+                legend_pixel(level)
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        })
+    }
+
+    fn layer(minutes: i64, image: &RgbaImage) -> RadarImageDataLayer {
+        let mut png = Cursor::new(Vec::new());
+        image.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+        let datetime = time(minutes);
+        RadarImageDataLayer {
+            radar_type: RadarType::OneTwentyEightKm,
+            png_buf: png.into_inner(),
+            radar_id: 40,
+            datetime,
+            filename: format!("IDR402.T.{}.png", datetime.format("%Y%m%d%H%M")),
+        }
+    }
+
+    fn from_layers(pixel: (f64, f64), layers: &[RadarImageDataLayer]) -> Option<Nowcast> {
+        from_layers_with(pixel, layers, &NowcastOptions::default())
+    }
+
+    fn from_layers_with(
+        pixel: (f64, f64),
+        layers: &[RadarImageDataLayer],
+        opts: &NowcastOptions,
+    ) -> Option<Nowcast> {
+        let location = unproject(RADAR, RANGE_KM, pixel);
+        Nowcast::from_layers(location, RADAR, RANGE_KM, layers, opts).unwrap()
+    }
+
+    /// An 80 pixel square of patchy rain with its top left corner at the offset, so every part
+    /// of its edge can be matched
+    fn patchy_rain(offset: (i64, i64)) -> RgbaImage {
+        RgbaImage::from_fn(512, 512, |x, y| {
+            let (x, y) = (x as i64 - offset.0, y as i64 - offset.1);
+            if !(0..80).contains(&x) || !(0..80).contains(&y) {
+                return Rgba([0, 0, 0, 0]);
+            }
+            let (cell_x, cell_y) = (x / 4, y / 4);
+            match (cell_x * 7 + cell_y * 13 + cell_x * cell_y) % 5 {
+                0 => Rgba([0, 0, 0, 0]),
+                level => legend_pixel(level as u8 * 2),
+            }
+        })
+    }
+
+    #[test]
+    fn legend_colours() {
+        for (i, (_, rate)) in RAINFALL_LEGEND.iter().enumerate() {
+            let pixel = legend_pixel(i as u8 + 1);
+            assert_eq!(rain_level(&pixel), i as u8 + 1);
+            assert_eq!(rain_rate(&pixel), *rate);
+        }
+        // Transparent pixels and colours that aren't in the legend are dry
+        let mut transparent = legend_pixel(5);
+        transparent[3] = 0;
+        assert_eq!(rain_level(&transparent), 0);
+        assert_eq!(rain_rate(&transparent), 0.0);
+        assert_eq!(rain_level(&Rgba([0xFF, 0xFF, 0xFF, 0xFF])), 0);
+        assert_eq!(rain_rate(&Rgba([0x00, 0x00, 0x00, 0xFF])), 0.0);
+    }
+
+    #[test]
+    fn max_level_radius() {
+        let grid = RainGrid::new(&layer(0, &square((100, 100), 0, 7))).unwrap();
+        assert_eq!(grid.max_level(100.0, 110.0, 9.9), 0);
+        assert_eq!(grid.max_level(100.0, 110.0, 10.0), 7);
+        // The corners of the square around the circle are left out
+        assert_eq!(grid.max_level(107.0, 107.0, 9.8), 0);
+        assert_eq!(grid.max_level(107.0, 107.0, 9.9), 7);
+        // A radius too small to reach any pixel still covers the one the point is on
+        assert_eq!(grid.max_level(100.2, 99.8, 0.0), 7);
+
+        // The circle can go past the edges of the image
+        let grid = RainGrid::new(&layer(0, &square((0, 511), 0, 3))).unwrap();
+        assert_eq!(grid.max_level(-5.0, 511.0, 4.0), 0);
+        assert_eq!(grid.max_level(-5.0, 511.0, 5.0), 3);
+        assert_eq!(grid.max_level(3.0, 515.0, 5.0), 3);
+        assert_eq!(grid.max_level(-100.0, -100.0, 5.0), 0);
+    }
+
+    #[test]
+    fn trend_thresholds() {
+        let rates = |values: &[f32]| -> Vec<(DateTime<Utc>, f32)> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, rate)| (time(i as i64 * 10), *rate))
+                .collect()
+        };
+        // A change of 2 mm/h over the loop
+        let rising = rates(&[1.0, 2.0, 3.0]);
+        assert_eq!(trend(&rising, 1.9), RainTrend::Increasing);
+        assert_eq!(trend(&rising, 2.1), RainTrend::Steady);
+        let falling = rates(&[3.0, 2.0, 1.0]);
+        assert_eq!(trend(&falling, 1.9), RainTrend::Decreasing);
+        assert_eq!(trend(&falling, 2.1), RainTrend::Steady);
+        // A single heavy image doesn't outweigh the rest of the loop
+        assert_eq!(
+            trend(&rates(&[0.0, 0.0, 0.0, 10.0, 0.0, 0.0]), 2.0),
+            RainTrend::Steady
+        );
+        assert_eq!(trend(&rates(&[]), 0.5), RainTrend::Steady);
+        assert_eq!(trend(&rates(&[50.0]), 0.5), RainTrend::Steady);
+    }
+
+    #[test]
+    fn motion_from_shifted_rain() {
+        let grids = [
+            RainGrid::new(&layer(0, &patchy_rain((160, 160)))).unwrap(),
+            RainGrid::new(&layer(5, &patchy_rain((170, 154)))).unwrap(),
+        ];
+        assert_eq!(estimate_motion(&grids, 4.0), Some((2.0, -1.2)));
+        // Too slow to look far enough for the shift
+        assert_ne!(estimate_motion(&grids, 1.0), Some((2.0, -1.2)));
+        assert_eq!(estimate_motion(&grids[..1], 4.0), None);
+
+        // 10 pixels right and 6 up in 5 minutes at 2 pixels per km
+        let layers = [
+            layer(0, &patchy_rain((160, 160))),
+            layer(5, &patchy_rain((170, 154))),
+        ];
+        let nowcast = from_layers((400.0, 400.0), &layers).unwrap();
+        let motion = nowcast.motion.unwrap();
+        assert!((motion.speed_kmh - 2.0f64.hypot(1.2) * 30.0).abs() < 1e-9);
+        assert!((motion.bearing - 2.0f64.atan2(1.2).to_degrees()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn arrival_of_moving_rain() {
+        // Moving east at 2 pixels a minute, with its east edge at x 249 in the newest image
+        let layers = [
+            layer(0, &patchy_rain((160, 160))),
+            layer(5, &patchy_rain((170, 160))),
+        ];
+        let nowcast = from_layers((290.0, 200.0), &layers).unwrap();
+        assert_eq!(nowcast.rain_rate, 0.0);
+        let arrival = nowcast.arrival.unwrap();
+        assert!(arrival > time(5) + Duration::minutes(10));
+        assert!(arrival <= time(5) + Duration::minutes(25));
+
+        // Too far away to arrive within the lead time
+        let opts = NowcastOptions {
+            max_lead_minutes: 10,
+            ..Default::default()
+        };
+        let nowcast = from_layers_with((290.0, 200.0), &layers, &opts).unwrap();
+        assert!(nowcast.motion.is_some());
+        assert_eq!(nowcast.arrival, None);
+
+        // Moving away
+        let nowcast = from_layers((120.0, 200.0), &layers).unwrap();
+        assert!(nowcast.motion.is_some());
+        assert_eq!(nowcast.arrival, None);
+    }
+
+    #[test]
+    fn nowcast_from_layers() {
+        // Passed newest first to check they're put in order
+        let layers = [
+            layer(10, &square((300, 200), 8, 7)),
+            layer(5, &square((300, 200), 8, 5)),
+            layer(0, &square((300, 200), 8, 3)),
+        ];
+        let nowcast = from_layers((300.0, 200.0), &layers).unwrap();
+        assert_eq!(nowcast.product, "IDR402");
+        assert_eq!(nowcast.time, time(10));
+        assert_eq!(nowcast.rain_rate, 10.0);
+        assert_eq!(
+            nowcast.rates,
+            vec![(time(0), 1.5), (time(5), 4.0), (time(10), 10.0)]
+        );
+        assert_eq!(nowcast.trend, RainTrend::Increasing);
+        assert_eq!(nowcast.arrival, Some(time(10)));
+        assert!(nowcast.is_raining());
+
+        // Dry but close enough to the rain to count
+        let nowcast = from_layers((300.0, 211.5), &layers).unwrap();
+        assert_eq!(nowcast.rain_rate, 10.0);
+        let nowcast = from_layers((300.0, 220.0), &layers).unwrap();
+        assert_eq!(nowcast.rain_rate, 0.0);
+        assert_eq!(nowcast.trend, RainTrend::Steady);
+        assert!(!nowcast.is_raining());
+
+        // Too close to the edge, or no images
+        assert!(from_layers((510.0, 200.0), &layers).is_none());
+        assert!(from_layers((300.0, -50.0), &layers).is_none());
+        assert!(from_layers((300.0, 200.0), &[]).is_none());
+    }
+}
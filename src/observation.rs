@@ -1,5 +1,7 @@
+use anyhow::Result;
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Observation {
@@ -15,6 +17,120 @@ pub struct Observation {
     pub rain_since_9am: Option<f32>,
     pub humidity: u8,
     pub station: Station,
+    // The fields below are only available from the station's 72-hour product
+    /// Mean sea level pressure in hPa
+    pub pressure: Option<f64>,
+    pub dew_point: Option<f32>,
+    pub cloud: Option<String>,
+    pub cloud_oktas: Option<u32>,
+    /// Visibility in kilometres
+    pub visibility: Option<f32>,
+    /// Rainfall in the 10 minutes before the observation
+    pub rain_ten: Option<f32>,
+}
+
+impl Observation {
+    /// Copy the fields only available from the 72-hour product
+    pub fn merge_extra(&mut self, other: &Observation) {
+        self.pressure = self.pressure.or(other.pressure);
+        self.dew_point = self.dew_point.or(other.dew_point);
+        self.cloud = self.cloud.take().or_else(|| other.cloud.clone());
+        self.cloud_oktas = self.cloud_oktas.or(other.cloud_oktas);
+        self.visibility = self.visibility.or(other.visibility);
+        self.rain_ten = self.rain_ten.or(other.rain_ten);
+    }
+
+    pub fn has_extra(&self) -> bool {
+        self.pressure.is_some()
+            || self.dew_point.is_some()
+            || self.cloud.is_some()
+            || self.visibility.is_some()
+            || self.rain_ten.is_some()
+    }
+
+    /// Convert data from the 72-hour product, calculating the daily extremes that the API
+    /// provides but the product doesn't. Returned in the same order as the input (newest first)
+    pub fn from_past_observations(
+        data: &[PastObservationData],
+        station: &Station,
+    ) -> Result<Vec<Observation>> {
+        let mut observations = Vec::with_capacity(data.len());
+        // Extremes reset at 9am local time, so track them for each 9am period
+        let mut extremes: BTreeMap<&str, (Temperature, Temperature, MaxGust)> = BTreeMap::new();
+        for past in data.iter().rev() {
+            let Some(temp) = past.air_temp else {
+                continue;
+            };
+            let time = parse_aifstime(&past.aifstime_utc)?;
+            let gust = Gust {
+                speed_kilometre: past.gust_kmh.unwrap_or(0) as u8,
+                speed_knot: past.gust_kt.unwrap_or(0) as u8,
+            };
+            let (max_temp, min_temp, max_gust) = extremes
+                .entry(&past.local_9am_date_time_utc)
+                .and_modify(|(max, min, max_gust)| {
+                    if temp > max.value {
+                        *max = Temperature { time, value: temp };
+                    }
+                    if temp < min.value {
+                        *min = Temperature { time, value: temp };
+                    }
+                    if gust.speed_kilometre > max_gust.speed_kilometre {
+                        *max_gust = MaxGust {
+                            speed_kilometre: gust.speed_kilometre,
+                            speed_knot: gust.speed_knot,
+                            time,
+                        };
+                    }
+                })
+                .or_insert((
+                    Temperature { time, value: temp },
+                    Temperature { time, value: temp },
+                    MaxGust {
+                        speed_kilometre: gust.speed_kilometre,
+                        speed_knot: gust.speed_knot,
+                        time,
+                    },
+                ));
+            let direction = match past.wind_dir.as_str() {
+                "-" | "" => None,
+                dir => Some(dir.to_string()),
+            };
+
+            observations.push(Observation {
+                issue_time: time,
+                observation_time: time,
+                temp,
+                temp_feels_like: past.apparent_t.unwrap_or(temp),
+                wind: Wind {
+                    direction,
+                    speed_kilometre: past.wind_spd_kmh.unwrap_or(0) as u8,
+                    speed_knot: past.wind_spd_kt.unwrap_or(0) as u8,
+                },
+                gust,
+                max_gust: max_gust.clone(),
+                max_temp: max_temp.clone(),
+                min_temp: min_temp.clone(),
+                rain_since_9am: past.rain_trace.parse().ok(),
+                humidity: past.rel_hum.unwrap_or(0) as u8,
+                station: station.clone(),
+                pressure: past.press_msl,
+                dew_point: past.dewpt,
+                cloud: Some(past.cloud.clone()).filter(|c| c != "-"),
+                cloud_oktas: past.cloud_oktas,
+                visibility: past.vis_km.parse().ok(),
+                rain_ten: past.rain_ten.map(|r| r as f32),
+            });
+        }
+        observations.reverse();
+        Ok(observations)
+    }
+}
+
+// e.g. 20231113033000
+fn parse_aifstime(aifstime: &str) -> Result<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(aifstime, "%Y%m%d%H%M%S")?;
+    Ok(DateTime::from_naive_utc_and_offset(naive, Utc))
 }
 
 impl From<ObservationResponse> for Option<Observation> {
@@ -34,6 +150,12 @@ impl From<ObservationResponse> for Option<Observation> {
             rain_since_9am: response.data.rain_since_9am,
             humidity: response.data.humidity.unwrap(),
             station: response.data.station.unwrap(),
+            pressure: None,
+            dew_point: None,
+            cloud: None,
+            cloud_oktas: None,
+            visibility: None,
+            rain_ten: None,
         })
     }
 }
@@ -71,7 +193,7 @@ pub struct Gust {
     pub speed_knot: u8,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MaxGust {
     pub speed_kilometre: u8,
     pub speed_knot: u8,
@@ -85,7 +207,7 @@ pub struct Wind {
     pub speed_knot: u8,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Temperature {
     pub time: DateTime<Utc>,
     pub value: f32,
@@ -104,34 +226,34 @@ pub struct PastObservationData {
     pub tdz: String,
     pub aifstime_local: String,
     pub aifstime_utc: String,
-    pub air_temp: f32,
-    pub apparent_t: f32,
+    pub air_temp: Option<f32>,
+    pub apparent_t: Option<f32>,
     pub cloud: String,
     pub cloud_base_m: Option<u32>,
     pub cloud_oktas: Option<u32>,
     pub cloud_type: String,
     pub cloud_type_id: Option<String>, // Uncertain type
-    pub delta_t: f32,
-    pub dewpt: f32,
+    pub delta_t: Option<f32>,
+    pub dewpt: Option<f32>,
     pub duration_from_local_9am_date: i64,
-    pub gust_kmh: i64,
-    pub gust_kt: i64,
+    pub gust_kmh: Option<i64>,
+    pub gust_kt: Option<i64>,
     pub history_product: String,
     pub lat: f64,
     pub local_9am_date_time: String,
     pub local_9am_date_time_utc: String,
     pub lon: f64,
     pub name: String,
-    pub press: f64,
-    pub press_msl: f64,
-    pub press_qnh: f64,
+    pub press: Option<f64>,
+    pub press_msl: Option<f64>,
+    pub press_qnh: Option<f64>,
     pub press_tend: String,
-    pub rain_hour: f64,
-    pub rain_ten: f64,
+    pub rain_hour: Option<f64>,
+    pub rain_ten: Option<f64>,
     pub rain_trace: String,
     pub rain_trace_time: String,
     pub rain_trace_time_utc: String,
-    pub rel_hum: i64,
+    pub rel_hum: Option<i64>,
     pub sea_state: String,
     pub sort_order: i64,
     pub swell_dir_worded: String,
@@ -142,9 +264,9 @@ pub struct PastObservationData {
     pub vis_km: String,
     pub weather: String,
     pub wind_dir: String,
-    pub wind_dir_deg: i64,
-    pub wind_spd_kmh: i64,
-    pub wind_spd_kt: i64,
+    pub wind_dir_deg: Option<i64>,
+    pub wind_spd_kmh: Option<i64>,
+    pub wind_spd_kt: Option<i64>,
     pub wind_src: String,
     pub wmo: i64,
 }
@@ -1,11 +1,18 @@
+use crate::changes::{ChangeValue, ForecastChange};
+use crate::daily::DailyForecast;
+use crate::hourly::HourlyForecast;
 use crate::location::{Location, State};
+use crate::nowcast::Nowcast;
+use crate::observation::Observation;
 use crate::radar::{
     Radar, RadarId, RadarImageDataLayer, RadarImageFeature, RadarImageFeatureLayer,
     RadarImageLegend, RadarType,
 };
 use crate::station::WeatherStation;
+use crate::summary::ClimateSummary;
 use anyhow::{anyhow, Result};
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
 use rusqlite::{named_params, params, Connection, Row};
 use std::fs;
 use std::path::PathBuf;
@@ -22,6 +29,9 @@ impl Database {
             fs::create_dir_all(parent)?;
         }
         let connection = Connection::open(&path)?;
+        // All tables are created with IF NOT EXISTS, so this adds any that are missing
+        // from a database created by an older version
+        connection.execute_batch(include_str!("../sql/schema.sql"))?;
         Ok(Self {
             path,
             conn: connection,
@@ -165,22 +175,22 @@ impl Database {
             };
             layers.push(image);
         }
-        if layers.is_empty() {
-            return Err(anyhow!(
-                "No data layers found for radar ID {id} type {type_}"
-            ));
-        }
         layers.reverse();
         Ok(layers)
     }
 
-    pub fn get_radar_data_layer_names(&mut self, radar_type: &RadarType) -> Result<Vec<String>> {
+    pub fn get_radar_data_layer_names(
+        &self,
+        id: RadarId,
+        radar_type: &RadarType,
+    ) -> Result<Vec<String>> {
         debug!(
             "Loading existing radar data layer names from {}",
             self.path.display()
         );
-        let params = params![radar_type.id() as u8];
-        let sql = "SELECT filename FROM radar_data_layer WHERE radar_type_id = (?)";
+        let params = params![id, radar_type.id() as u8];
+        let sql = "SELECT filename FROM radar_data_layer WHERE radar_id = (?) \
+            AND radar_type_id = (?) ORDER BY timestamp";
         let mut stmt = self.conn.prepare(sql)?;
         let mut names = Vec::new();
         let mut rows = stmt.query(params)?;
@@ -294,6 +304,35 @@ impl Database {
         Ok(())
     }
 
+    /// Save the location's latest nowcast, or remove it if there isn't one
+    pub fn update_nowcast(&self, location_id: &str, nowcast: Option<&Nowcast>) -> Result<()> {
+        let Some(nowcast) = nowcast else {
+            let sql = "DELETE FROM nowcast WHERE location_id = (?)";
+            self.conn.execute(sql, params![location_id])?;
+            return Ok(());
+        };
+        let mut stmt = self
+            .conn
+            .prepare_cached(include_str!("../sql/insert_nowcast.sql"))?;
+        stmt.execute(named_params! {
+            ":location_id": location_id,
+            ":time": nowcast.time.timestamp(),
+            ":data": serde_json::to_string(nowcast)?,
+        })?;
+        Ok(())
+    }
+
+    pub fn get_nowcast(&self, location_id: &str) -> Result<Option<Nowcast>> {
+        let sql = "SELECT data FROM nowcast WHERE location_id = (?)";
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query(params![location_id])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        let json: String = row.get(0)?;
+        Ok(Some(serde_json::from_str(&json)?))
+    }
+
     pub fn insert_location(&self, location: &Location) -> Result<()> {
         debug!(
             "Inserting location {} into {}",
@@ -316,7 +355,7 @@ impl Database {
             ":state": location.state.to_string(),
             ":postcode": location.postcode,
             ":tidal_point": location.tidal_point,
-            ":timezone": location.timezone,
+            ":timezone": location.timezone.name(),
             ":weather": serde_json::to_string(&location.weather)?,
         })?;
 
@@ -357,6 +396,8 @@ impl Database {
         let state = State::from_str(&state_name).unwrap();
         let weather_json: String = row.get(12)?;
         let weather = serde_json::from_str(&weather_json).unwrap();
+        let timezone: String = row.get(11)?;
+        let timezone = Tz::from_str(&timezone).map_err(|e| anyhow!(e))?;
 
         Ok(Location {
             id: row.get(0)?,
@@ -370,7 +411,7 @@ impl Database {
             state,
             postcode: row.get(9)?,
             tidal_point: row.get(10)?,
-            timezone: row.get(11)?,
+            timezone,
             weather,
         })
     }
@@ -383,4 +424,284 @@ impl Database {
         }
         Ok(locations)
     }
+
+    /// Add the observations to the archive, replacing any with the same issue or observation time
+    pub fn archive_observations(&self, location: &Location) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = tx.prepare(include_str!("../sql/insert_observation.sql"))?;
+        // Observations from the 72-hour product don't have the same issue time as the API
+        let mut delete_stmt =
+            tx.prepare(include_str!("../sql/delete_duplicate_observation.sql"))?;
+        let mut count = 0;
+        for obs in &location.weather.observations {
+            let issue_time = obs.issue_time.timestamp();
+            // Compare against the value as it's serialized in the data column
+            let observation_time = serde_json::to_value(obs.observation_time)?;
+            count += stmt.execute(named_params! {
+                ":location_id": location.id,
+                ":issue_time": issue_time,
+                ":data": serde_json::to_string(obs)?,
+            })?;
+            delete_stmt.execute(named_params! {
+                ":location_id": location.id,
+                ":issue_time": issue_time,
+                ":observation_time": observation_time.as_str(),
+                ":start": issue_time - 60 * 60,
+                ":end": issue_time + 60 * 60,
+            })?;
+        }
+        stmt.finalize()?;
+        delete_stmt.finalize()?;
+        tx.commit()?;
+        debug!(
+            "Archived {count} observations for {} in {}",
+            location.id,
+            self.path.display()
+        );
+        Ok(count)
+    }
+
+    pub fn get_observations(
+        &self,
+        location_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Observation>> {
+        let params = params![location_id, start.timestamp(), end.timestamp()];
+        let mut stmt = self
+            .conn
+            .prepare(include_str!("../sql/get_observations.sql"))?;
+        let mut observations = Vec::new();
+        let mut rows = stmt.query(params)?;
+        while let Some(row) = rows.next()? {
+            let json: String = row.get(0)?;
+            observations.push(serde_json::from_str::<Observation>(&json)?);
+        }
+        // Observations from the 72-hour product don't have a real issue time
+        observations.sort_by_key(|o| o.observation_time);
+        Ok(observations)
+    }
+
+    pub fn delete_observations_before(
+        &self,
+        location_id: &str,
+        cutoff: DateTime<Utc>,
+    ) -> Result<usize> {
+        let sql = "DELETE FROM observation WHERE location_id = (?) AND issue_time < (?)";
+        let count = self
+            .conn
+            .execute(sql, params![location_id, cutoff.timestamp()])?;
+        if count > 0 {
+            debug!(
+                "Deleted {count} observations for {location_id} from {}",
+                self.path.display()
+            );
+        }
+        Ok(count)
+    }
+
+    pub fn insert_daily_summaries(&self, summaries: &[ClimateSummary]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = tx.prepare(include_str!("../sql/insert_daily_summary.sql"))?;
+        for summary in summaries {
+            debug!(
+                "Inserting {} summary for {} into {}",
+                summary.location_id,
+                summary.start,
+                self.path.display()
+            );
+            stmt.execute(named_params! {
+                ":location_id": summary.location_id,
+                ":date": summary.start.to_string(),
+                ":min_temp": summary.min_temp,
+                ":min_temp_time": summary.min_temp_time.map(|t| t.timestamp()),
+                ":max_temp": summary.max_temp,
+                ":max_temp_time": summary.max_temp_time.map(|t| t.timestamp()),
+                ":rain": summary.rain,
+                ":max_gust": summary.max_gust,
+                ":max_gust_direction": summary.max_gust_direction,
+                ":max_gust_time": summary.max_gust_time.map(|t| t.timestamp()),
+                ":mean_humidity": summary.mean_humidity,
+                ":observation_count": summary.observation_count,
+            })?;
+        }
+        stmt.finalize()?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_daily_summaries(
+        &self,
+        location_id: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<ClimateSummary>> {
+        let params = params![location_id, start.to_string(), end.to_string()];
+        let mut stmt = self
+            .conn
+            .prepare(include_str!("../sql/get_daily_summaries.sql"))?;
+        let timestamp = |t: Option<i64>| t.map(|t| Utc.timestamp_opt(t, 0).unwrap());
+        let mut summaries = Vec::new();
+        let mut rows = stmt.query(params)?;
+        while let Some(row) = rows.next()? {
+            let date: String = row.get(0)?;
+            let date = NaiveDate::from_str(&date)?;
+            summaries.push(ClimateSummary {
+                location_id: location_id.to_string(),
+                start: date,
+                end: date,
+                min_temp: row.get(1)?,
+                min_temp_time: timestamp(row.get(2)?),
+                max_temp: row.get(3)?,
+                max_temp_time: timestamp(row.get(4)?),
+                rain: row.get(5)?,
+                max_gust: row.get(6)?,
+                max_gust_direction: row.get(7)?,
+                max_gust_time: timestamp(row.get(8)?),
+                mean_humidity: row.get(9)?,
+                observation_count: row.get(10)?,
+            });
+        }
+        Ok(summaries)
+    }
+
+    /// Save the location's current forecasts. Existing ones with the same issue time are kept
+    /// since later versions of the hourly forecast have past hours removed
+    pub fn archive_forecasts(&self, location: &Location) -> Result<()> {
+        let daily = &location.weather.daily_forecast;
+        let mut stmt = self
+            .conn
+            .prepare_cached(include_str!("../sql/insert_daily_forecast.sql"))?;
+        stmt.execute(named_params! {
+            ":location_id": location.id,
+            ":issue_time": daily.issue_time.timestamp(),
+            ":data": serde_json::to_string(daily)?,
+        })?;
+
+        let hourly = &location.weather.hourly_forecast;
+        let mut stmt = self
+            .conn
+            .prepare_cached(include_str!("../sql/insert_hourly_forecast.sql"))?;
+        stmt.execute(named_params! {
+            ":location_id": location.id,
+            ":issue_time": hourly.issue_time.timestamp(),
+            ":data": serde_json::to_string(hourly)?,
+        })?;
+        Ok(())
+    }
+
+    pub fn get_daily_forecasts(
+        &self,
+        location_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DailyForecast>> {
+        self.get_forecasts("daily_forecast", location_id, start, end)
+    }
+
+    pub fn get_hourly_forecasts(
+        &self,
+        location_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<HourlyForecast>> {
+        self.get_forecasts("hourly_forecast", location_id, start, end)
+    }
+
+    fn get_forecasts<T: serde::de::DeserializeOwned>(
+        &self,
+        table: &str,
+        location_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<T>> {
+        let sql = format!(
+            "SELECT data FROM {table} WHERE location_id = (?) \
+            AND issue_time >= (?) AND issue_time <= (?) ORDER BY issue_time ASC"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![location_id, start.timestamp(), end.timestamp()])?;
+        let mut forecasts = Vec::new();
+        while let Some(row) = rows.next()? {
+            let json: String = row.get(0)?;
+            forecasts.push(serde_json::from_str(&json)?);
+        }
+        Ok(forecasts)
+    }
+
+    /// Save the differences between the location's last two daily forecasts.
+    /// Returns the amount that weren't already saved
+    pub fn insert_forecast_changes(&self, location: &Location) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = tx.prepare(include_str!("../sql/insert_forecast_change.sql"))?;
+        let mut count = 0;
+        for change in &location.weather.daily_changes {
+            count += stmt.execute(named_params! {
+                ":location_id": location.id,
+                ":issue_time": change.issue_time.timestamp(),
+                ":previous_issue_time": change.previous_issue_time.timestamp(),
+                ":date": change.date.timestamp(),
+                ":kind": change.kind.as_ref(),
+                ":old": change.old.as_ref().map(serde_json::to_string).transpose()?,
+                ":new": change.new.as_ref().map(serde_json::to_string).transpose()?,
+                ":difference": change.difference,
+            })?;
+        }
+        stmt.finalize()?;
+        tx.commit()?;
+        if count > 0 {
+            debug!(
+                "Inserted {count} forecast changes for {} into {}",
+                location.id,
+                self.path.display()
+            );
+        }
+        Ok(count)
+    }
+
+    /// Changes from daily forecasts issued within the period, oldest first
+    pub fn get_forecast_changes(
+        &self,
+        location_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ForecastChange>> {
+        let params = params![location_id, start.timestamp(), end.timestamp()];
+        let mut stmt = self
+            .conn
+            .prepare(include_str!("../sql/get_forecast_changes.sql"))?;
+        let timestamp = |t: i64| Utc.timestamp_opt(t, 0).unwrap();
+        let stored_value = |v: Option<String>| v.map(|v| ChangeValue::from_stored(&v));
+        let mut changes = Vec::new();
+        let mut rows = stmt.query(params)?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(3)?;
+            changes.push(ForecastChange {
+                issue_time: timestamp(row.get(0)?),
+                previous_issue_time: timestamp(row.get(1)?),
+                date: timestamp(row.get(2)?),
+                kind: kind.parse()?,
+                old: stored_value(row.get(4)?),
+                new: stored_value(row.get(5)?),
+                difference: row.get(6)?,
+            });
+        }
+        Ok(changes)
+    }
+
+    pub fn delete_forecasts_before(&self, location_id: &str, cutoff: DateTime<Utc>) -> Result<()> {
+        for table in ["daily_forecast", "hourly_forecast", "forecast_change"] {
+            let sql = format!("DELETE FROM {table} WHERE location_id = (?) AND issue_time < (?)");
+            let count = self
+                .conn
+                .execute(&sql, params![location_id, cutoff.timestamp()])?;
+            if count > 0 {
+                debug!(
+                    "Deleted {count} rows from {table} for {location_id} in {}",
+                    self.path.display()
+                );
+            }
+        }
+        Ok(())
+    }
 }
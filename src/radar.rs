@@ -1,18 +1,30 @@
-use crate::config::Config;
+use crate::animation::{write_animation, AnimationFormat};
+use crate::chart::{draw_text, fill_rect, Anchor};
+use crate::colour::Rgb;
+use crate::config::{Config, MosaicConfig};
 use crate::ftp::FtpClient;
+use crate::nowcast::rain_level;
 use crate::persistence::Database;
+use crate::terminal_graphics::{encode, ImageArea, TerminalGraphics, TerminalGuard};
+use crate::util::remove_if_exists;
 use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
 use clap::Parser;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, queue, terminal};
 use image::codecs::png::PngDecoder;
 use image::io::Reader as ImageReader;
 use image::{imageops, DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
 use mpvipc::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::ffi::OsString;
 use std::fmt::Display;
 use std::fs;
-use std::io::BufWriter;
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process::Child;
 use std::str::FromStr;
@@ -159,6 +171,16 @@ impl RadarType {
         }
     }
 
+    /// The distance from the radar to the edge of the image
+    pub fn range_km(&self) -> f64 {
+        match self.size() {
+            Self::SixtyFourKm => 64.0,
+            Self::TwoFiftySixKm => 256.0,
+            Self::FiveTwelveKm => 512.0,
+            _ => 128.0,
+        }
+    }
+
     pub fn update_frequency(self) -> Duration {
         match self {
             Self::AccumulatedSinceNine => Duration::minutes(15),
@@ -348,7 +370,35 @@ fn get_dot_indices(name: &str) -> Result<(usize, usize)> {
     Ok((first_dot, last_dot))
 }
 
+/// Text and markers drawn on top of each frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum RadarOverlay {
+    /// The frame time in the first location's timezone
+    Time,
+    /// The radar's name
+    Name,
+    /// How long before the newest frame it is, e.g. Now-25min
+    Age,
+    /// A marker at each location
+    Locations,
+}
+
+/// What's needed to draw the overlays that isn't part of the images
+#[derive(Clone, Debug)]
+pub struct RadarOverlayInfo {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Times are shown in the local timezone if there isn't one
+    pub timezone: Option<Tz>,
+    /// Names and positions of the markers
+    pub markers: Vec<(String, f64, f64)>,
+}
+
 #[derive(Clone, Parser, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct RadarImageOptions {
     pub features: Vec<RadarImageFeature>,
     pub max_frames: Option<u64>,
@@ -356,12 +406,26 @@ pub struct RadarImageOptions {
     pub remove_header: bool,
     pub create_png: bool,
     pub create_apng: bool,
+    /// Other animated formats to combine the images into
+    #[serde(default)]
+    #[arg(value_enum)]
+    pub animation_formats: Vec<AnimationFormat>,
+    #[serde(default)]
+    pub create_html: bool,
+    #[serde(default)]
+    #[arg(value_enum)]
+    pub overlays: Vec<RadarOverlay>,
     pub frame_delay_ms: u16,
     pub image_dir: PathBuf,
     pub force: bool,
     pub open_mpv: bool,
     pub mpv_args: Vec<String>,
     pub mpv_ipc_dir: PathBuf,
+    #[serde(default)]
+    pub open_terminal: bool,
+    #[serde(default)]
+    #[arg(value_enum)]
+    pub terminal_graphics: TerminalGraphics,
 }
 
 impl Default for RadarImageOptions {
@@ -379,6 +443,9 @@ impl Default for RadarImageOptions {
             remove_header: false,
             create_png: true,
             create_apng: false,
+            animation_formats: Vec::new(),
+            create_html: false,
+            overlays: Vec::new(),
             force: false,
             open_mpv: false,
             mpv_args: vec![
@@ -391,6 +458,8 @@ impl Default for RadarImageOptions {
                 "--really-quiet".into(),
             ],
             mpv_ipc_dir: Config::default_dirs().run.join("mpv-ipc"),
+            open_terminal: false,
+            terminal_graphics: TerminalGraphics::Auto,
             image_dir: Config::default_dirs().state.join("radar-images"),
         }
     }
@@ -437,12 +506,74 @@ impl PartialEq for RadarImageFrame {
     }
 }
 
+/// The pixel position of a point in the 512x512 radar area of an image with the range, treating
+/// the small area around the radar as flat
+pub fn project(radar: (f64, f64), range_km: f64, point: (f64, f64)) -> (f64, f64) {
+    let km_per_degree_latitude = 110.574;
+    let km_per_degree_longitude = 111.320 * radar.0.to_radians().cos();
+    let north = (point.0 - radar.0) * km_per_degree_latitude;
+    let east = (point.1 - radar.1) * km_per_degree_longitude;
+    let pixels_per_km = 256.0 / range_km;
+    (256.0 + east * pixels_per_km, 256.0 - north * pixels_per_km)
+}
+
+/// The latitude and longitude of a pixel position, the reverse of [`project`]
+pub fn unproject(radar: (f64, f64), range_km: f64, pixel: (f64, f64)) -> (f64, f64) {
+    let km_per_degree_latitude = 110.574;
+    let km_per_degree_longitude = 111.320 * radar.0.to_radians().cos();
+    let km_per_pixel = range_km / 256.0;
+    let east = (pixel.0 - 256.0) * km_per_pixel;
+    let north = (256.0 - pixel.1) * km_per_pixel;
+    (
+        radar.0 + north / km_per_degree_latitude,
+        radar.1 + east / km_per_degree_longitude,
+    )
+}
+
+/// White text on a black box so it can be read over any part of the image. The position is the
+/// top of the box
+fn label(image: &mut RgbaImage, x: f32, y: f32, text: &str, size: f32, anchor: Anchor) {
+    let scale = (size / 9.0).round().max(1.0);
+    let width = text.chars().count() as f32 * 6.0 * scale + scale * 3.0;
+    let height = 9.0 * scale;
+    let left = match anchor {
+        Anchor::Start => x,
+        Anchor::Middle => x - width / 2.0,
+        Anchor::End => x - width,
+    };
+    fill_rect(image, left, y, width, height, Rgb(0, 0, 0));
+    let text_x = left + scale * 2.0;
+    let baseline = y + 8.0 * scale;
+    draw_text(
+        image,
+        text_x,
+        baseline,
+        text,
+        size,
+        Rgb(255, 255, 255),
+        Anchor::Start,
+    );
+}
+
 fn decode_png(png_buf: &[u8]) -> Result<DynamicImage> {
     let decoder = PngDecoder::new(png_buf)?;
     let img = DynamicImage::from_decoder(decoder)?;
     Ok(img)
 }
 
+fn overlay_feature(
+    base: &mut DynamicImage,
+    layers: &[RadarImageFeatureLayer],
+    feature: &RadarImageFeature,
+) -> Result<()> {
+    let Some(layer) = layers.iter().find(|l| l.feature == *feature) else {
+        warn!("Missing {feature} feature");
+        return Ok(());
+    };
+    imageops::overlay(base, &decode_png(&layer.png_buf)?, 0, 0);
+    Ok(())
+}
+
 pub fn get_radar_image_managers<'a>(
     id: RadarId,
     db: &'a mut Database,
@@ -452,25 +583,8 @@ pub fn get_radar_image_managers<'a>(
     let mut managers = Vec::new();
 
     for radar_type in &opts.radar_types {
-        let feature_layers = if let Ok(layers) = db.get_radar_feature_layers(id, radar_type) {
-            layers
-        } else {
-            info!(
-                "Fetching feature layers for IDR{:02}{}",
-                id,
-                radar_type.id()
-            );
-            let layers = ftp.get_radar_feature_layers(id, *radar_type)?;
-            db.insert_radar_feature_layers(&layers)?;
-            layers
-        };
-
-        let data_layers =
-            if let Ok(layers) = db.get_radar_data_layers(id, radar_type, opts.max_frames) {
-                layers
-            } else {
-                Vec::new()
-            };
+        let feature_layers = get_feature_layers(id, radar_type, db, ftp)?;
+        let data_layers = db.get_radar_data_layers(id, radar_type, opts.max_frames)?;
 
         let legend = db.get_radar_legend(radar_type)?;
         let manager = RadarImageManager::new(
@@ -486,6 +600,58 @@ pub fn get_radar_image_managers<'a>(
     Ok(managers)
 }
 
+/// A manager for each radar type that combines the radars' images into a mosaic
+pub fn get_mosaic_image_managers(
+    mosaic: &MosaicConfig,
+    db: &mut Database,
+    ftp: &mut FtpClient,
+) -> Result<Vec<RadarImageManager>> {
+    if mosaic.radars.is_empty() {
+        return Err(anyhow!(
+            "The {} mosaic doesn't have any radars",
+            mosaic.name
+        ));
+    }
+    let mut managers = Vec::new();
+    for radar_type in &mosaic.opts.radar_types {
+        let mut sources = Vec::new();
+        for &id in &mosaic.radars {
+            let radar = db.get_radar(id)?;
+            sources.push(MosaicSource {
+                radar_id: id,
+                latitude: radar.latitude as f64,
+                longitude: radar.longitude as f64,
+                feature_layers: get_feature_layers(id, radar_type, db, ftp)?,
+                data_layers: db.get_radar_data_layers(id, radar_type, mosaic.opts.max_frames)?,
+            });
+        }
+        let legend = db.get_radar_legend(radar_type)?;
+        let manager =
+            RadarImageManager::new_mosaic(*radar_type, legend, sources, mosaic.opts.clone())?;
+        managers.push(manager);
+    }
+    Ok(managers)
+}
+
+fn get_feature_layers(
+    id: RadarId,
+    radar_type: &RadarType,
+    db: &mut Database,
+    ftp: &mut FtpClient,
+) -> Result<Vec<RadarImageFeatureLayer>> {
+    if let Ok(layers) = db.get_radar_feature_layers(id, radar_type) {
+        return Ok(layers);
+    }
+    info!(
+        "Fetching feature layers for IDR{:02}{}",
+        id,
+        radar_type.id()
+    );
+    let layers = ftp.get_radar_feature_layers(id, *radar_type)?;
+    db.insert_radar_feature_layers(&layers)?;
+    Ok(layers)
+}
+
 pub fn fetch_new_data_layers(
     id: RadarId,
     db: &mut Database,
@@ -499,7 +665,7 @@ pub fn fetch_new_data_layers(
     let mut new_layers = Vec::new();
     let mut check_ftp_files = false;
 
-    let existing_names = db.get_radar_data_layer_names(radar_type)?;
+    let existing_names = db.get_radar_data_layer_names(id, radar_type)?;
     info!(
         "Fetching new data layers for IDR{:02}{}",
         id,
@@ -602,14 +768,30 @@ pub fn update_radar_images(
     // Cache the FTP file list so we don't re-download it for each radar type
     let mut ftp_files = Vec::new();
     let mut next_datetimes = Vec::new();
+    // A radar can be in a mosaic as well as on its own, and its new images are only fetched once
+    let mut fetched: HashMap<(RadarId, char), Vec<RadarImageDataLayer>> = HashMap::new();
     for m in managers {
-        let new_data_layers =
-            fetch_new_data_layers(m.radar_id, db, &m.radar_type, ftp, &mut ftp_files, &m.opts)?;
-        if !new_data_layers.is_empty() {
-            db.insert_radar_data_layers(&new_data_layers)?;
-            m.add_data_layers(new_data_layers);
+        for id in m.radar_ids() {
+            let key = (id, m.radar_type.id());
+            let new_data_layers = match fetched.get(&key) {
+                Some(layers) => layers.clone(),
+                None => {
+                    let layers =
+                        fetch_new_data_layers(id, db, &m.radar_type, ftp, &mut ftp_files, &m.opts)?;
+                    if !layers.is_empty() {
+                        db.insert_radar_data_layers(&layers)?;
+                    }
+                    fetched.insert(key, layers.clone());
+                    layers
+                }
+            };
+            if !new_data_layers.is_empty() {
+                m.add_data_layers(new_data_layers);
+            }
         }
-        if let Some(last) = m.data_layers.last() {
+        let sources = m.mosaic.iter().flat_map(|m| &m.sources);
+        let layers = std::iter::once(&m.data_layers).chain(sources.map(|s| &s.data_layers));
+        for last in layers.filter_map(|l| l.last()) {
             next_datetimes.push(last.next_datetime() + last.radar_type.check_after());
         }
     }
@@ -622,6 +804,163 @@ pub fn update_radar_images(
     Ok(next_check)
 }
 
+const MOSAIC_DRAWN_FILE: &str = "drawn.json";
+
+/// A radar whose images are combined into a mosaic
+#[derive(Debug)]
+pub struct MosaicSource {
+    pub radar_id: RadarId,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub feature_layers: Vec<RadarImageFeatureLayer>,
+    pub data_layers: Vec<RadarImageDataLayer>,
+}
+
+/// How the pixels of radars that cover the same part of a mosaic are combined
+#[derive(Clone, Copy)]
+enum MosaicBlend {
+    /// The nearest radar's pixel, so each feature layer is split between the radars
+    Nearest,
+    /// The nearest radar's pixel that isn't transparent
+    NearestData,
+    /// The heaviest rain on the rainfall legend, or the nearest radar's if it's the same
+    HeaviestRain,
+}
+
+/// The other radars of a mosaic and the grid their images are reprojected onto. The frames
+/// follow the times of the manager's own radar
+struct Mosaic {
+    /// The position of each radar, starting with the manager's
+    positions: Vec<(f64, f64)>,
+    /// The radars other than the manager's
+    sources: Vec<MosaicSource>,
+    /// The centre and range of the grid, which is projected like a radar image
+    centre: (f64, f64),
+    range_km: f64,
+    /// The range of each radar's images
+    image_range_km: f64,
+    /// How far apart the times of images from different radars can be to be combined
+    tolerance: Duration,
+    /// The times of the other radars' images each frame was drawn with, so it's drawn again
+    /// when a closer one arrives
+    drawn: HashMap<DateTime<Utc>, Vec<Option<DateTime<Utc>>>>,
+}
+
+impl Mosaic {
+    fn new(position: (f64, f64), sources: Vec<MosaicSource>, radar_type: RadarType) -> Self {
+        let image_range_km = radar_type.range_km();
+        let positions: Vec<(f64, f64)> = std::iter::once(position)
+            .chain(sources.iter().map(|s| (s.latitude, s.longitude)))
+            .collect();
+        // Fit every radar's whole image in the grid
+        let corners: Vec<(f64, f64)> = positions
+            .iter()
+            .flat_map(|&p| {
+                [
+                    unproject(p, image_range_km, (0.0, 0.0)),
+                    unproject(p, image_range_km, (512.0, 512.0)),
+                ]
+            })
+            .collect();
+        let min = |f: fn(&(f64, f64)) -> f64| corners.iter().map(f).fold(f64::MAX, f64::min);
+        let max = |f: fn(&(f64, f64)) -> f64| corners.iter().map(f).fold(f64::MIN, f64::max);
+        let (south, north) = (min(|c| c.0), max(|c| c.0));
+        let (west, east) = (min(|c| c.1), max(|c| c.1));
+        let centre = ((south + north) / 2.0, (west + east) / 2.0);
+        let half_height = (north - south) / 2.0 * 110.574;
+        let half_width = (east - west) / 2.0 * 111.320 * centre.0.to_radians().cos();
+        Self {
+            positions,
+            sources,
+            centre,
+            range_km: half_height.max(half_width),
+            image_range_km,
+            tolerance: radar_type.update_frequency(),
+            drawn: HashMap::new(),
+        }
+    }
+
+    /// The frames' PNG files are loaded on the next start, so what they were drawn with is kept
+    /// alongside them. Otherwise they'd all be drawn again
+    fn load_drawn(&mut self, image_dir: &Path) {
+        let Ok(json) = fs::read_to_string(image_dir.join(MOSAIC_DRAWN_FILE)) else {
+            return;
+        };
+        match serde_json::from_str(&json) {
+            Ok(drawn) => self.drawn = drawn,
+            Err(e) => warn!(
+                "Unable to read {}. {e}",
+                image_dir.join(MOSAIC_DRAWN_FILE).display()
+            ),
+        }
+    }
+
+    fn save_drawn(&self, image_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string(&self.drawn)?;
+        fs::write(image_dir.join(MOSAIC_DRAWN_FILE), json)?;
+        Ok(())
+    }
+
+    fn matching_times(&self, datetime: DateTime<Utc>) -> Vec<Option<DateTime<Utc>>> {
+        self.matching_layers(datetime)
+            .iter()
+            .map(|l| l.map(|l| l.datetime))
+            .collect()
+    }
+
+    /// The other radars' images closest in time to the manager's image
+    fn matching_layers(&self, datetime: DateTime<Utc>) -> Vec<Option<&RadarImageDataLayer>> {
+        self.sources
+            .iter()
+            .map(|source| {
+                source
+                    .data_layers
+                    .iter()
+                    .filter(|l| (l.datetime - datetime).abs() <= self.tolerance)
+                    .min_by_key(|l| (l.datetime - datetime).abs())
+            })
+            .collect()
+    }
+
+    /// Reproject an image of each radar onto the grid, using the nearest pixel so the colours
+    /// still match the legend. The header at the top of data images is left out
+    fn combine(&self, images: &[Option<RgbaImage>], blend: MosaicBlend) -> RgbaImage {
+        let top = match blend {
+            MosaicBlend::Nearest => 0.0,
+            MosaicBlend::NearestData | MosaicBlend::HeaviestRain => 16.0,
+        };
+        RgbaImage::from_fn(512, 512, |x, y| {
+            let point = unproject(self.centre, self.range_km, (x as f64 + 0.5, y as f64 + 0.5));
+            let mut best: Option<(f64, u8, Rgba<u8>)> = None;
+            for (position, image) in self.positions.iter().zip(images) {
+                let Some(image) = image else {
+                    continue;
+                };
+                let (px, py) = project(*position, self.image_range_km, point);
+                if !(0.0..512.0).contains(&px) || !(top..512.0).contains(&py) {
+                    continue;
+                }
+                let pixel = *image.get_pixel(px as u32, py as u32);
+                let distance = (px - 256.0).hypot(py - 256.0);
+                let level = rain_level(&pixel);
+                let better = match (blend, best) {
+                    (MosaicBlend::NearestData, _) if pixel[3] == 0 => false,
+                    (MosaicBlend::HeaviestRain, _) if level == 0 => false,
+                    (_, None) => true,
+                    (MosaicBlend::HeaviestRain, Some((d, l, _))) => {
+                        level > l || (level == l && distance < d)
+                    }
+                    (_, Some((d, _, _))) => distance < d,
+                };
+                if better {
+                    best = Some((distance, level, pixel));
+                }
+            }
+            best.map_or(Rgba([0, 0, 0, 0]), |(_, _, pixel)| pixel)
+        })
+    }
+}
+
 pub struct RadarImageManager {
     image_dir: PathBuf,
     radar_type: RadarType,
@@ -632,14 +971,29 @@ pub struct RadarImageManager {
     frames: Vec<RadarImageFrame>,
     pub opts: RadarImageOptions,
     mpv: Option<MpvRadarViewer>,
+    overlay_info: Option<RadarOverlayInfo>,
+    /// The newest frame's time when the frames were last drawn, which their ages are relative to
+    newest: Option<DateTime<Utc>>,
+    /// Whether existing PNG files are out of date, because the frames were drawn again
+    rewrite: bool,
+    mosaic: Option<Mosaic>,
 }
 
 impl Display for RadarImageManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "IDR{:02}{}", self.radar_id, self.radar_type.id())
+        write!(f, "{}", product_name(&self.radar_ids(), self.radar_type))
     }
 }
 
+/// e.g. IDR403, or IDR403+IDR713 for a mosaic
+fn product_name(radar_ids: &[RadarId], radar_type: RadarType) -> String {
+    radar_ids
+        .iter()
+        .map(|id| format!("IDR{:02}{}", id, radar_type.id()))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
 impl RadarImageManager {
     pub fn new(
         radar_id: RadarId,
@@ -649,9 +1003,58 @@ impl RadarImageManager {
         feature_layers: Vec<RadarImageFeatureLayer>,
         opts: RadarImageOptions,
     ) -> Result<Self> {
-        let mpv = MpvRadarViewer::from_opts(radar_id, radar_type, &opts)?;
-        let image_dirname = format!("IDR{:02}{}", radar_id, radar_type.id());
-        let image_dir = opts.image_dir.join(image_dirname);
+        Self::build(
+            radar_id,
+            radar_type,
+            legend,
+            data_layers,
+            feature_layers,
+            opts,
+            None,
+        )
+    }
+
+    /// Combine the images of several radars into one loop. The first radar's image times are
+    /// used for the frames, with the closest image of each other radar
+    pub fn new_mosaic(
+        radar_type: RadarType,
+        legend: RadarImageLegend,
+        sources: Vec<MosaicSource>,
+        opts: RadarImageOptions,
+    ) -> Result<Self> {
+        let mut sources = sources.into_iter();
+        let first = sources
+            .next()
+            .ok_or_else(|| anyhow!("A radar mosaic needs at least one radar"))?;
+        let position = (first.latitude, first.longitude);
+        let mosaic = Mosaic::new(position, sources.collect(), radar_type);
+        Self::build(
+            first.radar_id,
+            radar_type,
+            legend,
+            first.data_layers,
+            first.feature_layers,
+            opts,
+            Some(mosaic),
+        )
+    }
+
+    fn build(
+        radar_id: RadarId,
+        radar_type: RadarType,
+        legend: RadarImageLegend,
+        data_layers: Vec<RadarImageDataLayer>,
+        feature_layers: Vec<RadarImageFeatureLayer>,
+        opts: RadarImageOptions,
+        mut mosaic: Option<Mosaic>,
+    ) -> Result<Self> {
+        let mut radar_ids = vec![radar_id];
+        if let Some(mosaic) = &mosaic {
+            radar_ids.extend(mosaic.sources.iter().map(|s| s.radar_id));
+        }
+        let name = product_name(&radar_ids, radar_type);
+        let mpv = MpvRadarViewer::from_opts(&name, &opts)?;
+        let image_dir = opts.image_dir.join(name);
         let mut frames = Vec::new();
         if let Ok(entries) = fs::read_dir(&image_dir) {
             for entry in entries {
@@ -660,6 +1063,9 @@ impl RadarImageManager {
                 }
             }
         }
+        if let Some(mosaic) = &mut mosaic {
+            mosaic.load_drawn(&image_dir);
+        }
 
         Ok(Self {
             mpv,
@@ -671,21 +1077,66 @@ impl RadarImageManager {
             frames,
             image_dir,
             opts,
+            overlay_info: None,
+            newest: None,
+            rewrite: false,
+            mosaic,
         })
     }
 
-    fn construct_frames(&mut self) -> Result<()> {
+    /// The manager's radar, followed by the other radars if it's a mosaic
+    pub fn radar_ids(&self) -> Vec<RadarId> {
+        let mut ids = vec![self.radar_id];
+        if let Some(mosaic) = &self.mosaic {
+            ids.extend(mosaic.sources.iter().map(|s| s.radar_id));
+        }
+        ids
+    }
+
+    /// The time of the newest data layer, from any of the radars if it's a mosaic
+    pub fn newest_data_time(&self) -> Option<DateTime<Utc>> {
+        let sources = self.mosaic.iter().flat_map(|m| &m.sources);
+        std::iter::once(&self.data_layers)
+            .chain(sources.map(|s| &s.data_layers))
+            .flatten()
+            .map(|l| l.datetime)
+            .max()
+    }
+
+    pub fn set_overlay_info(&mut self, info: RadarOverlayInfo) {
+        self.overlay_info = Some(info);
+    }
+
+    /// The composed frames, oldest first
+    pub fn frames(&self) -> &[RadarImageFrame] {
+        &self.frames
+    }
+
+    pub fn construct_frames(&mut self) -> Result<()> {
         let mut bottom_layer = decode_png(&self.legend.png_buf)?;
         let mut top_layer = DynamicImage::ImageRgba8(RgbaImage::new(512, 512));
 
         if self.opts.force {
             self.frames.clear();
         }
+        // Every frame's age changes when there's a new one
+        let newest = self.data_layers.iter().map(|l| l.datetime).max();
+        if self.opts.overlays.contains(&RadarOverlay::Age) && newest != self.newest {
+            self.frames.clear();
+            self.rewrite = true;
+        }
+        self.newest = newest;
 
         let mut todo = Vec::new();
         for layer in &self.data_layers {
             if !self.frames.iter().any(|f| f.datetime == layer.datetime) {
                 todo.push(layer);
+            } else if let Some(mosaic) = &self.mosaic {
+                // A closer image from another radar has arrived since it was drawn
+                let matching = mosaic.matching_times(layer.datetime);
+                if mosaic.drawn.get(&layer.datetime) != Some(&matching) {
+                    todo.push(layer);
+                }
             }
         }
 
@@ -693,24 +1144,83 @@ impl RadarImageManager {
             return Ok(());
         }
 
-        for feature in &self.opts.features {
-            match feature {
-                RadarImageFeature::Background | RadarImageFeature::Topography => {
-                    self.overlay_feature(&mut bottom_layer, feature)?
+        if let Some(mosaic) = &self.mosaic {
+            let mut bottoms = Vec::new();
+            let mut tops = Vec::new();
+            let sources = mosaic.sources.iter().map(|s| &s.feature_layers);
+            for feature_layers in std::iter::once(&self.feature_layers).chain(sources) {
+                let mut bottom = DynamicImage::ImageRgba8(RgbaImage::new(512, 512));
+                let mut top = DynamicImage::ImageRgba8(RgbaImage::new(512, 512));
+                for feature in &self.opts.features {
+                    match feature {
+                        RadarImageFeature::Background | RadarImageFeature::Topography => {
+                            overlay_feature(&mut bottom, feature_layers, feature)?
+                        }
+                        _ => overlay_feature(&mut top, feature_layers, feature)?,
+                    }
+                }
+                bottoms.push(Some(bottom.to_rgba8()));
+                tops.push(Some(top.to_rgba8()));
+            }
+            let bottom = mosaic.combine(&bottoms, MosaicBlend::Nearest);
+            imageops::overlay(&mut bottom_layer, &bottom, 0, 0);
+            top_layer = DynamicImage::ImageRgba8(mosaic.combine(&tops, MosaicBlend::Nearest));
+        } else {
+            for feature in &self.opts.features {
+                match feature {
+                    RadarImageFeature::Background | RadarImageFeature::Topography => {
+                        overlay_feature(&mut bottom_layer, &self.feature_layers, feature)?
+                    }
+                    _ => overlay_feature(&mut top_layer, &self.feature_layers, feature)?,
                 }
-                _ => self.overlay_feature(&mut top_layer, feature)?,
             }
         }
 
         for layer in todo {
             debug!("Constructing frame for {}", layer.filename);
-            let mut data_layer = decode_png(&layer.png_buf)?;
-            if self.opts.remove_header {
-                self.remove_header(&mut data_layer);
-            }
+            let data_layer = match &mut self.mosaic {
+                Some(mosaic) => {
+                    let mut images = vec![Some(decode_png(&layer.png_buf)?.to_rgba8())];
+                    for other in mosaic.matching_layers(layer.datetime) {
+                        images.push(match other {
+                            Some(other) => Some(decode_png(&other.png_buf)?.to_rgba8()),
+                            None => None,
+                        });
+                    }
+                    let blend = match self.radar_type.legend_type() {
+                        RadarLegendType::Rainfall => MosaicBlend::HeaviestRain,
+                        _ => MosaicBlend::NearestData,
+                    };
+                    let image = mosaic.combine(&images, blend);
+                    let matching = mosaic.matching_times(layer.datetime);
+                    mosaic.drawn.insert(layer.datetime, matching);
+                    DynamicImage::ImageRgba8(image)
+                }
+                None => {
+                    let mut data_layer = decode_png(&layer.png_buf)?;
+                    if self.opts.remove_header {
+                        self.remove_header(&mut data_layer);
+                    }
+                    data_layer
+                }
+            };
             let mut final_image = bottom_layer.clone();
             imageops::overlay(&mut final_image, &data_layer, 0, 0);
             imageops::overlay(&mut final_image, &top_layer, 0, 0);
+            if !self.opts.overlays.is_empty() {
+                let mut image = final_image.to_rgba8();
+                self.draw_overlays(&mut image, layer.datetime);
+                final_image = DynamicImage::ImageRgba8(image);
+            }
+            // A mosaic frame being drawn again replaces the old one
+            if let Some(i) = self
+                .frames
+                .iter()
+                .position(|f| f.datetime == layer.datetime)
+            {
+                let old = self.frames.remove(i);
+                remove_if_exists(&old.path)?;
+            }
             let frame = RadarImageFrame {
                 radar_id: layer.radar_id,
                 radar_type: layer.radar_type,
@@ -723,6 +1233,56 @@ impl RadarImageManager {
         Ok(())
     }
 
+    fn draw_overlays(&self, image: &mut RgbaImage, datetime: DateTime<Utc>) {
+        let Some(info) = &self.overlay_info else {
+            warn!("Missing radar details for the {self} overlays");
+            return;
+        };
+        for overlay in &self.opts.overlays {
+            match overlay {
+                RadarOverlay::Time => {
+                    let time = match info.timezone {
+                        Some(tz) => datetime.with_timezone(&tz).format("%a %d %b %H:%M %Z"),
+                        None => datetime.with_timezone(&Local).format("%a %d %b %H:%M"),
+                    };
+                    label(image, 4.0, 4.0, &time.to_string(), 18.0, Anchor::Start);
+                }
+                RadarOverlay::Age => {
+                    let age = (self.newest.unwrap_or(datetime) - datetime).num_minutes();
+                    let text = match age {
+                        0 => "Now".to_string(),
+                        age => format!("Now-{age}min"),
+                    };
+                    label(image, 508.0, 4.0, &text, 18.0, Anchor::End);
+                }
+                RadarOverlay::Name => {
+                    let text = match self.mosaic {
+                        Some(_) => info.name.clone(),
+                        None => format!("{} {}km", info.name, self.radar_type.range_km()),
+                    };
+                    label(image, 4.0, 497.0, &text, 9.0, Anchor::Start);
+                }
+                RadarOverlay::Locations => {
+                    let (centre, range_km) = match &self.mosaic {
+                        Some(mosaic) => (mosaic.centre, mosaic.range_km),
+                        None => ((info.latitude, info.longitude), self.radar_type.range_km()),
+                    };
+                    for (name, latitude, longitude) in &info.markers {
+                        let (x, y) = project(centre, range_km, (*latitude, *longitude));
+                        if !(0.0..512.0).contains(&x) || !(0.0..512.0).contains(&y) {
+                            continue;
+                        }
+                        let (x, y) = (x as f32, y as f32);
+                        fill_rect(image, x - 3.0, y - 3.0, 7.0, 7.0, Rgb(0, 0, 0));
+                        fill_rect(image, x - 2.0, y - 2.0, 5.0, 5.0, Rgb(255, 255, 255));
+                        fill_rect(image, x - 1.0, y - 1.0, 3.0, 3.0, Rgb(0, 0, 0));
+                        label(image, x + 6.0, y - 5.0, name, 9.0, Anchor::Start);
+                    }
+                }
+            }
+        }
+    }
+
     fn remove_header(&self, image: &mut DynamicImage) {
         let DynamicImage::ImageRgba8(ref mut rgba_image) = image else {
             return;
@@ -734,20 +1294,28 @@ impl RadarImageManager {
         }
     }
 
-    fn overlay_feature(&self, base: &mut DynamicImage, feature: &RadarImageFeature) -> Result<()> {
-        let Some(layer) = self.feature_layers.iter().find(|l| l.feature == *feature) else {
-            warn!("Missing {feature} feature");
-            return Ok(());
-        };
-        imageops::overlay(base, &decode_png(&layer.png_buf)?, 0, 0);
-        Ok(())
-    }
-
     fn sort_frames(&mut self) {
         self.frames
             .sort_by(|a, b| a.datetime.partial_cmp(&b.datetime).unwrap());
     }
 
+    /// Whether an image with the same radar, type and time is still used, including by the
+    /// other radars of a mosaic
+    pub fn has_data_layer(&self, layer: &RadarImageDataLayer) -> bool {
+        let same = |l: &RadarImageDataLayer| {
+            l.radar_id == layer.radar_id
+                && l.radar_type == layer.radar_type
+                && l.datetime == layer.datetime
+        };
+        self.data_layers.iter().any(same)
+            || self.mosaic.as_ref().is_some_and(|mosaic| {
+                mosaic
+                    .sources
+                    .iter()
+                    .any(|source| source.data_layers.iter().any(same))
+            })
+    }
+
     fn remove_images(&mut self, idx: usize) -> Result<Vec<RadarImageDataLayer>> {
         // Prevent a panic if the database is somehow out of sync with the filesystem
         let didx = idx.min(self.data_layers.len());
@@ -787,24 +1355,57 @@ impl RadarImageManager {
                 }
             }
         }
+
+        // Keep the other radars' images that can still be matched to a frame
+        if let Some(mosaic) = &mut self.mosaic {
+            let oldest = self
+                .data_layers
+                .first()
+                .map(|l| l.datetime - mosaic.tolerance);
+            for source in &mut mosaic.sources {
+                let idx = source
+                    .data_layers
+                    .iter()
+                    .take_while(|l| Some(l.datetime) < oldest)
+                    .count();
+                removed.extend(source.data_layers.drain(..idx));
+            }
+            let frames = &self.frames;
+            mosaic
+                .drawn
+                .retain(|datetime, _| frames.iter().any(|f| f.datetime == *datetime));
+        }
         Ok(removed)
     }
     pub fn write_pngs(&mut self) -> Result<()> {
         self.construct_frames()?;
         fs::create_dir_all(&self.image_dir)?;
         for frame in &self.frames {
-            if frame.path.exists() && !self.opts.force {
+            if frame.path.exists() && !self.opts.force && !self.rewrite {
                 continue;
             }
             let file = fs::File::create(&frame.path)?;
             let mut writer = BufWriter::new(file);
             frame.image.write_to(&mut writer, ImageOutputFormat::Png)?;
         }
+        if let Some(mosaic) = &self.mosaic {
+            mosaic.save_drawn(&self.image_dir)?;
+        }
+        self.rewrite = false;
         Ok(())
     }
 
-    pub fn add_data_layers(&mut self, mut layers: Vec<RadarImageDataLayer>) {
-        self.data_layers.append(&mut layers);
+    pub fn add_data_layers(&mut self, layers: Vec<RadarImageDataLayer>) {
+        for layer in layers {
+            let source = self
+                .mosaic
+                .as_mut()
+                .and_then(|m| m.sources.iter_mut().find(|s| s.radar_id == layer.radar_id));
+            match source {
+                Some(source) => source.data_layers.push(layer),
+                None => self.data_layers.push(layer),
+            }
+        }
     }
 
     pub fn open_images(&mut self) -> Result<()> {
@@ -841,22 +1442,229 @@ impl RadarImageManager {
 
         Ok(())
     }
+
+    pub fn create_animation(&mut self, format: AnimationFormat) -> Result<PathBuf> {
+        self.construct_frames()?;
+        self.sort_frames();
+        let filename = format!("{}.{}", self.frame_span_name()?, format.as_ref());
+        fs::create_dir_all(&self.image_dir)?;
+        let path = self.image_dir.join(filename);
+        let images: Vec<RgbaImage> = self.frames.iter().map(|f| f.image.to_rgba8()).collect();
+        write_animation(&path, &images, self.opts.frame_delay_ms, format)
+            .with_context(|| format!("Unable to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// The file name for the sorted frames, without an extension
+    fn frame_span_name(&self) -> Result<String> {
+        let (Some(first), Some(last)) = (self.frames.first(), self.frames.last()) else {
+            return Err(anyhow!("No {self} radar frames to write"));
+        };
+        let start = first.datetime.format("%Y%m%d%H%M");
+        let end = last.datetime.format("%Y%m%d%H%M");
+        Ok(format!("{self}.T.{start}-{end}"))
+    }
+
+    /// Write a single HTML page that plays the frames, with each one embedded as a PNG
+    pub fn create_html(&mut self) -> Result<PathBuf> {
+        self.construct_frames()?;
+        self.sort_frames();
+        let filename = format!("{}.html", self.frame_span_name()?);
+        fs::create_dir_all(&self.image_dir)?;
+        let path = self.image_dir.join(filename);
+
+        const LOCAL_FORMAT: &str = "%a %d %b %Y %H:%M %:z";
+        let mut frames = Vec::new();
+        for frame in &self.frames {
+            let mut png = io::Cursor::new(Vec::new());
+            frame.image.write_to(&mut png, ImageOutputFormat::Png)?;
+            frames.push(serde_json::json!({
+                "png": STANDARD.encode(png.into_inner()),
+                "utc": frame.datetime.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "local": match self.overlay_info.as_ref().and_then(|info| info.timezone) {
+                    Some(tz) => frame.datetime.with_timezone(&tz).format(LOCAL_FORMAT).to_string(),
+                    None => frame.datetime.with_timezone(&Local).format(LOCAL_FORMAT).to_string(),
+                },
+            }));
+        }
+        let title = format!("{self} {}", self.radar_type);
+        let html = include_str!("radar_viewer.html")
+            .replace("{{title}}", &title)
+            .replace("{{delay}}", &self.opts.frame_delay_ms.to_string())
+            .replace("{{frames}}", &serde_json::to_string(&frames)?);
+        fs::write(&path, html)?;
+        Ok(path)
+    }
+}
+
+/// Plays the radar loops inline in the terminal, one radar at a time
+pub struct TerminalRadarViewer {
+    graphics: TerminalGraphics,
+    selected: usize,
+    frame: usize,
+    area: Option<ImageArea>,
+    /// Encoded frames by radar and time, so each is only encoded once per terminal size
+    cache: HashMap<(String, DateTime<Utc>), String>,
+    /// Timezone of the main location for the frame times, falling back to the local one
+    timezone: Option<Tz>,
+    _guard: TerminalGuard,
+}
+
+impl TerminalRadarViewer {
+    pub fn new(graphics: TerminalGraphics, timezone: Option<Tz>) -> Result<Self> {
+        let graphics = graphics.resolve();
+        debug!("Showing radar images in the terminal with {graphics} graphics");
+        Ok(Self {
+            graphics,
+            selected: 0,
+            frame: 0,
+            area: None,
+            cache: HashMap::new(),
+            timezone,
+            _guard: TerminalGuard::new()?,
+        })
+    }
+
+    /// Loop the frames of the managers with open_terminal set until the deadline, or until the
+    /// user quits if there isn't one. Returns false if the user quit
+    pub fn play(
+        &mut self,
+        managers: &[RadarImageManager],
+        until: Option<DateTime<Utc>>,
+    ) -> Result<bool> {
+        let managers: Vec<&RadarImageManager> =
+            managers.iter().filter(|m| m.opts.open_terminal).collect();
+        if managers.is_empty() {
+            return Ok(true);
+        }
+        // Frames with their age drawn on them change whenever there's a new frame
+        self.cache.retain(|(name, datetime), _| {
+            managers.iter().any(|m| {
+                &m.to_string() == name
+                    && !m.opts.overlays.contains(&RadarOverlay::Age)
+                    && m.frames.iter().any(|f| f.datetime == *datetime)
+            })
+        });
+        // Clear anything logged while updating
+        queue!(io::stdout(), terminal::Clear(terminal::ClearType::All))?;
+        let delay = |manager: &RadarImageManager| {
+            Duration::milliseconds(manager.opts.frame_delay_ms.into())
+        };
+        let mut manager = managers[self.selected % managers.len()];
+        self.draw(manager, managers.len())?;
+        // Only moving on to the next frame when it's due, so events don't skip frames
+        let mut next_frame = Utc::now() + delay(manager);
+        loop {
+            let now = Utc::now();
+            if until.is_some_and(|until| now >= until) {
+                return Ok(true);
+            }
+            let timeout = (next_frame - now).max(Duration::zero());
+            if !event::poll(timeout.to_std()?)? {
+                self.frame += 1;
+                self.draw(manager, managers.len())?;
+                next_frame = Utc::now() + delay(manager);
+                continue;
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Ok(false)
+                        }
+                        KeyCode::Right | KeyCode::Tab | KeyCode::Char('l') => {
+                            self.select(self.selected + 1)?;
+                        }
+                        KeyCode::Left | KeyCode::BackTab | KeyCode::Char('h') => {
+                            self.select(self.selected + managers.len() - 1)?;
+                        }
+                        _ => continue,
+                    }
+                    manager = managers[self.selected % managers.len()];
+                    self.draw(manager, managers.len())?;
+                    next_frame = Utc::now() + delay(manager);
+                }
+                Event::Resize(..) => {
+                    self.area = None;
+                    self.cache.clear();
+                    queue!(io::stdout(), terminal::Clear(terminal::ClearType::All))?;
+                    self.draw(manager, managers.len())?;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn select(&mut self, selected: usize) -> Result<()> {
+        self.selected = selected;
+        self.frame = 0;
+        self.area = None;
+        queue!(io::stdout(), terminal::Clear(terminal::ClearType::All))?;
+        Ok(())
+    }
+
+    fn draw(&mut self, manager: &RadarImageManager, count: usize) -> Result<()> {
+        let mut stdout = io::stdout();
+        let Some(frame) = manager.frames.get(self.frame % manager.frames.len().max(1)) else {
+            queue!(stdout, cursor::MoveTo(0, 0))?;
+            write!(stdout, "No images for {manager} yet")?;
+            stdout.flush()?;
+            return Ok(());
+        };
+        let key = (manager.to_string(), frame.datetime);
+        if !self.cache.contains_key(&key) {
+            let image = frame.image.to_rgba8();
+            let area = match self.area {
+                Some(area) => area,
+                None => {
+                    let (columns, rows) = terminal::size()?;
+                    let area = ImageArea::fit(&image, columns, rows.saturating_sub(1));
+                    self.area = Some(area);
+                    area
+                }
+            };
+            self.cache
+                .insert(key.clone(), encode(&image, self.graphics, area)?);
+        }
+
+        let position = self.frame % manager.frames.len() + 1;
+        let time = match self.timezone {
+            Some(tz) => frame.datetime.with_timezone(&tz).format("%a %H:%M"),
+            None => frame.datetime.with_timezone(&Local).format("%a %H:%M"),
+        };
+        let mut header = format!(
+            "{manager} {} {time} ({position}/{})",
+            manager.radar_type,
+            manager.frames.len(),
+        );
+        if count > 1 {
+            header.push_str("  ←/→ switch radar");
+        }
+        header.push_str("  q quit");
+        queue!(
+            stdout,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )?;
+        write!(stdout, "{header}")?;
+        queue!(stdout, cursor::MoveTo(0, 1))?;
+        write!(stdout, "{}", self.cache[&key])?;
+        stdout.flush()?;
+        Ok(())
+    }
 }
 
 struct MpvRadarViewer {
     handle: Option<Child>,
     socket_path: PathBuf,
-    radar_id: RadarId,
-    radar_type: RadarType,
+    /// The product name e.g. IDR403
+    name: String,
     frame_delay: f32,
 }
 
 impl MpvRadarViewer {
-    pub fn from_opts(
-        radar_id: RadarId,
-        radar_type: RadarType,
-        opts: &RadarImageOptions,
-    ) -> Result<Option<Self>> {
+    pub fn from_opts(name: &str, opts: &RadarImageOptions) -> Result<Option<Self>> {
         if opts.open_mpv {
             let output = std::process::Command::new("mpv")
                 .arg("--version")
@@ -870,10 +1678,9 @@ impl MpvRadarViewer {
                 debug!("Using {}", stdout);
             }
 
-            let socket_name = format!("IDR{:02}{}.sock", radar_id, radar_type.id());
+            let socket_name = format!("{name}.sock");
             Ok(Some(Self {
-                radar_id,
-                radar_type,
+                name: name.to_string(),
                 socket_path: opts.mpv_ipc_dir.join(socket_name),
                 handle: None,
                 frame_delay: opts.frame_delay_ms as f32 / 1000.0,
@@ -937,7 +1744,7 @@ impl MpvRadarViewer {
     fn start(&mut self, image_paths: &[&Path], args: &[String]) -> Result<()> {
         let mut ipc_arg = OsString::from("--input-ipc-server=");
         ipc_arg.push(&self.socket_path);
-        let app_id = format!("mpv-radar-IDR{:02}{}", self.radar_id, self.radar_type.id());
+        let app_id = format!("mpv-radar-{}", self.name);
         fs::create_dir_all(self.socket_path.parent().unwrap())?;
         let child = std::process::Command::new("mpv")
             .arg(ipc_arg)
@@ -954,3 +1761,79 @@ impl MpvRadarViewer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIGHT: Rgba<u8> = Rgba([0x78, 0x78, 0xFF, 0xFF]);
+    const HEAVY: Rgba<u8> = Rgba([0xFF, 0x96, 0x00, 0xFF]);
+
+    fn assert_close(a: (f64, f64), b: (f64, f64)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn project_round_trip() {
+        for radar in [(-35.66, 149.51), (-12.46, 130.93), (-42.84, 147.51)] {
+            for range_km in [64.0, 128.0, 256.0, 512.0] {
+                assert_close(project(radar, range_km, radar), (256.0, 256.0));
+                for pixel in [(0.0, 0.0), (511.5, 3.25), (100.0, 400.0), (-20.0, 600.0)] {
+                    let point = unproject(radar, range_km, pixel);
+                    assert_close(project(radar, range_km, point), pixel);
+                    assert_close(unproject(radar, range_km, pixel), point);
+                }
+            }
+        }
+        // North is up and east is right
+        let (x, y) = project((-35.0, 149.0), 128.0, (-34.9, 149.1));
+        assert!(x > 256.0 && y < 256.0);
+    }
+
+    #[test]
+    fn mosaic_blends() {
+        // Two radars 60km apart, so their 128km images overlap
+        let west = (-35.0, 149.0);
+        let east = (-35.0, 149.0 + 60.0 / (111.320 * 35.0f64.to_radians().cos()));
+        let source = MosaicSource {
+            radar_id: 2,
+            latitude: east.0,
+            longitude: east.1,
+            feature_layers: Vec::new(),
+            data_layers: Vec::new(),
+        };
+        let mosaic = Mosaic::new(west, vec![source], RadarType::OneTwentyEightKm);
+        let images = [
+            Some(RgbaImage::from_pixel(512, 512, LIGHT)),
+            Some(RgbaImage::from_pixel(512, 512, HEAVY)),
+        ];
+        let pixel = |image: &RgbaImage, point: (f64, f64)| {
+            let (x, y) = project(mosaic.centre, mosaic.range_km, point);
+            *image.get_pixel(x as u32, y as u32)
+        };
+        // In the overlap, a quarter of the way from the west radar to the east one
+        let near_west = (west.0, west.1 + (east.1 - west.1) / 4.0);
+        let near_east = (west.0, west.1 + (east.1 - west.1) * 3.0 / 4.0);
+
+        let nearest = mosaic.combine(&images, MosaicBlend::NearestData);
+        assert_eq!(pixel(&nearest, near_west), LIGHT);
+        assert_eq!(pixel(&nearest, near_east), HEAVY);
+        let heaviest = mosaic.combine(&images, MosaicBlend::HeaviestRain);
+        assert_eq!(pixel(&heaviest, near_west), HEAVY);
+        assert_eq!(pixel(&heaviest, near_east), HEAVY);
+
+        // Only the west radar covers its own corner, where the header is left out
+        assert_eq!(*nearest.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+
+        // A missing image or transparent pixels leave it to the other radar
+        let images = [Some(RgbaImage::new(512, 512)), images[1].clone()];
+        let nearest = mosaic.combine(&images, MosaicBlend::NearestData);
+        assert_eq!(pixel(&nearest, near_west), HEAVY);
+        let images = [None, Some(RgbaImage::from_pixel(512, 512, LIGHT))];
+        let heaviest = mosaic.combine(&images, MosaicBlend::HeaviestRain);
+        assert_eq!(pixel(&heaviest, near_west), LIGHT);
+    }
+}
@@ -1,13 +1,24 @@
+use crate::chart::{render_charts, ChartOptions};
 use crate::client::Client;
-use crate::radar::{Radar, RadarId};
+use crate::config::RadarConfig;
+use crate::nowcast::{Nowcast, NowcastOptions};
+use crate::observation::{Observation, Station};
+use crate::radar::{Radar, RadarId, RadarLegendType};
+use crate::summary::ClimateSummary;
+use crate::units::Units;
+use crate::util::{compass_point, local_date, start_of_day};
+use crate::verification::Verification;
 use crate::{
     location::{Location, SearchResult},
     persistence::Database,
 };
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use geo::{HaversineDistance, Point, RhumbBearing};
+use std::collections::HashMap;
 use std::fmt::{self, Display};
+use std::path::PathBuf;
+use tracing::{info, warn};
 
 pub fn create_location(
     result: SearchResult,
@@ -22,7 +33,7 @@ pub fn create_location(
         None
     };
 
-    let location = Location {
+    let mut location = Location {
         geohash: result.geohash,
         station,
         has_wave: location_data.has_wave,
@@ -34,11 +45,20 @@ pub fn create_location(
         longitude: location_data.longitude,
         marine_area_id: location_data.marine_area_id,
         tidal_point: location_data.tidal_point,
-        timezone: location_data.timezone,
+        timezone: location_data.timezone.parse().map_err(|e| anyhow!("{e}"))?,
         weather,
     };
 
+    if let Err(e) = backfill_observations(&mut location, client) {
+        warn!("Unable to fetch past observations for {}. {e}", location.id);
+    }
     database.insert_location(&location)?;
+    archive_observations(&location, database)?;
+    archive_forecasts(&location, database)?;
+    if let Some(oldest) = location.weather.observations.back() {
+        let start = local_date(oldest.observation_time, location.timezone);
+        update_summaries(&location, database, start)?;
+    }
 
     Ok(location)
 }
@@ -67,17 +87,7 @@ pub fn get_nearby_radars(location: &Location, radars: &[Radar]) -> Vec<NearbyRad
         let distance = (distance / 1000.0) as i32;
         let bearing = location_point.rhumb_bearing(radar_point);
 
-        let direction = match bearing {
-            x if x < 22.5 => "N",
-            x if x < 67.5 => "NE",
-            x if x < 112.5 => "E",
-            x if x < 157.5 => "SE",
-            x if x < 202.5 => "S",
-            x if x < 247.5 => "SW",
-            x if x < 292.5 => "W",
-            x if x < 337.5 => "NW",
-            _ => "N",
-        };
+        let direction = compass_point(bearing);
 
         nearby_radars.push(NearbyRadar {
             id: radar.id,
@@ -146,11 +156,266 @@ pub fn update_if_due(
 ) -> Result<DateTime<Utc>> {
     let mut next_datetimes = Vec::with_capacity(locations.len());
     for location in locations {
-        let (was_updated, next_check) = location.weather.update_if_due(client)?;
+        let (mut was_updated, next_check) = location.weather.update_if_due(client)?;
+        let mut summary_start = location.today() - Duration::days(1);
+        if location.weather.opts.check_observations
+            && Utc::now() > location.weather.next_past_observations_due
+        {
+            match backfill_observations(location, client) {
+                Ok(added) => {
+                    was_updated = true;
+                    let oldest = location.weather.observations.back();
+                    if let Some(oldest) = oldest.filter(|_| added > 0) {
+                        summary_start = local_date(oldest.observation_time, location.timezone);
+                    }
+                }
+                Err(e) => warn!("Unable to fetch past observations for {}. {e}", location.id),
+            }
+        }
         if was_updated {
             database.update_weather(location)?;
+            archive_observations(location, database)?;
+            archive_forecasts(location, database)?;
+            update_summaries(location, database, summary_start)?;
         }
-        next_datetimes.push(next_check);
+        next_datetimes.push(next_check.min(location.weather.next_past_observations_due));
     }
     Ok(*next_datetimes.iter().min().unwrap())
 }
+
+/// Save new observations to the database and remove any older than the retention period
+pub fn archive_observations(location: &Location, database: &Database) -> Result<()> {
+    database.archive_observations(location)?;
+    if let Some(cutoff) = location
+        .weather
+        .opts
+        .observation_retention
+        .cutoff(Utc::now())
+    {
+        database.delete_observations_before(&location.id, cutoff)?;
+    }
+    Ok(())
+}
+
+/// Save the current forecasts for verification and remove any older than the retention period.
+/// Any new changes to the daily forecast are saved, and notified if enabled
+pub fn archive_forecasts(location: &Location, database: &Database) -> Result<()> {
+    database.archive_forecasts(location)?;
+    if database.insert_forecast_changes(location)? > 0 {
+        notify_forecast_changes(location);
+    }
+    if let Some(cutoff) = location.weather.opts.forecast_retention.cutoff(Utc::now()) {
+        database.delete_forecasts_before(&location.id, cutoff)?;
+    }
+    Ok(())
+}
+
+/// Render the location's charts with observations from the last few days for the history chart
+pub fn update_charts(
+    location: &Location,
+    database: &Database,
+    units: &Units,
+    opts: &ChartOptions,
+) -> Result<Vec<PathBuf>> {
+    let end = Utc::now();
+    let start = end - Duration::days(opts.history_days.into());
+    let observations = database.get_observations(&location.id, start, end)?;
+    render_charts(location, &observations, units, opts)
+}
+
+/// Check the stored images of the configured rainfall radars for rain at the location, using
+/// the most detailed one that covers it. Returns None if none of them have recent images
+pub fn radar_nowcast(
+    location: &Location,
+    database: &Database,
+    radars: &[RadarConfig],
+    opts: &NowcastOptions,
+) -> Result<Option<Nowcast>> {
+    let mut products = Vec::new();
+    for radar in radars {
+        for radar_type in &radar.opts.radar_types {
+            if matches!(radar_type.legend_type(), RadarLegendType::Rainfall) {
+                products.push((radar, *radar_type));
+            }
+        }
+    }
+    products.sort_by(|a, b| a.1.range_km().total_cmp(&b.1.range_km()));
+
+    let oldest = Utc::now() - Duration::minutes(opts.max_age_minutes);
+    for (radar, radar_type) in products {
+        let layers =
+            database.get_radar_data_layers(radar.id, &radar_type, radar.opts.max_frames)?;
+        if layers.iter().all(|l| l.datetime < oldest) {
+            continue;
+        }
+        let details = database.get_radar(radar.id)?;
+        let position = (details.latitude as f64, details.longitude as f64);
+        let nowcast = Nowcast::from_layers(
+            (location.latitude, location.longitude),
+            position,
+            radar_type.range_km(),
+            &layers,
+            opts,
+        )?;
+        if nowcast.is_some() {
+            return Ok(nowcast);
+        }
+    }
+    Ok(None)
+}
+
+/// Work out each location's nowcast from the latest radar images and save it, so it's only done
+/// once per image rather than whenever it's shown
+pub fn update_nowcasts(
+    locations: &[Location],
+    database: &Database,
+    radars: &[RadarConfig],
+    opts: &NowcastOptions,
+) -> Result<()> {
+    for location in locations {
+        let nowcast = radar_nowcast(location, database, radars, opts)?;
+        database.update_nowcast(&location.id, nowcast.as_ref())?;
+    }
+    Ok(())
+}
+
+/// The location's saved nowcast, unless its images are too old
+pub fn latest_nowcast(
+    location: &Location,
+    database: &Database,
+    opts: &NowcastOptions,
+) -> Result<Option<Nowcast>> {
+    let oldest = Utc::now() - Duration::minutes(opts.max_age_minutes);
+    Ok(database
+        .get_nowcast(&location.id)?
+        .filter(|nowcast| nowcast.time >= oldest))
+}
+
+fn notify_forecast_changes(location: &Location) {
+    let opts = &location.weather.opts;
+    let descriptions: Vec<String> = location
+        .weather
+        .daily_changes
+        .iter()
+        .filter(|c| c.is_significant(opts.notify_temp_change, opts.notify_rain_chance_change))
+        .map(|c| c.description(location.timezone))
+        .collect();
+    if descriptions.is_empty() {
+        return;
+    }
+    info!(
+        "{} forecast revised. {}",
+        location.id,
+        descriptions.join(". ")
+    );
+    if !opts.notify_forecast_changes {
+        return;
+    }
+    let result = std::process::Command::new("notify-send")
+        .arg(format!("Forecast revised for {}", location.name))
+        .arg(descriptions.join("\n"))
+        .status();
+    if let Err(e) = result {
+        warn!("Unable to send notification with notify-send. {e}");
+    }
+}
+
+/// Fill in observations from the station's 72-hour product. Extra fields like pressure and dew
+/// point are merged into the observations from the API. Returns the amount of observations added
+pub fn backfill_observations(location: &mut Location, client: &Client) -> Result<usize> {
+    let weather = &mut location.weather;
+    weather.next_past_observations_due =
+        Utc::now() + weather.opts.past_observation_update_frequency;
+    let data = client.get_past_observations(location)?;
+
+    let station = if let Some(obs) = location.weather.observation() {
+        obs.station.clone()
+    } else if let Some(station) = &location.station {
+        let location_point = Point::new(location.longitude, location.latitude);
+        let station_point = Point::new(station.longitude, station.latitude);
+        Station {
+            bom_id: station.id.to_string(),
+            distance: location_point.haversine_distance(&station_point),
+            name: station.name.clone(),
+        }
+    } else {
+        return Err(anyhow!("{} doesn't have a weather station", location.id));
+    };
+
+    let observations = Observation::from_past_observations(&data, &station)?;
+    let added = location.weather.merge_past_observations(observations);
+    if added > 0 {
+        info!("Added {added} past observations for {}", location.id);
+    }
+    Ok(added)
+}
+
+/// Recalculate the daily summaries from the start date up to today using archived observations
+pub fn update_summaries(
+    location: &Location,
+    database: &Database,
+    start: NaiveDate,
+) -> Result<Vec<ClimateSummary>> {
+    let tz = location.timezone;
+    let end = location.today();
+    // Include the previous day's last observation so rain after midnight can be calculated
+    let observations = database.get_observations(
+        &location.id,
+        start_of_day(start, tz) - Duration::hours(1),
+        start_of_day(end + Duration::days(1), tz),
+    )?;
+    let mut summaries = Vec::new();
+    let mut date = start;
+    while date <= end {
+        let day_start = start_of_day(date, tz) - Duration::hours(1);
+        let day_end = start_of_day(date + Duration::days(1), tz);
+        let first = observations.partition_point(|o| o.observation_time < day_start);
+        let last = observations.partition_point(|o| o.observation_time < day_end);
+        if let Some(summary) =
+            ClimateSummary::from_observations(&location.id, date, &observations[first..last], tz)
+        {
+            summaries.push(summary);
+        }
+        date += Duration::days(1);
+    }
+    database.insert_daily_summaries(&summaries)?;
+    Ok(summaries)
+}
+
+/// Score the archived forecasts for days within the period against the observations
+pub fn verify_forecasts(
+    location: &Location,
+    database: &Database,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Verification> {
+    let tz = location.timezone;
+    let mut verification = Verification::new(&location.id, start, end, tz);
+    let period_start = start_of_day(start, tz);
+    let period_end = start_of_day(end + Duration::days(1), tz);
+
+    let summaries = database.get_daily_summaries(&location.id, start, end)?;
+    let summaries: HashMap<NaiveDate, &ClimateSummary> =
+        summaries.iter().map(|s| (s.start, s)).collect();
+    // The daily forecast covers 7 days and the hourly forecast covers 3 days
+    let daily_forecasts =
+        database.get_daily_forecasts(&location.id, period_start - Duration::days(8), period_end)?;
+    for forecast in &daily_forecasts {
+        verification.add_daily(forecast, &summaries);
+    }
+
+    let observations = database.get_observations(
+        &location.id,
+        period_start - Duration::hours(1),
+        period_end + Duration::hours(1),
+    )?;
+    let hourly_forecasts = database.get_hourly_forecasts(
+        &location.id,
+        period_start - Duration::days(4),
+        period_end,
+    )?;
+    for forecast in &hourly_forecasts {
+        verification.add_hourly(forecast, &observations);
+    }
+    Ok(verification)
+}
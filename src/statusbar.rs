@@ -0,0 +1,147 @@
+use crate::fstring::Fstring;
+use crate::weather::CurrentWeather;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// How `current` prints its output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusBarFormat {
+    /// Just the rendered fstring
+    #[default]
+    Plain,
+    /// JSON for a custom module with `"return-type": "json"`
+    Waybar,
+    /// Full text, short text and colour lines
+    I3blocks,
+    /// Text wrapped in colour formatting tags
+    Polybar,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StatusBarOptions {
+    /// Shown on hover in Waybar
+    pub tooltip_fstring: String,
+    /// Used by i3blocks when there isn't room for the full text
+    pub short_fstring: String,
+    /// Colour used by i3blocks and Polybar while a warning is active
+    pub warning_colour: String,
+}
+
+impl Default for StatusBarOptions {
+    fn default() -> Self {
+        // The next 6 hours e.g. "14:00 ⛅ 24°C 10%"
+        let tooltip_fstring = (1..=6)
+            .map(|h| {
+                format!(
+                    "{{hour+{h}.time}} {{hour+{h}.icon}} {{hour+{h}.temp}}{{temp_unit}} {{hour+{h}.rain_chance}}%"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self {
+            tooltip_fstring,
+            short_fstring: "{icon} {temp}".to_string(),
+            warning_colour: "#FF5555".to_string(),
+        }
+    }
+}
+
+/// The fstrings used to build status bar output, parsed once up front
+pub struct StatusBar<'a> {
+    pub format: StatusBarFormat,
+    pub text: Fstring,
+    pub tooltip: Fstring,
+    pub short: Fstring,
+    pub opts: &'a StatusBarOptions,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct WaybarOutput {
+    text: String,
+    tooltip: String,
+    class: Vec<String>,
+    percentage: Option<u8>,
+}
+
+impl<'a> StatusBar<'a> {
+    pub fn new(
+        format: StatusBarFormat,
+        text: &str,
+        tooltip: Option<&str>,
+        opts: &'a StatusBarOptions,
+    ) -> Result<Self> {
+        Ok(Self {
+            format,
+            text: Fstring::parse(text)?,
+            tooltip: Fstring::parse(tooltip.unwrap_or(&opts.tooltip_fstring))?,
+            short: Fstring::parse(&opts.short_fstring)?,
+            opts,
+        })
+    }
+
+    pub fn render(&self, current: &CurrentWeather) -> Result<String> {
+        let text = self.text.render(current);
+        let has_warning = !current.warnings.is_empty();
+        let output = match self.format {
+            StatusBarFormat::Plain => text,
+            StatusBarFormat::Waybar => {
+                let output = WaybarOutput {
+                    text: escape_markup(&text),
+                    tooltip: escape_markup(&self.tooltip.render(current)),
+                    class: classes(current),
+                    percentage: Some(current.hourly_rain_chance),
+                };
+                serde_json::to_string(&output)?
+            }
+            StatusBarFormat::I3blocks => {
+                let mut lines = vec![text, self.short.render(current)];
+                if has_warning {
+                    lines.push(self.opts.warning_colour.clone());
+                }
+                lines.join("\n")
+            }
+            StatusBarFormat::Polybar if has_warning => {
+                format!("%{{F{}}}{text}%{{F-}}", self.opts.warning_colour)
+            }
+            StatusBarFormat::Polybar => text,
+        };
+        Ok(output)
+    }
+}
+
+/// CSS classes for the current conditions e.g. ["partly-cloudy", "night", "raining", "warning",
+/// "severe-thunderstorm-warning"]
+fn classes(current: &CurrentWeather) -> Vec<String> {
+    let mut classes = vec![css_class(current.icon_descriptor.as_ref())];
+    if current.is_night {
+        classes.push("night".to_string());
+    }
+    if current.nowcast.as_ref().is_some_and(|n| n.is_raining()) {
+        classes.push("raining".to_string());
+    }
+    if !current.warnings.is_empty() {
+        classes.push("warning".to_string());
+    }
+    for warning in current.warnings {
+        let class = css_class(&warning.r#type);
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    }
+    classes
+}
+
+fn css_class(name: &str) -> String {
+    name.to_lowercase().replace([' ', '_'], "-")
+}
+
+// Waybar renders text and tooltips as Pango markup
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
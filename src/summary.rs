@@ -0,0 +1,307 @@
+use crate::location::Location;
+use crate::observation::Observation;
+use crate::units::Units;
+use crate::util::local_date;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+/// Statistics calculated from the observations over one or more days
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClimateSummary {
+    pub location_id: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub min_temp: Option<f32>,
+    pub min_temp_time: Option<DateTime<Utc>>,
+    pub max_temp: Option<f32>,
+    pub max_temp_time: Option<DateTime<Utc>>,
+    pub rain: Option<f32>,
+    pub max_gust: Option<u8>,
+    pub max_gust_direction: Option<String>,
+    pub max_gust_time: Option<DateTime<Utc>>,
+    pub mean_humidity: Option<f32>,
+    pub observation_count: u32,
+}
+
+impl ClimateSummary {
+    /// Summarise a single day in the timezone. Observations must be sorted from oldest to newest,
+    /// and can include the last one from the previous day so that rain just after midnight is counted
+    pub fn from_observations(
+        location_id: &str,
+        date: NaiveDate,
+        observations: &[Observation],
+        tz: Tz,
+    ) -> Option<Self> {
+        let mut summary = Self::empty(location_id, date, date);
+        let mut humidity_total = 0.0;
+        let mut previous: Option<&Observation> = None;
+
+        for obs in observations {
+            if local_date(obs.observation_time, tz) != date {
+                previous = Some(obs);
+                continue;
+            }
+            summary.observation_count += 1;
+            humidity_total += obs.humidity as f32;
+
+            if summary.min_temp.is_none_or(|t| obs.temp < t) {
+                summary.min_temp = Some(obs.temp);
+                summary.min_temp_time = Some(obs.observation_time);
+            }
+            if summary.max_temp.is_none_or(|t| obs.temp > t) {
+                summary.max_temp = Some(obs.temp);
+                summary.max_temp_time = Some(obs.observation_time);
+            }
+            if summary
+                .max_gust
+                .is_none_or(|g| obs.gust.speed_kilometre > g)
+            {
+                summary.max_gust = Some(obs.gust.speed_kilometre);
+                summary.max_gust_direction = obs.wind.direction.clone();
+                summary.max_gust_time = Some(obs.observation_time);
+            }
+            if let Some(rain) = rain_between(previous, obs, tz) {
+                summary.rain = Some(summary.rain.unwrap_or(0.0) + rain);
+            }
+            previous = Some(obs);
+        }
+
+        if summary.observation_count == 0 {
+            return None;
+        }
+        summary.mean_humidity = Some(humidity_total / summary.observation_count as f32);
+        Some(summary)
+    }
+
+    /// Combine daily summaries into a summary for the whole period
+    pub fn combine(
+        location_id: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+        days: &[&ClimateSummary],
+    ) -> Self {
+        let mut summary = Self::empty(location_id, start, end);
+        let mut humidity_total = 0.0;
+        for day in days {
+            if let Some(min) = day.min_temp {
+                if summary.min_temp.is_none_or(|t| min < t) {
+                    summary.min_temp = Some(min);
+                    summary.min_temp_time = day.min_temp_time;
+                }
+            }
+            if let Some(max) = day.max_temp {
+                if summary.max_temp.is_none_or(|t| max > t) {
+                    summary.max_temp = Some(max);
+                    summary.max_temp_time = day.max_temp_time;
+                }
+            }
+            if let Some(gust) = day.max_gust {
+                if summary.max_gust.is_none_or(|g| gust > g) {
+                    summary.max_gust = Some(gust);
+                    summary.max_gust_direction = day.max_gust_direction.clone();
+                    summary.max_gust_time = day.max_gust_time;
+                }
+            }
+            if let Some(rain) = day.rain {
+                summary.rain = Some(summary.rain.unwrap_or(0.0) + rain);
+            }
+            if let Some(humidity) = day.mean_humidity {
+                humidity_total += humidity * day.observation_count as f32;
+            }
+            summary.observation_count += day.observation_count;
+        }
+        if summary.observation_count > 0 {
+            summary.mean_humidity = Some(humidity_total / summary.observation_count as f32);
+        }
+        summary
+    }
+
+    fn empty(location_id: &str, start: NaiveDate, end: NaiveDate) -> Self {
+        Self {
+            location_id: location_id.to_string(),
+            start,
+            end,
+            min_temp: None,
+            min_temp_time: None,
+            max_temp: None,
+            max_temp_time: None,
+            rain: None,
+            max_gust: None,
+            max_gust_direction: None,
+            max_gust_time: None,
+            mean_humidity: None,
+            observation_count: 0,
+        }
+    }
+}
+
+/// A summary with values converted to the units and times in the location's timezone
+#[derive(Debug, Serialize)]
+pub struct SummaryRow<'a> {
+    pub location_id: &'a str,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub min_temp: Option<f32>,
+    pub min_temp_time: Option<DateTime<Tz>>,
+    pub max_temp: Option<f32>,
+    pub max_temp_time: Option<DateTime<Tz>>,
+    pub rain: Option<f32>,
+    pub max_gust: Option<f32>,
+    pub max_gust_direction: Option<&'a str>,
+    pub max_gust_time: Option<DateTime<Tz>>,
+    pub mean_humidity: Option<f32>,
+    pub observation_count: u32,
+}
+
+impl<'a> SummaryRow<'a> {
+    pub fn new(location: &'a Location, summary: &'a ClimateSummary, units: &Units) -> Self {
+        let time = |t: Option<DateTime<Utc>>| t.map(|t| t.with_timezone(&location.timezone));
+        Self {
+            location_id: &location.id,
+            start: summary.start,
+            end: summary.end,
+            min_temp: summary.min_temp.map(|t| units.temp(t)),
+            min_temp_time: time(summary.min_temp_time),
+            max_temp: summary.max_temp.map(|t| units.temp(t)),
+            max_temp_time: time(summary.max_temp_time),
+            rain: summary.rain.map(|r| units.rain(r)),
+            max_gust: summary.max_gust.map(|g| units.wind_kmh(g.into())),
+            max_gust_direction: summary.max_gust_direction.as_deref(),
+            max_gust_time: time(summary.max_gust_time),
+            mean_humidity: summary.mean_humidity,
+            observation_count: summary.observation_count,
+        }
+    }
+}
+
+/// The rain that fell between two consecutive observations. rain_since_9am resets just after
+/// the 9am observation, so if a reset happened in between, all of the current value is new rain.
+/// Any rain between the previous observation and the reset can't be known.
+fn rain_between(previous: Option<&Observation>, current: &Observation, tz: Tz) -> Option<f32> {
+    let rain = current.rain_since_9am?;
+    let Some((previous, previous_rain)) =
+        previous.and_then(|p| p.rain_since_9am.map(|rain| (p, rain)))
+    else {
+        // Without an earlier value we only know when the rain fell if it was after a reset
+        return (current.observation_time.with_timezone(&tz).time() > nine_am()).then_some(rain);
+    };
+    if rain_period(previous.observation_time, tz) != rain_period(current.observation_time, tz) {
+        Some(rain)
+    } else {
+        Some((rain - previous_rain).max(0.0))
+    }
+}
+
+/// The date of the 9am that started the rain period containing the time. The 9am observation
+/// itself belongs to the previous period
+fn rain_period(time: DateTime<Utc>, tz: Tz) -> NaiveDate {
+    let local = time.with_timezone(&tz);
+    if local.time() > nine_am() {
+        local.date_naive()
+    } else {
+        local.date_naive() - Duration::days(1)
+    }
+}
+
+fn nine_am() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+}
+
+#[derive(Clone, Copy, Debug, Default, Display, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryPeriod {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl SummaryPeriod {
+    /// The first day of the period containing the date. Weeks start on Monday
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// The last day of the period starting on the date
+    pub fn end_of(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => start,
+            Self::Week => start + Duration::days(6),
+            Self::Month => start
+                .checked_add_months(chrono::Months::new(1))
+                .unwrap()
+                .pred_opt()
+                .unwrap(),
+        }
+    }
+
+    /// The default start date when showing the most recent periods up to the date
+    pub fn default_start(&self, end: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => end - Duration::days(6),
+            Self::Week => self.start_of(end) - Duration::weeks(7),
+            Self::Month => self
+                .start_of(end)
+                .checked_sub_months(chrono::Months::new(11))
+                .unwrap(),
+        }
+    }
+
+    /// Combine daily summaries (sorted by date) into summaries for each period
+    pub fn group(&self, days: &[ClimateSummary]) -> Vec<ClimateSummary> {
+        if let Self::Day = self {
+            return days.to_vec();
+        }
+        let mut periods = Vec::new();
+        let mut iter = days.iter().peekable();
+        while let Some(first) = iter.next() {
+            let start = self.start_of(first.start);
+            let end = self.end_of(start);
+            let mut group = vec![first];
+            while let Some(day) = iter.next_if(|d| d.start <= end) {
+                group.push(day);
+            }
+            periods.push(ClimateSummary::combine(
+                &first.location_id,
+                start,
+                end,
+                &group,
+            ));
+        }
+        periods
+    }
+
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Self::Day => start.format("%a %d %b").to_string(),
+            Self::Week => start.format("Week of %d %b").to_string(),
+            Self::Month => start.format("%b %Y").to_string(),
+        }
+    }
+}
+
+/// The rain that fell yesterday and so far this month, if any observations were recorded
+pub fn rain_totals(
+    location_id: &str,
+    summaries: &[ClimateSummary],
+    today: NaiveDate,
+) -> (Option<f32>, Option<f32>) {
+    let yesterday = today - Duration::days(1);
+    let yesterday_rain = summaries
+        .iter()
+        .find(|s| s.start == yesterday)
+        .and_then(|s| s.rain);
+    let month_start = SummaryPeriod::Month.start_of(today);
+    let month: Vec<&ClimateSummary> = summaries
+        .iter()
+        .filter(|s| s.start >= month_start && s.start <= today)
+        .collect();
+    let month_rain = ClimateSummary::combine(location_id, month_start, today, &month).rain;
+    (yesterday_rain, month_rain)
+}
@@ -0,0 +1,280 @@
+use crate::animation::IndexedImage;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::{cursor, execute, terminal};
+use image::imageops::{self, FilterType};
+use image::{ImageOutputFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::io::{self, Cursor};
+use strum_macros::Display;
+
+/// Switches to a full screen without line buffering, and puts the terminal back to normal when
+/// dropped, even on an error
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn new() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// How images are drawn in the terminal
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Display, Deserialize, Serialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum TerminalGraphics {
+    /// Detect what the terminal supports
+    #[default]
+    Auto,
+    /// The kitty graphics protocol (kitty, WezTerm, Ghostty, Konsole)
+    Kitty,
+    /// Inline images (iTerm2, WezTerm)
+    Iterm,
+    /// Sixel graphics (foot, mlterm, xterm -ti vt340, Windows Terminal)
+    Sixel,
+    /// Coloured half-block characters, which work in any terminal with true colour
+    Blocks,
+}
+
+impl TerminalGraphics {
+    /// Pick a protocol from the environment. Terminals don't reliably answer queries over SSH,
+    /// so this relies on TERM (which SSH forwards) and the variables set by terminal emulators
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if term.contains("kitty")
+            || term.contains("ghostty")
+            || !var("KITTY_WINDOW_ID").is_empty()
+            || program == "ghostty"
+            || !var("KONSOLE_VERSION").is_empty()
+        {
+            Self::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" {
+            Self::Iterm
+        } else if term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || term.contains("sixel")
+            || term == "yaft-256color"
+            || !var("WT_SESSION").is_empty()
+        {
+            Self::Sixel
+        } else {
+            Self::Blocks
+        }
+    }
+
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto => Self::detect(),
+            other => other,
+        }
+    }
+}
+
+/// The area an image is drawn in, in terminal cells and pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ImageArea {
+    pub columns: u16,
+    pub rows: u16,
+    pub cell_width: u16,
+    pub cell_height: u16,
+}
+
+impl ImageArea {
+    /// The largest area with the image's aspect ratio that fits in the columns and rows
+    pub fn fit(image: &RgbaImage, columns: u16, rows: u16) -> Self {
+        let (cell_width, cell_height) = cell_size();
+        let aspect = image.width() as f32 / image.height() as f32;
+        let max_width = columns as f32 * cell_width as f32;
+        let max_height = rows as f32 * cell_height as f32;
+        let (width, height) = if max_width / max_height > aspect {
+            (max_height * aspect, max_height)
+        } else {
+            (max_width, max_width / aspect)
+        };
+        Self {
+            columns: ((width / cell_width as f32).floor() as u16).max(1),
+            rows: ((height / cell_height as f32).floor() as u16).max(1),
+            cell_width,
+            cell_height,
+        }
+    }
+
+    fn pixels(&self) -> (u32, u32) {
+        (
+            self.columns as u32 * self.cell_width as u32,
+            self.rows as u32 * self.cell_height as u32,
+        )
+    }
+}
+
+/// The size of a terminal cell in pixels, falling back to a common size if the terminal
+/// doesn't report it
+fn cell_size() -> (u16, u16) {
+    // SAFETY: TIOCGWINSZ only writes to the winsize struct
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col > 0 && size.ws_row > 0 && size.ws_xpixel > 0 {
+        (size.ws_xpixel / size.ws_col, size.ws_ypixel / size.ws_row)
+    } else {
+        (10, 20)
+    }
+}
+
+/// Encode the image as escape sequences that draw it at the cursor, filling the area
+pub fn encode(image: &RgbaImage, graphics: TerminalGraphics, area: ImageArea) -> Result<String> {
+    match graphics.resolve() {
+        TerminalGraphics::Kitty => kitty(image, area),
+        TerminalGraphics::Iterm => iterm(image, area),
+        TerminalGraphics::Sixel => Ok(sixel(image, area)),
+        TerminalGraphics::Auto | TerminalGraphics::Blocks => Ok(half_blocks(image, area)),
+    }
+}
+
+fn png_base64(image: &RgbaImage) -> Result<String> {
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+    Ok(STANDARD.encode(png.into_inner()))
+}
+
+/// The terminal scales the image to the columns and rows. Reusing the image ID replaces the
+/// previous frame. Only 4096 bytes can be sent at a time
+fn kitty(image: &RgbaImage, area: ImageArea) -> Result<String> {
+    let data = png_base64(image)?;
+    let mut output = String::new();
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk)?;
+        if i == 0 {
+            write!(
+                output,
+                "\x1b_Ga=T,f=100,i=1,q=2,C=1,c={},r={},m={more};{chunk}\x1b\\",
+                area.columns, area.rows
+            )?;
+        } else {
+            write!(output, "\x1b_Gm={more};{chunk}\x1b\\")?;
+        }
+    }
+    Ok(output)
+}
+
+fn iterm(image: &RgbaImage, area: ImageArea) -> Result<String> {
+    Ok(format!(
+        "\x1b]1337;File=inline=1;width={};height={};preserveAspectRatio=1:{}\x07",
+        area.columns,
+        area.rows,
+        png_base64(image)?
+    ))
+}
+
+/// Six pixel high bands with up to 256 colours. Images with more are reduced with median cut
+fn sixel(image: &RgbaImage, area: ImageArea) -> String {
+    let (width, height) = area.pixels();
+    // Blending pixels when resizing would add colours that aren't in the palette
+    let image = imageops::resize(image, width, height, FilterType::Nearest);
+
+    let IndexedImage { palette, indices } = IndexedImage::new(&image);
+
+    let mut output = format!("\x1bPq\"1;1;{width};{height}");
+    for (i, [r, g, b, _]) in palette.iter().enumerate() {
+        let percent = |v: &u8| *v as u32 * 100 / 255;
+        let _ = write!(
+            output,
+            "#{i};2;{};{};{}",
+            percent(r),
+            percent(g),
+            percent(b)
+        );
+    }
+    let (width, height) = (width as usize, height as usize);
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut colours: Vec<u8> = (band..band + rows)
+            .flat_map(|y| indices[y * width..(y + 1) * width].iter().copied())
+            .collect();
+        colours.sort_unstable();
+        colours.dedup();
+        for (n, colour) in colours.iter().enumerate() {
+            if n > 0 {
+                // Return to the start of the band to draw the next colour
+                output.push('$');
+            }
+            let _ = write!(output, "#{colour}");
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let mut bits = 0;
+                for row in 0..rows {
+                    if indices[(band + row) * width + x] == *colour {
+                        bits |= 1 << row;
+                    }
+                }
+                let c = char::from(63 + bits);
+                run = match run {
+                    Some((last, count)) if last == c => Some((c, count + 1)),
+                    Some((last, count)) => {
+                        push_run(&mut output, last, count);
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some((last, count)) = run {
+                // Trailing empty sixels don't need to be sent
+                if last != '?' {
+                    push_run(&mut output, last, count);
+                }
+            }
+        }
+        output.push('-');
+    }
+    output.push_str("\x1b\\");
+    output
+}
+
+fn push_run(output: &mut String, c: char, count: usize) {
+    if count > 3 {
+        let _ = write!(output, "!{count}{c}");
+    } else {
+        output.extend(std::iter::repeat_n(c, count));
+    }
+}
+
+/// Two pixels per cell using the upper half block, with the top pixel as the foreground colour
+/// and the bottom as the background
+fn half_blocks(image: &RgbaImage, area: ImageArea) -> String {
+    let (width, height) = (area.columns as u32, area.rows as u32 * 2);
+    let image = imageops::resize(image, width, height, FilterType::Triangle);
+    let mut output = String::new();
+    for row in 0..area.rows as u32 {
+        if row > 0 {
+            // Move down a line and back to the image's first column
+            let _ = write!(output, "\x1b[1B\x1b[{width}D");
+        }
+        for x in 0..width {
+            let top = image.get_pixel(x, row * 2);
+            let bottom = image.get_pixel(x, row * 2 + 1);
+            let _ = write!(
+                output,
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+            );
+        }
+        output.push_str("\x1b[0m");
+    }
+    output
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// The units used to display temperature, wind speed and rainfall. The API and database always
+/// use °C, km/h (or knots) and mm, so values are only converted when they're output
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub wind: WindUnit,
+    pub rain: RainUnit,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    #[value(alias = "c")]
+    Celsius,
+    #[value(alias = "f")]
+    Fahrenheit,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WindUnit {
+    #[default]
+    Kmh,
+    Knots,
+    Ms,
+    Mph,
+    Beaufort,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RainUnit {
+    #[default]
+    Mm,
+    #[serde(rename = "in")]
+    #[value(name = "in", alias = "inches")]
+    Inches,
+}
+
+// Upper limit in km/h of each Beaufort number up to 11. Anything faster is 12
+const BEAUFORT_LIMITS: [f32; 12] = [
+    1.0, 6.0, 12.0, 20.0, 29.0, 39.0, 50.0, 62.0, 75.0, 89.0, 103.0, 118.0,
+];
+
+// Conversions are rounded to a sensible precision for display
+impl Units {
+    pub fn temp(&self, celsius: f32) -> f32 {
+        match self.temperature {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => round(celsius * 1.8 + 32.0, 1),
+        }
+    }
+
+    /// Convert a difference between temperatures e.g. a forecast error
+    pub fn temp_difference(&self, celsius: f32) -> f32 {
+        match self.temperature {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 1.8,
+        }
+    }
+
+    pub fn format_temp(&self, celsius: f32) -> String {
+        self.temp(celsius).to_string()
+    }
+
+    /// The API provides speeds in both km/h and knots, so use the knots directly if wanted
+    pub fn wind(&self, kmh: u8, knots: u8) -> f32 {
+        self.convert_wind(kmh as f32, knots as f32)
+    }
+
+    /// For speeds that were only stored or calculated in km/h
+    pub fn wind_kmh(&self, kmh: f32) -> f32 {
+        self.convert_wind(kmh, (kmh / 1.852).round())
+    }
+
+    fn convert_wind(&self, kmh: f32, knots: f32) -> f32 {
+        match self.wind {
+            WindUnit::Kmh => kmh.round(),
+            WindUnit::Knots => knots,
+            WindUnit::Ms => round(kmh / 3.6, 1),
+            WindUnit::Mph => (kmh / 1.609_344).round(),
+            WindUnit::Beaufort => BEAUFORT_LIMITS
+                .iter()
+                .position(|&limit| kmh < limit)
+                .unwrap_or(BEAUFORT_LIMITS.len()) as f32,
+        }
+    }
+
+    pub fn format_wind(&self, kmh: u8, knots: u8) -> String {
+        self.wind(kmh, knots).to_string()
+    }
+
+    pub fn rain(&self, mm: f32) -> f32 {
+        match self.rain {
+            RainUnit::Mm => round(mm, 1),
+            RainUnit::Inches => round(mm / 25.4, 2),
+        }
+    }
+
+    pub fn format_rain(&self, mm: f32) -> String {
+        self.rain(mm).to_string()
+    }
+
+    /// A forecast rain range e.g. 1-5mm, or 0mm if there's no upper limit
+    pub fn format_rain_range(&self, min: u16, max: Option<u16>) -> String {
+        let symbol = self.rain_symbol();
+        match max {
+            Some(max) => format!(
+                "{}-{}{symbol}",
+                self.format_rain(min as f32),
+                self.format_rain(max as f32)
+            ),
+            None => format!("0{symbol}"),
+        }
+    }
+
+    pub fn temp_symbol(&self) -> &'static str {
+        match self.temperature {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    pub fn wind_symbol(&self) -> &'static str {
+        match self.wind {
+            WindUnit::Kmh => "km/h",
+            WindUnit::Knots => "kn",
+            WindUnit::Ms => "m/s",
+            WindUnit::Mph => "mph",
+            WindUnit::Beaufort => "Bft",
+        }
+    }
+
+    pub fn rain_symbol(&self) -> &'static str {
+        match self.rain {
+            RainUnit::Mm => "mm",
+            RainUnit::Inches => "in",
+        }
+    }
+}
+
+fn round(value: f32, decimals: i32) -> f32 {
+    let factor = 10f32.powi(decimals);
+    (value * factor).round() / factor
+}
@@ -1,13 +1,20 @@
 use anyhow::Result;
-use chrono::Duration;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
 use std::{fs, path::Path};
 use tracing::info;
 
-pub fn format_duration(duration: Duration) -> String {
+fn hours_minutes_seconds(duration: Duration) -> (i64, i64, i64) {
     let total_seconds = duration.num_seconds();
-    let minutes = total_seconds / 60 % 60;
-    let hours = total_seconds / 60 / 60;
-    let seconds = total_seconds % 60;
+    (
+        total_seconds / 60 / 60,
+        total_seconds / 60 % 60,
+        total_seconds % 60,
+    )
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let (hours, minutes, seconds) = hours_minutes_seconds(duration);
     if hours > 0 {
         format!("{}h{}m{:02}s", hours, minutes, seconds)
     } else if minutes > 0 {
@@ -17,6 +24,16 @@ pub fn format_duration(duration: Duration) -> String {
     }
 }
 
+/// Like format_duration without the seconds e.g. 2h05m or 45m. Negative durations are 0m
+pub fn format_hours_minutes(duration: Duration) -> String {
+    let (hours, minutes, _) = hours_minutes_seconds(duration.max(Duration::zero()));
+    if hours > 0 {
+        format!("{hours}h{minutes:02}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
 pub fn remove_if_exists(path: &Path) -> Result<()> {
     if path.exists() {
         info!("Deleting {}", path.display());
@@ -24,3 +41,53 @@ pub fn remove_if_exists(path: &Path) -> Result<()> {
     }
     Ok(())
 }
+
+/// The date of the time in the timezone
+pub fn local_date(time: DateTime<Utc>, tz: Tz) -> NaiveDate {
+    time.with_timezone(&tz).date_naive()
+}
+
+/// The first moment of the date in the timezone
+pub fn start_of_day(date: NaiveDate, tz: Tz) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN)
+        .and_local_timezone(tz)
+        .earliest()
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// The nearest of the 8 compass points to a bearing in degrees e.g. 50 is NE
+pub fn compass_point(bearing: f64) -> &'static str {
+    match bearing.rem_euclid(360.0) {
+        x if x < 22.5 => "N",
+        x if x < 67.5 => "NE",
+        x if x < 112.5 => "E",
+        x if x < 157.5 => "SE",
+        x if x < 202.5 => "S",
+        x if x < 247.5 => "SW",
+        x if x < 292.5 => "W",
+        x if x < 337.5 => "NW",
+        _ => "N",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compass_point_boundaries() {
+        assert_eq!(compass_point(0.0), "N");
+        assert_eq!(compass_point(22.4), "N");
+        assert_eq!(compass_point(22.5), "NE");
+        assert_eq!(compass_point(90.0), "E");
+        assert_eq!(compass_point(337.4), "NW");
+        assert_eq!(compass_point(337.5), "N");
+        assert_eq!(compass_point(360.0), "N");
+        assert_eq!(compass_point(-22.4), "N");
+        assert_eq!(compass_point(-22.6), "NW");
+        assert_eq!(compass_point(-90.0), "W");
+        assert_eq!(compass_point(-337.5), "NE");
+        assert_eq!(compass_point(725.0), "N");
+    }
+}
@@ -0,0 +1,208 @@
+use crate::daily::DailyForecast;
+use crate::hourly::HourlyForecast;
+use crate::observation::Observation;
+use crate::summary::ClimateSummary;
+use crate::units::Units;
+use crate::util::local_date;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The minimum amount of rain in mm that the BOM considers to be "any rain"
+pub const RAIN_THRESHOLD: f32 = 0.2;
+/// Start of each lead time group for the hourly forecast in hours
+const HOURLY_LEAD_GROUPS: [i64; 6] = [0, 6, 12, 24, 48, 72];
+
+/// Accumulated differences between forecast and observed values
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ErrorStats {
+    pub count: u32,
+    /// Mean of forecast minus observed. Positive means the forecast was too high
+    pub bias: f32,
+    pub mean_absolute_error: f32,
+    #[serde(skip)]
+    total: f32,
+    #[serde(skip)]
+    total_absolute: f32,
+}
+
+impl ErrorStats {
+    pub fn add(&mut self, forecast: f32, observed: f32) {
+        let error = forecast - observed;
+        self.count += 1;
+        self.total += error;
+        self.total_absolute += error.abs();
+        self.bias = self.total / self.count as f32;
+        self.mean_absolute_error = self.total_absolute / self.count as f32;
+    }
+}
+
+/// How often it rained on days given a chance of rain within the bucket
+#[derive(Clone, Debug, Serialize)]
+pub struct RainChanceBucket {
+    pub min_chance: u8,
+    pub max_chance: u8,
+    pub count: u32,
+    pub mean_chance: f32,
+    /// Percentage of days with at least RAIN_THRESHOLD mm of rain
+    pub observed_frequency: f32,
+    #[serde(skip)]
+    chance_total: u32,
+    #[serde(skip)]
+    rain_days: u32,
+}
+
+impl RainChanceBucket {
+    fn new(min_chance: u8, max_chance: u8) -> Self {
+        Self {
+            min_chance,
+            max_chance,
+            count: 0,
+            mean_chance: 0.0,
+            observed_frequency: 0.0,
+            chance_total: 0,
+            rain_days: 0,
+        }
+    }
+
+    fn add(&mut self, chance: u8, rained: bool) {
+        self.count += 1;
+        self.chance_total += chance as u32;
+        if rained {
+            self.rain_days += 1;
+        }
+        self.mean_chance = self.chance_total as f32 / self.count as f32;
+        self.observed_frequency = self.rain_days as f32 / self.count as f32 * 100.0;
+    }
+}
+
+/// Forecast accuracy over a period, calculated from archived forecasts and observations
+#[derive(Debug, Serialize)]
+pub struct Verification {
+    pub location_id: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// Keyed by the amount of days between the issue date and forecast date
+    pub daily_max: BTreeMap<i64, ErrorStats>,
+    pub daily_min: BTreeMap<i64, ErrorStats>,
+    /// Keyed by the start of the lead time group in hours
+    pub hourly_temp: BTreeMap<i64, ErrorStats>,
+    pub rain_chance: Vec<RainChanceBucket>,
+    #[serde(skip)]
+    tz: Tz,
+}
+
+impl Verification {
+    pub fn new(location_id: &str, start: NaiveDate, end: NaiveDate, tz: Tz) -> Self {
+        let mut rain_chance: Vec<RainChanceBucket> = (0..10)
+            .map(|i| RainChanceBucket::new(i * 10, i * 10 + 9))
+            .collect();
+        rain_chance.last_mut().unwrap().max_chance = 100;
+        Self {
+            location_id: location_id.to_string(),
+            start,
+            end,
+            daily_max: BTreeMap::new(),
+            daily_min: BTreeMap::new(),
+            hourly_temp: BTreeMap::new(),
+            rain_chance,
+            tz,
+        }
+    }
+
+    fn in_period(&self, date: NaiveDate) -> bool {
+        date >= self.start && date <= self.end
+    }
+
+    /// Score each day of the forecast that has a summary of the observations
+    pub fn add_daily(
+        &mut self,
+        forecast: &DailyForecast,
+        summaries: &HashMap<NaiveDate, &ClimateSummary>,
+    ) {
+        let issue_date = local_date(forecast.issue_time, self.tz);
+        for day in &forecast.days {
+            let date = local_date(day.date, self.tz);
+            if !self.in_period(date) {
+                continue;
+            }
+            let Some(summary) = summaries.get(&date) else {
+                continue;
+            };
+            let lead = (date - issue_date).num_days();
+            if let (Some(forecast_max), Some(observed_max)) = (day.temp_max, summary.max_temp) {
+                self.daily_max
+                    .entry(lead)
+                    .or_default()
+                    .add(forecast_max, observed_max);
+            }
+            if let (Some(forecast_min), Some(observed_min)) = (day.temp_min, summary.min_temp) {
+                self.daily_min
+                    .entry(lead)
+                    .or_default()
+                    .add(forecast_min, observed_min);
+            }
+            if let (Some(chance), Some(rain)) = (day.rain.chance, summary.rain) {
+                let idx = (chance as usize / 10).min(self.rain_chance.len() - 1);
+                self.rain_chance[idx].add(chance, rain >= RAIN_THRESHOLD);
+            }
+        }
+    }
+
+    /// Score each hour of the forecast against the observation closest to it.
+    /// Observations must be sorted from oldest to newest
+    pub fn add_hourly(&mut self, forecast: &HourlyForecast, observations: &[Observation]) {
+        for hour in &forecast.data {
+            if !self.in_period(local_date(hour.time, self.tz)) {
+                continue;
+            }
+            let Some(observed) = closest_observation(observations, hour.time) else {
+                continue;
+            };
+            let lead = (hour.time - forecast.issue_time).num_hours();
+            let Some(group) = HOURLY_LEAD_GROUPS.iter().rev().find(|&&g| lead >= g) else {
+                continue;
+            };
+            self.hourly_temp
+                .entry(*group)
+                .or_default()
+                .add(hour.temp, observed.temp);
+        }
+    }
+
+    /// Convert the temperature errors from °C to the units
+    pub fn convert_units(&mut self, units: &Units) {
+        let stats = self
+            .daily_max
+            .values_mut()
+            .chain(self.daily_min.values_mut())
+            .chain(self.hourly_temp.values_mut());
+        for stats in stats {
+            stats.bias = units.temp_difference(stats.bias);
+            stats.mean_absolute_error = units.temp_difference(stats.mean_absolute_error);
+        }
+    }
+
+    /// A label for the hourly lead time group e.g. 6-11h
+    pub fn hourly_lead_label(group: i64) -> String {
+        match HOURLY_LEAD_GROUPS.iter().position(|&g| g == group) {
+            Some(idx) if idx + 1 < HOURLY_LEAD_GROUPS.len() => {
+                format!("{group}-{}h", HOURLY_LEAD_GROUPS[idx + 1] - 1)
+            }
+            _ => format!("{group}h+"),
+        }
+    }
+}
+
+/// The observation within 10 minutes of the time, if any
+fn closest_observation(observations: &[Observation], time: DateTime<Utc>) -> Option<&Observation> {
+    let idx = observations.partition_point(|o| o.observation_time < time);
+    let before = idx.checked_sub(1).and_then(|i| observations.get(i));
+    let after = observations.get(idx);
+    [before, after]
+        .into_iter()
+        .flatten()
+        .filter(|o| (o.observation_time - time).abs() <= Duration::minutes(10))
+        .min_by_key(|o| (o.observation_time - time).abs())
+}
@@ -0,0 +1,124 @@
+use crate::fstring::{Fstring, FstringContext, ParseKey};
+use crate::weather::{DailyKey, HourlyKey};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A table column with a header and a format string that's rendered for each row
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Column {
+    pub header: String,
+    pub template: String,
+}
+
+impl Column {
+    pub fn new(header: &str, template: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            template: template.to_string(),
+        }
+    }
+}
+
+/// Named sets of columns for the hourly and daily tables, selected with --view
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TableViews {
+    pub hourly: BTreeMap<String, Vec<Column>>,
+    pub daily: BTreeMap<String, Vec<Column>>,
+}
+
+impl Default for TableViews {
+    fn default() -> Self {
+        let hourly = BTreeMap::from([
+            (
+                "rain".to_string(),
+                vec![
+                    Column::new("Time", "{day} {time}"),
+                    Column::new("Desc", "{icon} {description}"),
+                    Column::new("Chance", "{rain_chance}%"),
+                    Column::new("10%", "{rain_10_percent}"),
+                    Column::new("25%", "{rain_25_percent}"),
+                    Column::new("50%", "{rain_50_percent}"),
+                ],
+            ),
+            (
+                "wind".to_string(),
+                vec![
+                    Column::new("Time", "{day} {time}"),
+                    Column::new("Wind", "{wind_speed} {wind_direction}"),
+                    Column::new("Gust", "{wind_gust}"),
+                    Column::new("Knots", "{wind_speed_knot} ({wind_gust_knot})"),
+                    Column::new("UV", "{uv}"),
+                ],
+            ),
+        ]);
+        let daily = BTreeMap::from([(
+            "rain".to_string(),
+            vec![
+                Column::new("Day", "{day} {date}"),
+                Column::new("Chance", "{rain_chance|0}%"),
+                Column::new("25%", "{rain_25_percent|}"),
+                Column::new("50%", "{rain_50_percent|}"),
+                Column::new("75%", "{rain_75_percent|}"),
+                Column::new("Description", "{short_text|}"),
+            ],
+        )]);
+        Self { hourly, daily }
+    }
+}
+
+/// A view with its templates parsed, ready to render rows
+pub struct View<K> {
+    columns: Vec<(String, Fstring<K>)>,
+}
+
+impl<K: ParseKey> View<K> {
+    fn new(views: &BTreeMap<String, Vec<Column>>, name: &str) -> Result<Self> {
+        let Some(columns) = views.get(name) else {
+            let available: Vec<&str> = views.keys().map(|k| k.as_str()).collect();
+            return Err(anyhow!(
+                "No view named {name}. Available views: {}",
+                available.join(", ")
+            ));
+        };
+        Self::from_columns(columns)
+    }
+
+    pub fn from_columns(columns: &[Column]) -> Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|column| {
+                let template = Fstring::parse(&column.template).with_context(|| {
+                    format!("Invalid template for the {} column", column.header)
+                })?;
+                Ok((column.header.clone(), template))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { columns })
+    }
+
+    pub fn header(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .map(|(header, _)| header.as_str())
+            .collect()
+    }
+
+    pub fn row<C: FstringContext<Key = K>>(&self, context: &C) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|(_, template)| template.render(context))
+            .collect()
+    }
+}
+
+impl TableViews {
+    pub fn hourly(&self, name: &str) -> Result<View<HourlyKey>> {
+        View::new(&self.hourly, name)
+    }
+
+    pub fn daily(&self, name: &str) -> Result<View<DailyKey>> {
+        View::new(&self.daily, name)
+    }
+}
@@ -1,15 +1,21 @@
+use crate::changes::ForecastChange;
 use crate::client::Client;
-use crate::daily::DailyForecast;
-use crate::descriptor::IconDescriptor;
-use crate::hourly::HourlyForecast;
+use crate::daily::{DailyForecast, DailyForecastData};
+use crate::descriptor::{IconDescriptor, IconOptions};
+use crate::fstring::{Fstring, FstringContext, FstringValue, Key};
+use crate::history::Retention;
+use crate::hourly::{HourlyForecast, HourlyForecastData};
+use crate::nowcast::{Motion, Nowcast, RainTrend};
 use crate::observation::Observation;
-use crate::util::format_duration;
+use crate::units::Units;
+use crate::util::{compass_point, format_duration, format_hours_minutes};
 use crate::warning::Warning;
-use anyhow::{anyhow, Result};
-use chrono::{DateTime, Duration, Local, NaiveTime, Utc};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use serde_with::DurationSeconds;
-use std::{collections::VecDeque, str::FromStr};
+use std::{cmp::Reverse, collections::VecDeque};
 use strum_macros::{AsRefStr, EnumIter, EnumString};
 use tracing::debug;
 
@@ -24,6 +30,11 @@ pub struct Weather {
     pub next_daily_due: DateTime<Utc>,
     pub next_hourly_due: DateTime<Utc>,
     pub next_warning_due: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub next_past_observations_due: DateTime<Utc>,
+    /// Differences between the current daily forecast and the one it replaced
+    #[serde(default)]
+    pub daily_changes: Vec<ForecastChange>,
     pub opts: WeatherOptions,
 }
 
@@ -31,6 +42,12 @@ pub struct Weather {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WeatherOptions {
     pub past_observation_amount: usize,
+    /// How long to keep observations in the database's archive
+    #[serde(default)]
+    pub observation_retention: Retention,
+    /// How long to keep every issued forecast for verification
+    #[serde(default = "default_forecast_retention")]
+    pub forecast_retention: Retention,
     pub check_observations: bool,
     /// A delay to account for lag between issue time and appearance in API
     #[serde_as(as = "DurationSeconds<i64>")]
@@ -41,6 +58,10 @@ pub struct WeatherOptions {
     pub observation_overdue_delay: Duration,
     #[serde_as(as = "DurationSeconds<i64>")]
     pub observation_missing_delay: Duration,
+    /// How often to fetch the station's 72-hour product to fill gaps and add extra fields
+    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde(default = "default_past_observation_update_frequency")]
+    pub past_observation_update_frequency: Duration,
     #[serde_as(as = "DurationSeconds<i64>")]
     pub hourly_update_frequency: Duration,
     #[serde_as(as = "DurationSeconds<i64>")]
@@ -54,27 +75,58 @@ pub struct WeatherOptions {
     pub use_daily_next_issue_time: bool,
     #[serde_as(as = "DurationSeconds<i64>")]
     pub warning_update_frequency: Duration,
+    /// Send a desktop notification when a new daily forecast revises a day significantly
+    #[serde(default)]
+    pub notify_forecast_changes: bool,
+    /// The change in min or max temperature (°C) considered significant
+    #[serde(default = "default_notify_temp_change")]
+    pub notify_temp_change: f32,
+    /// The change in rain chance (percentage points) considered significant
+    #[serde(default = "default_notify_rain_chance_change")]
+    pub notify_rain_chance_change: u8,
 }
 
 impl Default for WeatherOptions {
     fn default() -> Self {
         Self {
             past_observation_amount: 6 * 24 * 2,
+            observation_retention: Retention::default(),
+            forecast_retention: default_forecast_retention(),
             check_observations: true,
             update_delay: Duration::minutes(2),
             observation_update_frequency: Duration::minutes(10),
             observation_overdue_delay: Duration::minutes(2),
             observation_missing_delay: Duration::hours(1),
+            past_observation_update_frequency: default_past_observation_update_frequency(),
             hourly_update_frequency: Duration::hours(3),
             hourly_overdue_delay: Duration::hours(1),
             daily_update_frequency: Duration::hours(1),
             use_daily_next_issue_time: false,
             daily_overdue_delay: Duration::minutes(30),
             warning_update_frequency: Duration::minutes(30),
+            notify_forecast_changes: false,
+            notify_temp_change: default_notify_temp_change(),
+            notify_rain_chance_change: default_notify_rain_chance_change(),
         }
     }
 }
 
+fn default_past_observation_update_frequency() -> Duration {
+    Duration::hours(3)
+}
+
+fn default_forecast_retention() -> Retention {
+    Retention::Months(3)
+}
+
+fn default_notify_temp_change() -> f32 {
+    3.0
+}
+
+fn default_notify_rain_chance_change() -> u8 {
+    30
+}
+
 impl Weather {
     pub fn observation(&self) -> Option<&Observation> {
         self.observations.front()
@@ -121,7 +173,7 @@ impl Weather {
         Ok((was_updated, *next_check))
     }
 
-    pub fn update_observation(&mut self, now: DateTime<Utc>, observation: Observation) {
+    pub fn update_observation(&mut self, now: DateTime<Utc>, mut observation: Observation) {
         if let Some(last) = self.observation() {
             if observation.issue_time == last.issue_time {
                 debug!(
@@ -146,12 +198,46 @@ impl Weather {
             &self.geohash,
             format_duration(self.next_observation_due - now)
         );
+        if let Some(idx) = self
+            .observations
+            .iter()
+            .position(|o| o.observation_time == observation.observation_time)
+        {
+            // Replace one previously added from the 72-hour product
+            let past = self.observations.remove(idx).unwrap();
+            observation.merge_extra(&past);
+        }
         self.observations.push_front(observation);
         if self.observations.len() > self.opts.past_observation_amount {
             self.observations.pop_back();
         }
     }
 
+    /// Merge observations from the station's 72-hour product. Extra fields are added to existing
+    /// observations with the same observation time, and any others fill the gaps between them.
+    /// Returns the amount of observations added
+    pub fn merge_past_observations(&mut self, past: Vec<Observation>) -> usize {
+        let mut added = 0;
+        for past_obs in past {
+            if let Some(existing) = self
+                .observations
+                .iter_mut()
+                .find(|o| o.observation_time == past_obs.observation_time)
+            {
+                existing.merge_extra(&past_obs);
+            } else {
+                self.observations.push_back(past_obs);
+                added += 1;
+            }
+        }
+        self.observations
+            .make_contiguous()
+            .sort_by_key(|o| Reverse(o.observation_time));
+        self.observations
+            .truncate(self.opts.past_observation_amount);
+        added
+    }
+
     pub fn update_hourly(&mut self, now: DateTime<Utc>, hourly: HourlyForecast) {
         let last = &self.hourly_forecast;
         if hourly.issue_time == last.issue_time {
@@ -177,8 +263,14 @@ impl Weather {
     }
 
     pub fn update_daily(&mut self, now: DateTime<Utc>, new_daily: DailyForecast) {
-        let last = &self.daily_forecast;
+        let last = &mut self.daily_forecast;
         if new_daily.issue_time == last.issue_time {
+            // The now section switches between day and night without a new issue
+            for new_day in new_daily.days {
+                if let Some(day) = last.days.iter_mut().find(|d| d.date == new_day.date) {
+                    day.now = new_day.now;
+                }
+            }
             self.next_daily_due = if self.opts.use_daily_next_issue_time {
                 debug!(
                     "{} daily forecast overdue. Next check in {}",
@@ -211,10 +303,18 @@ impl Weather {
             &self.geohash,
             format_duration(self.next_daily_due - now)
         );
+        self.daily_changes = ForecastChange::diff(&self.daily_forecast, &new_daily);
         self.daily_forecast = new_daily;
     }
 
-    pub fn current(&self) -> CurrentWeather {
+    /// The current conditions with days determined in the location's timezone.
+    /// Values are converted to the units when the format string is processed
+    pub fn current<'a>(
+        &'a self,
+        tz: Tz,
+        units: Units,
+        icons: &'a IconOptions,
+    ) -> CurrentWeather<'a> {
         let now = Utc::now();
         let observation = self.observation();
         let hourly = self
@@ -223,16 +323,42 @@ impl Weather {
             .iter()
             .find(|h| now > h.time)
             .unwrap();
-        let mut days = self.daily_forecast.days.iter();
+        // The first day of the forecast is yesterday until a new one is issued after midnight
+        let today_date = now.with_timezone(&tz).date_naive();
+        // A forecast that hasn't been updated for days may not reach tomorrow, so fall back to
+        // its last two days
+        let days = &self.daily_forecast.days;
+        let first = days
+            .iter()
+            .position(|d| d.date.with_timezone(&tz).date_naive() >= today_date)
+            .unwrap_or(0)
+            .min(days.len().saturating_sub(2));
+        let remaining_days = &days[first..];
+        let mut days = remaining_days.iter();
+        let first_hour = self
+            .hourly_forecast
+            .data
+            .iter()
+            .position(|h| h.next_forecast_period > now)
+            .unwrap_or(0);
         let today = days.next().unwrap();
         let tomorrow = days.next().unwrap();
+        let next_sunrise = if now < today.astronomical.sunrise_time {
+            today.astronomical.sunrise_time
+        } else {
+            tomorrow.astronomical.sunrise_time
+        };
+        let next_sunset = if now < today.astronomical.sunset_time {
+            today.astronomical.sunset_time
+        } else {
+            tomorrow.astronomical.sunset_time
+        };
         // temp_max should only ever be None on the last day of the forecast
-        // provide an obviously wrong value rather than crashing if it is None
-        let today_max = today.temp_max.unwrap_or(-9999.0);
-        let overnight_min = tomorrow.temp_min.unwrap_or(-9999.0);
-        let tomorrow_max = tomorrow.temp_max.unwrap_or(-9999.0);
+        let today_max = today.temp_max;
+        let overnight_min = tomorrow.temp_min;
+        let tomorrow_max = tomorrow.temp_max;
 
-        let (temp, temp_feels_like, max_temp, wind_speed, wind_direction, gust) =
+        let (temp, temp_feels_like, max_temp, wind_speed, wind_direction, gust, knots) =
             if let Some(obs) = observation {
                 let wind_direction = if let Some(dir) = &obs.wind.direction {
                     dir
@@ -242,10 +368,11 @@ impl Weather {
                 (
                     obs.temp,
                     obs.temp_feels_like,
-                    f32::max(obs.max_temp.value, today_max),
+                    Some(today_max.map_or(obs.max_temp.value, |t| t.max(obs.max_temp.value))),
                     obs.wind.speed_kilometre,
                     wind_direction,
                     obs.gust.speed_kilometre,
+                    (obs.wind.speed_knot, obs.gust.speed_knot),
                 )
             } else {
                 (
@@ -255,13 +382,31 @@ impl Weather {
                     hourly.wind.speed_kilometre,
                     &hourly.wind.direction,
                     hourly.wind.gust_speed_kilometre,
+                    (hourly.wind.speed_knot, hourly.wind.gust_speed_knot),
                 )
             };
 
-        let current_time = now.with_timezone(&Local).time();
-        let start = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
-        let end = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
-        let next_is_max = current_time > start && current_time < end;
+        // The 72-hour product is only updated every half hour, so use the latest
+        // observation that has the extra fields
+        let recent_observations = self
+            .observations
+            .iter()
+            .take_while(|o| now - o.observation_time < Duration::hours(1));
+        let extra = recent_observations.clone().find(|o| o.has_extra());
+
+        // The API switches from the max to the overnight min in the afternoon. Fall back to a
+        // fixed window in the location's timezone if it's missing or today isn't in the forecast
+        let next_is_max = match &today.now {
+            Some(today_now) if today.date.with_timezone(&tz).date_naive() == today_date => {
+                !today_now.is_night
+            }
+            _ => {
+                let current_time = now.with_timezone(&tz).time();
+                let start = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+                let end = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+                current_time > start && current_time < end
+            }
+        };
 
         let (next_temp, next_label, later_temp, later_label) = if next_is_max {
             (max_temp, "Max", overnight_min, "Overnight min")
@@ -283,20 +428,52 @@ impl Weather {
             extended_text: &today.extended_text,
             short_text: &today.short_text,
             humidity: observation.as_ref().map(|obs| obs.humidity),
+            pressure: extra.and_then(|obs| obs.pressure),
+            dew_point: extra.and_then(|obs| obs.dew_point),
+            cloud: extra.and_then(|obs| obs.cloud.as_deref()),
+            visibility: extra.and_then(|obs| obs.visibility),
+            rain_ten: recent_observations.clone().find_map(|obs| obs.rain_ten),
+            yesterday_rain: None,
+            month_rain: None,
+            nowcast: None,
             hourly_rain_chance: hourly.rain.chance,
             hourly_rain_min: hourly.rain.amount.min,
             hourly_rain_max: hourly.rain.amount.max.unwrap_or(0),
-            today_rain_chance: today.rain.chance.unwrap_or(0),
-            today_rain_min: today.rain.amount.min.unwrap_or(0),
-            today_rain_max: today.rain.amount.max.unwrap_or(0),
+            today_rain_chance: today.rain.chance,
+            today_rain_min: today.rain.amount.min,
+            today_rain_max: today.rain.amount.max,
             wind_speed,
+            wind_speed_knot: knots.0,
             wind_direction,
             gust,
+            gust_knot: knots.1,
             relative_humidity: hourly.relative_humidity,
             uv: hourly.uv,
-            icon: hourly.icon_descriptor.get_icon_emoji(hourly.is_night),
+            icon: icons.icon(&hourly.icon_descriptor, hourly.is_night),
             icon_descriptor: &hourly.icon_descriptor,
             is_night: hourly.is_night,
+            sunrise: today.astronomical.sunrise_time.with_timezone(&tz),
+            sunset: today.astronomical.sunset_time.with_timezone(&tz),
+            time_to_sunrise: next_sunrise - now,
+            time_to_sunset: next_sunset - now,
+            uv_category: today.uv.category.as_deref(),
+            uv_max_index: today.uv.max_index,
+            sun_protection_start: today.uv.start_time.map(|t| t.with_timezone(&tz)),
+            sun_protection_end: today.uv.end_time.map(|t| t.with_timezone(&tz)),
+            fire_danger: today
+                .fire_danger
+                .as_deref()
+                .or(today.fire_danger_category.text.as_deref()),
+            surf_danger: today.surf_danger.as_deref(),
+            units,
+            hours: &self.hourly_forecast.data[first_hour..],
+            days: remaining_days,
+            tz,
+            warnings: &self.warnings,
+            icons,
+            observation_time: observation.map(|obs| obs.observation_time.with_timezone(&tz)),
+            hourly_issue_time: self.hourly_forecast.issue_time.with_timezone(&tz),
+            daily_issue_time: self.daily_forecast.issue_time.with_timezone(&tz),
         }
     }
 }
@@ -304,21 +481,31 @@ impl Weather {
 pub struct CurrentWeather<'a> {
     pub temp: f32,
     pub temp_feels_like: f32,
-    pub max_temp: f32,
-    pub next_temp: f32,
-    pub later_temp: f32,
+    pub max_temp: Option<f32>,
+    pub next_temp: Option<f32>,
+    pub later_temp: Option<f32>,
     pub next_label: &'a str,
     pub later_label: &'a str,
-    pub overnight_min: f32,
-    pub tomorrow_max: f32,
+    pub overnight_min: Option<f32>,
+    pub tomorrow_max: Option<f32>,
     pub rain_since_9am: Option<f32>,
-    pub today_rain_chance: u8,
-    pub today_rain_min: u16,
-    pub today_rain_max: u16,
+    pub today_rain_chance: Option<u8>,
+    pub today_rain_min: Option<u16>,
+    pub today_rain_max: Option<u16>,
     pub hourly_rain_chance: u8,
     pub hourly_rain_min: u16,
     pub hourly_rain_max: u16,
     pub humidity: Option<u8>,
+    pub pressure: Option<f64>,
+    pub dew_point: Option<f32>,
+    pub cloud: Option<&'a str>,
+    pub visibility: Option<f32>,
+    pub rain_ten: Option<f32>,
+    /// Calculated from the daily summaries in the database
+    pub yesterday_rain: Option<f32>,
+    pub month_rain: Option<f32>,
+    /// Calculated from the radar images in the database
+    pub nowcast: Option<Nowcast>,
     pub relative_humidity: u8,
     pub uv: u8,
     pub icon: &'a str,
@@ -327,42 +514,162 @@ pub struct CurrentWeather<'a> {
     pub icon_descriptor: &'a IconDescriptor,
     pub is_night: bool,
     pub wind_speed: u8,
+    pub wind_speed_knot: u8,
     pub wind_direction: &'a str,
     pub gust: u8,
+    pub gust_knot: u8,
+    pub sunrise: DateTime<Tz>,
+    pub sunset: DateTime<Tz>,
+    /// Until the next sunrise or sunset, which may be tomorrow's
+    pub time_to_sunrise: Duration,
+    pub time_to_sunset: Duration,
+    pub uv_category: Option<&'a str>,
+    pub uv_max_index: Option<u8>,
+    /// When sun protection is recommended (UV index of 3 or more)
+    pub sun_protection_start: Option<DateTime<Tz>>,
+    pub sun_protection_end: Option<DateTime<Tz>>,
+    pub fire_danger: Option<&'a str>,
+    pub surf_danger: Option<&'a str>,
+    pub units: Units,
+    /// The hourly forecast starting from the current hour
+    pub hours: &'a [HourlyForecastData],
+    /// The daily forecast starting from today
+    pub days: &'a [DailyForecastData],
+    pub tz: Tz,
+    pub warnings: &'a [Warning],
+    pub icons: &'a IconOptions,
+    pub observation_time: Option<DateTime<Tz>>,
+    pub hourly_issue_time: DateTime<Tz>,
+    pub daily_issue_time: DateTime<Tz>,
+}
+
+/// The current weather flattened for CSV and JSON output, with values converted to the units
+#[derive(Debug, Serialize)]
+pub struct CurrentRow<'a> {
+    pub location_id: &'a str,
+    pub observation_time: Option<DateTime<Tz>>,
+    pub hourly_issue_time: DateTime<Tz>,
+    pub daily_issue_time: DateTime<Tz>,
+    pub temp: f32,
+    pub temp_feels_like: f32,
+    pub max_temp: Option<f32>,
+    pub overnight_min: Option<f32>,
+    pub tomorrow_max: Option<f32>,
+    pub next_label: &'a str,
+    pub next_temp: Option<f32>,
+    pub later_label: &'a str,
+    pub later_temp: Option<f32>,
+    pub icon_descriptor: String,
+    pub icon: &'a str,
+    pub is_night: bool,
+    pub short_text: Option<&'a str>,
+    pub humidity: Option<u8>,
+    pub wind_direction: &'a str,
+    pub wind_speed: f32,
+    pub gust_speed: f32,
+    pub rain_since_9am: Option<f32>,
+    pub yesterday_rain: Option<f32>,
+    pub month_rain: Option<f32>,
+    pub hourly_rain_chance: u8,
+    pub today_rain_chance: Option<u8>,
+    pub today_rain_min: Option<f32>,
+    pub today_rain_max: Option<f32>,
+    pub uv: u8,
+    pub uv_category: Option<&'a str>,
+    pub uv_max_index: Option<u8>,
+    pub sunrise: DateTime<Tz>,
+    pub sunset: DateTime<Tz>,
+    pub fire_danger: Option<&'a str>,
+    /// Titles of the active warnings separated by semicolons
+    pub warnings: String,
+    pub radar_rain_rate: Option<f32>,
+    pub radar_rain_trend: Option<RainTrend>,
+    pub rain_eta: Option<i64>,
+    pub rain_motion_speed: Option<f32>,
+    pub rain_motion_direction: Option<&'static str>,
+}
+
+impl<'a> CurrentRow<'a> {
+    pub fn new(location_id: &'a str, w: &CurrentWeather<'a>) -> Self {
+        let u = &w.units;
+        Self {
+            location_id,
+            observation_time: w.observation_time,
+            hourly_issue_time: w.hourly_issue_time,
+            daily_issue_time: w.daily_issue_time,
+            temp: u.temp(w.temp),
+            temp_feels_like: u.temp(w.temp_feels_like),
+            max_temp: w.max_temp.map(|t| u.temp(t)),
+            overnight_min: w.overnight_min.map(|t| u.temp(t)),
+            tomorrow_max: w.tomorrow_max.map(|t| u.temp(t)),
+            next_label: w.next_label,
+            next_temp: w.next_temp.map(|t| u.temp(t)),
+            later_label: w.later_label,
+            later_temp: w.later_temp.map(|t| u.temp(t)),
+            icon_descriptor: w.icon_descriptor.name(),
+            icon: w.icon,
+            is_night: w.is_night,
+            short_text: w.short_text.as_deref(),
+            humidity: w.humidity,
+            wind_direction: w.wind_direction,
+            wind_speed: u.wind(w.wind_speed, w.wind_speed_knot),
+            gust_speed: u.wind(w.gust, w.gust_knot),
+            rain_since_9am: w.rain_since_9am.map(|r| u.rain(r)),
+            yesterday_rain: w.yesterday_rain.map(|r| u.rain(r)),
+            month_rain: w.month_rain.map(|r| u.rain(r)),
+            hourly_rain_chance: w.hourly_rain_chance,
+            today_rain_chance: w.today_rain_chance,
+            today_rain_min: w.today_rain_min.map(|r| u.rain(r as f32)),
+            today_rain_max: w.today_rain_max.map(|r| u.rain(r as f32)),
+            uv: w.uv,
+            uv_category: w.uv_category,
+            uv_max_index: w.uv_max_index,
+            sunrise: w.sunrise,
+            sunset: w.sunset,
+            fire_danger: w.fire_danger,
+            warnings: w
+                .warnings
+                .iter()
+                .map(|w| w.short_title.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
+            radar_rain_rate: w.nowcast.as_ref().map(|n| u.rain(n.rain_rate)),
+            radar_rain_trend: w.nowcast.as_ref().map(|n| n.trend),
+            rain_eta: w.rain_eta().map(|eta| eta.num_minutes()),
+            rain_motion_speed: w.rain_motion().map(|m| u.wind_kmh(m.speed_kmh as f32)),
+            rain_motion_direction: w.rain_motion().map(|m| compass_point(m.bearing)),
+        }
+    }
 }
 
 impl<'a> CurrentWeather<'a> {
     /// Process a user-provided format string e.g. "{icon} {temp} ({temp_feels_like})".
-    /// Just a basic implementation that doesn't handle mismatched curly brackets
+    /// Use Fstring directly to avoid parsing the same string repeatedly
     pub fn process_fstring(&self, fstring: &str) -> Result<String> {
-        let mut pos = 0;
-        let mut remainder = fstring;
-        let mut output = String::new();
-        while !remainder.is_empty() {
-            if let Some(next) = remainder.find('{') {
-                output.push_str(&remainder[..next]);
-                let start = next + 1;
-                let Some(end) = remainder.find('}') else {
-                    return Err(anyhow!("{fstring} is not a valid format string"));
-                };
-                let key = &remainder[start..end];
-                let Ok(fstring_key) = FstringKey::from_str(key) else {
-                    return Err(anyhow!("{} is not a valid key", key));
-                };
-                fstring_key.push_value(&mut output, self);
-                pos = pos + end + 1;
-                remainder = &fstring[pos..];
-            } else {
-                output.push_str(remainder);
-                break;
-            }
-        }
+        Ok(Fstring::<Key>::parse(fstring)?.render(self))
+    }
+
+    /// How long until rain is expected at the location, or zero if it's already raining
+    pub fn rain_eta(&self) -> Option<Duration> {
+        let arrival = self.nowcast.as_ref()?.arrival?;
+        Some((arrival - Utc::now()).max(Duration::zero()))
+    }
 
-        Ok(output)
+    pub fn rain_motion(&self) -> Option<Motion> {
+        self.nowcast.as_ref()?.motion
+    }
+
+    pub fn forecast_context<T>(&self, data: &'a T) -> ForecastContext<'a, T> {
+        ForecastContext {
+            data,
+            units: self.units,
+            tz: self.tz,
+            icons: self.icons,
+        }
     }
 }
 
-#[derive(AsRefStr, EnumString, EnumIter)]
+#[derive(Clone, Copy, Debug, AsRefStr, EnumString, EnumIter)]
 #[strum(serialize_all = "snake_case")]
 pub enum FstringKey {
     Temp,
@@ -388,41 +695,278 @@ pub enum FstringKey {
     WindSpeed,
     WindDirection,
     WindGust,
+    Pressure,
+    DewPoint,
+    Cloud,
+    Visibility,
+    RainTen,
+    YesterdayRain,
+    MonthRain,
+    RadarRaining,
+    RadarRainRate,
+    RadarRainTrend,
+    RadarTime,
+    RainEta,
+    RainEtaTime,
+    RainMotionSpeed,
+    RainMotionDirection,
+    Sunrise,
+    Sunset,
+    TimeToSunrise,
+    TimeToSunset,
+    UvCategory,
+    UvMaxIndex,
+    SunProtectionStart,
+    SunProtectionEnd,
+    FireDanger,
+    SurfDanger,
+    TempUnit,
+    WindUnit,
+    RainUnit,
 }
 
 impl FstringKey {
-    fn push_value(&self, s: &mut String, w: &CurrentWeather) {
+    pub fn value<'a>(&self, w: &'a CurrentWeather) -> FstringValue<'a> {
+        let u = &w.units;
         match self {
-            Self::Temp => s.push_str(&w.temp.to_string()),
-            Self::TempFeelsLike => s.push_str(&w.temp_feels_like.to_string()),
-            Self::Icon => s.push_str(w.icon),
-            Self::NextTemp => s.push_str(&w.next_temp.to_string()),
-            Self::NextLabel => s.push_str(w.next_label),
-            Self::LaterTemp => s.push_str(&w.later_temp.to_string()),
-            Self::LaterLabel => s.push_str(w.later_label),
-            Self::MaxTemp => s.push_str(&w.max_temp.to_string()),
-            Self::OvernightMin => s.push_str(&w.overnight_min.to_string()),
-            Self::TomorrowMax => s.push_str(&w.tomorrow_max.to_string()),
-            Self::RainSince9am => {
-                // API usually returns 0 if there hasn't been rain
-                // so take None to mean data unavailable
-                if let Some(rain) = w.rain_since_9am {
-                    s.push_str(&rain.to_string())
-                } else {
-                    s.push_str("??")
-                }
-            }
-            Self::HourlyRainChance => s.push_str(&w.hourly_rain_chance.to_string()),
-            Self::HourlyRainMin => s.push_str(&w.hourly_rain_min.to_string()),
-            Self::HourlyRainMax => s.push_str(&w.hourly_rain_max.to_string()),
-            Self::TodayRainChance => s.push_str(&w.today_rain_chance.to_string()),
-            Self::TodayRainMin => s.push_str(&w.today_rain_min.to_string()),
-            Self::TodayRainMax => s.push_str(&w.today_rain_max.to_string()),
-            Self::ShortText => s.push_str(w.short_text.as_ref().unwrap_or(&String::new())),
-            Self::ExtendedText => s.push_str(w.extended_text.as_ref().unwrap_or(&String::new())),
-            Self::WindSpeed => s.push_str(&w.wind_speed.to_string()),
-            Self::WindDirection => s.push_str(w.wind_direction),
-            Self::WindGust => s.push_str(&w.gust.to_string()),
+            Self::Temp => u.temp(w.temp).into(),
+            Self::TempFeelsLike => u.temp(w.temp_feels_like).into(),
+            Self::Icon => w.icon.into(),
+            Self::NextTemp => w.next_temp.map(|t| u.temp(t)).into(),
+            Self::NextLabel => w.next_label.into(),
+            Self::LaterTemp => w.later_temp.map(|t| u.temp(t)).into(),
+            Self::LaterLabel => w.later_label.into(),
+            Self::MaxTemp => w.max_temp.map(|t| u.temp(t)).into(),
+            Self::OvernightMin => w.overnight_min.map(|t| u.temp(t)).into(),
+            Self::TomorrowMax => w.tomorrow_max.map(|t| u.temp(t)).into(),
+            // API usually returns 0 if there hasn't been rain
+            // so take None to mean data unavailable
+            Self::RainSince9am => w.rain_since_9am.map(|r| u.rain(r)).into(),
+            Self::HourlyRainChance => w.hourly_rain_chance.into(),
+            Self::HourlyRainMin => u.rain(w.hourly_rain_min as f32).into(),
+            Self::HourlyRainMax => u.rain(w.hourly_rain_max as f32).into(),
+            Self::TodayRainChance => w.today_rain_chance.unwrap_or(0).into(),
+            Self::TodayRainMin => u.rain(w.today_rain_min.unwrap_or(0) as f32).into(),
+            Self::TodayRainMax => u.rain(w.today_rain_max.unwrap_or(0) as f32).into(),
+            Self::ShortText => w.short_text.as_deref().unwrap_or_default().into(),
+            Self::ExtendedText => w.extended_text.as_deref().unwrap_or_default().into(),
+            Self::WindSpeed => u.wind(w.wind_speed, w.wind_speed_knot).into(),
+            Self::WindDirection => w.wind_direction.into(),
+            Self::WindGust => u.wind(w.gust, w.gust_knot).into(),
+            // Fields from the 72-hour product won't be available if the station doesn't
+            // provide them. The UV, fire and surf danger are also missing for some days
+            Self::Pressure => w.pressure.into(),
+            Self::DewPoint => w.dew_point.map(|t| u.temp(t)).into(),
+            Self::Cloud => w.cloud.into(),
+            Self::Visibility => w.visibility.into(),
+            Self::RainTen => w.rain_ten.map(|r| u.rain(r)).into(),
+            Self::YesterdayRain => w.yesterday_rain.map(|r| u.rain(r)).into(),
+            Self::MonthRain => w.month_rain.map(|r| u.rain(r)).into(),
+            // Missing unless a rainfall radar covering the location has recent images
+            Self::RadarRaining => w.nowcast.as_ref().map(|n| u8::from(n.is_raining())).into(),
+            Self::RadarRainRate => w.nowcast.as_ref().map(|n| u.rain(n.rain_rate)).into(),
+            Self::RadarRainTrend => w.nowcast.as_ref().map(|n| n.trend.to_string()).into(),
+            Self::RadarTime => w
+                .nowcast
+                .as_ref()
+                .map(|n| n.time.with_timezone(&w.tz).format(TIME_FORMAT).to_string())
+                .into(),
+            Self::RainEta => w.rain_eta().map(|eta| eta.num_minutes() as u32).into(),
+            Self::RainEtaTime => w
+                .nowcast
+                .as_ref()
+                .and_then(|n| n.arrival)
+                .map(|t| t.with_timezone(&w.tz).format(TIME_FORMAT).to_string())
+                .into(),
+            Self::RainMotionSpeed => w
+                .rain_motion()
+                .map(|m| u.wind_kmh(m.speed_kmh as f32))
+                .into(),
+            Self::RainMotionDirection => w.rain_motion().map(|m| compass_point(m.bearing)).into(),
+            Self::Sunrise => w.sunrise.format(TIME_FORMAT).to_string().into(),
+            Self::Sunset => w.sunset.format(TIME_FORMAT).to_string().into(),
+            Self::TimeToSunrise => format_hours_minutes(w.time_to_sunrise).into(),
+            Self::TimeToSunset => format_hours_minutes(w.time_to_sunset).into(),
+            Self::UvCategory => w.uv_category.into(),
+            Self::UvMaxIndex => w.uv_max_index.into(),
+            Self::SunProtectionStart => w
+                .sun_protection_start
+                .map(|t| t.format(TIME_FORMAT).to_string())
+                .into(),
+            Self::SunProtectionEnd => w
+                .sun_protection_end
+                .map(|t| t.format(TIME_FORMAT).to_string())
+                .into(),
+            Self::FireDanger => w.fire_danger.into(),
+            Self::SurfDanger => w.surf_danger.into(),
+            Self::TempUnit => u.temp_symbol().into(),
+            Self::WindUnit => u.wind_symbol().into(),
+            Self::RainUnit => u.rain_symbol().into(),
         }
     }
 }
+
+/// One hour or day of the forecast along with what's needed to format its values
+pub struct ForecastContext<'a, T> {
+    pub data: &'a T,
+    pub units: Units,
+    pub tz: Tz,
+    pub icons: &'a IconOptions,
+}
+
+impl FstringContext for ForecastContext<'_, HourlyForecastData> {
+    type Key = HourlyKey;
+
+    fn value(&self, key: &HourlyKey) -> FstringValue<'_> {
+        key.value(self)
+    }
+}
+
+impl FstringContext for ForecastContext<'_, DailyForecastData> {
+    type Key = DailyKey;
+
+    fn value(&self, key: &DailyKey) -> FstringValue<'_> {
+        key.value(self)
+    }
+}
+
+/// A field of one hour of the hourly forecast e.g. hour+3.temp
+#[derive(Clone, Copy, Debug, AsRefStr, EnumString, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum HourlyKey {
+    /// Abbreviated weekday e.g. Sat
+    Day,
+    Time,
+    Temp,
+    TempFeelsLike,
+    TempUnit,
+    Icon,
+    Description,
+    RainChance,
+    RainMin,
+    RainMax,
+    /// The amounts with a 10, 25 and 50% chance of being exceeded
+    #[strum(serialize = "rain_10_percent")]
+    Rain10Percent,
+    #[strum(serialize = "rain_25_percent")]
+    Rain25Percent,
+    #[strum(serialize = "rain_50_percent")]
+    Rain50Percent,
+    WindSpeed,
+    WindSpeedKnot,
+    WindDirection,
+    WindGust,
+    WindGustKnot,
+    Humidity,
+    Uv,
+}
+
+impl HourlyKey {
+    pub fn value<'a>(&self, c: &ForecastContext<'a, HourlyForecastData>) -> FstringValue<'a> {
+        let (h, u) = (c.data, &c.units);
+        let rain = |mm: u8| u.rain(mm as f32);
+        match self {
+            Self::Day => h.time.with_timezone(&c.tz).format("%a").to_string().into(),
+            Self::Time => h
+                .time
+                .with_timezone(&c.tz)
+                .format(TIME_FORMAT)
+                .to_string()
+                .into(),
+            Self::Temp => u.temp(h.temp).into(),
+            Self::TempFeelsLike => u.temp(h.temp_feels_like).into(),
+            Self::TempUnit => u.temp_symbol().into(),
+            Self::Icon => c.icons.icon(&h.icon_descriptor, h.is_night).into(),
+            Self::Description => h.icon_descriptor.get_description(h.is_night).into(),
+            Self::RainChance => h.rain.chance.into(),
+            Self::RainMin => u.rain(h.rain.amount.min as f32).into(),
+            Self::RainMax => u.rain(h.rain.amount.max.unwrap_or(0) as f32).into(),
+            Self::Rain10Percent => rain(h.rain.precipitation_amount_10_percent_chance).into(),
+            Self::Rain25Percent => rain(h.rain.precipitation_amount_25_percent_chance).into(),
+            Self::Rain50Percent => rain(h.rain.precipitation_amount_50_percent_chance).into(),
+            Self::WindSpeed => u.wind(h.wind.speed_kilometre, h.wind.speed_knot).into(),
+            Self::WindSpeedKnot => h.wind.speed_knot.into(),
+            Self::WindDirection => h.wind.direction.as_str().into(),
+            Self::WindGust => u
+                .wind(h.wind.gust_speed_kilometre, h.wind.gust_speed_knot)
+                .into(),
+            Self::WindGustKnot => h.wind.gust_speed_knot.into(),
+            Self::Humidity => h.relative_humidity.into(),
+            Self::Uv => h.uv.into(),
+        }
+    }
+}
+
+/// A field of one day of the daily forecast e.g. day2.max
+#[derive(Clone, Copy, Debug, AsRefStr, EnumString, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum DailyKey {
+    /// Abbreviated weekday e.g. Sat
+    Day,
+    Date,
+    Min,
+    Max,
+    TempUnit,
+    Icon,
+    ShortText,
+    ExtendedText,
+    RainChance,
+    RainMin,
+    RainMax,
+    /// The amounts with a 25, 50 and 75% chance of being exceeded
+    #[strum(serialize = "rain_25_percent")]
+    Rain25Percent,
+    #[strum(serialize = "rain_50_percent")]
+    Rain50Percent,
+    #[strum(serialize = "rain_75_percent")]
+    Rain75Percent,
+    UvCategory,
+    UvMaxIndex,
+    SunProtectionStart,
+    SunProtectionEnd,
+    Sunrise,
+    Sunset,
+    FireDanger,
+}
+
+impl DailyKey {
+    pub fn value<'a>(&self, c: &ForecastContext<'a, DailyForecastData>) -> FstringValue<'a> {
+        let (d, u) = (c.data, &c.units);
+        let date = d.date.with_timezone(&c.tz);
+        let time = |t: DateTime<Utc>| t.with_timezone(&c.tz).format(TIME_FORMAT).to_string();
+        let rain = |mm: Option<u8>| mm.map(|mm| u.rain(mm as f32));
+        match self {
+            Self::Day => date.format("%a").to_string().into(),
+            Self::Date => date.format("%d %b").to_string().into(),
+            Self::Min => d.temp_min.map(|t| u.temp(t)).into(),
+            Self::Max => d.temp_max.map(|t| u.temp(t)).into(),
+            Self::TempUnit => u.temp_symbol().into(),
+            Self::Icon => d
+                .icon_descriptor
+                .as_ref()
+                .map(|i| c.icons.icon(i, false))
+                .into(),
+            Self::ShortText => d.short_text.as_deref().into(),
+            Self::ExtendedText => d.extended_text.as_deref().into(),
+            Self::RainChance => d.rain.chance.into(),
+            Self::RainMin => u.rain(d.rain.amount.lower_range.unwrap_or(0) as f32).into(),
+            Self::RainMax => u.rain(d.rain.amount.max.unwrap_or(0) as f32).into(),
+            Self::Rain25Percent => rain(d.rain.precipitation_amount_25_percent_chance).into(),
+            Self::Rain50Percent => rain(d.rain.precipitation_amount_50_percent_chance).into(),
+            Self::Rain75Percent => rain(d.rain.precipitation_amount_75_percent_chance).into(),
+            Self::UvCategory => d.uv.category.as_deref().into(),
+            Self::UvMaxIndex => d.uv.max_index.into(),
+            Self::SunProtectionStart => d.uv.start_time.map(time).into(),
+            Self::SunProtectionEnd => d.uv.end_time.map(time).into(),
+            Self::Sunrise => time(d.astronomical.sunrise_time).into(),
+            Self::Sunset => time(d.astronomical.sunset_time).into(),
+            Self::FireDanger => d
+                .fire_danger
+                .as_deref()
+                .or(d.fire_danger_category.text.as_deref())
+                .into(),
+        }
+    }
+}
+
+const TIME_FORMAT: &str = "%H:%M";